@@ -0,0 +1,358 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::errors::{AggregatorError, ServerError};
+use axum::body::Bytes;
+use crypto::elgamal::Encryption;
+use fastcrypto::groups::bls12381::G1Element;
+use fastcrypto::groups::GroupElement;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+/// Headers of the client request which are forwarded to the key servers.
+pub const FORWARDED_HEADERS: [&str; 4] = [
+    "Request-Id",
+    "Client-Sdk-Version",
+    "Client-Sdk-Type",
+    "Client-Target-Api-Version",
+];
+
+/// A response from a single key server. The body is returned as received, so the signature from the key server can
+/// be verified by the client.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerResponse {
+    pub url: String,
+    pub body: String,
+    pub signature: Option<String>,
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregatedResponse {
+    pub responses: Vec<ServerResponse>,
+}
+
+/// The fields of a fetch key response (Sui or Ethereum) that can be checked without the user's secret key.
+#[derive(Deserialize)]
+struct DecryptionKey {
+    id: Vec<u8>,
+    encrypted_key: Encryption<G1Element>,
+}
+
+#[derive(Deserialize)]
+struct FetchKeyResponse {
+    decryption_keys: Vec<DecryptionKey>,
+}
+
+/// Fans out fetch key requests to a set of key servers and collects the first `threshold` valid responses.
+pub struct Aggregator {
+    client: reqwest::Client,
+    servers: Vec<String>,
+    threshold: usize,
+    hedge_delay: Duration,
+}
+
+impl Aggregator {
+    /// Create a new aggregator.
+    ///
+    /// Requests are first sent to `threshold` servers. Another server is tried if one of them fails, or if no
+    /// response has been received within `hedge_delay`. Requests to a single server time out after `timeout`.
+    pub fn new(
+        servers: Vec<String>,
+        threshold: usize,
+        timeout: Duration,
+        hedge_delay: Duration,
+    ) -> anyhow::Result<Self> {
+        if threshold == 0 || threshold > servers.len() {
+            anyhow::bail!(
+                "Invalid threshold {} for {} key servers",
+                threshold,
+                servers.len()
+            );
+        }
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self {
+            client,
+            servers: servers
+                .into_iter()
+                .map(|url| url.trim_end_matches('/').to_string())
+                .collect(),
+            threshold,
+            hedge_delay,
+        })
+    }
+
+    /// Send a request to the given path of the key servers and return the first `threshold` valid responses.
+    pub async fn fetch_key(
+        &self,
+        path: &str,
+        headers: &[(&'static str, String)],
+        body: Bytes,
+    ) -> Result<AggregatedResponse, AggregatorError> {
+        let mut remaining = self.servers.iter();
+        let mut tasks = JoinSet::new();
+        for url in remaining.by_ref().take(self.threshold) {
+            self.spawn_request(&mut tasks, url, path, headers, &body);
+        }
+
+        let mut responses = vec![];
+        let mut errors = vec![];
+        while responses.len() < self.threshold {
+            tokio::select! {
+                result = tasks.join_next() => match result {
+                    Some(Ok(Ok(response))) => responses.push(response),
+                    Some(Ok(Err(error))) => {
+                        warn!("Request to {} failed: {}", error.url, error.error);
+                        errors.push(error);
+                        if let Some(url) = remaining.next() {
+                            self.spawn_request(&mut tasks, url, path, headers, &body);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        warn!("Request task failed: {}", e);
+                        if let Some(url) = remaining.next() {
+                            self.spawn_request(&mut tasks, url, path, headers, &body);
+                        }
+                    }
+                    None => break,
+                },
+                _ = sleep(self.hedge_delay), if !remaining.as_slice().is_empty() => {
+                    let url = remaining.next().expect("Checked above");
+                    debug!("No response within hedge delay, sending request to {}", url);
+                    self.spawn_request(&mut tasks, url, path, headers, &body);
+                }
+            }
+        }
+        // Pending requests are aborted when the join set is dropped.
+
+        if responses.len() < self.threshold {
+            return Err(AggregatorError::NotEnoughResponses(errors));
+        }
+        Ok(AggregatedResponse { responses })
+    }
+
+    fn spawn_request(
+        &self,
+        tasks: &mut JoinSet<Result<ServerResponse, ServerError>>,
+        url: &str,
+        path: &str,
+        headers: &[(&'static str, String)],
+        body: &Bytes,
+    ) {
+        tasks.spawn(fetch_from_server(
+            self.client.clone(),
+            url.to_string(),
+            format!("{}{}", url, path),
+            headers.to_vec(),
+            body.clone(),
+        ));
+    }
+}
+
+async fn fetch_from_server(
+    client: reqwest::Client,
+    url: String,
+    endpoint: String,
+    headers: Vec<(&'static str, String)>,
+    body: Bytes,
+) -> Result<ServerResponse, ServerError> {
+    let error = |status: Option<u16>, error: String| ServerError {
+        url: url.clone(),
+        status,
+        error,
+    };
+
+    let mut request = client
+        .post(&endpoint)
+        .header("Content-Type", "application/json")
+        .body(body);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| error(None, e.to_string()))?;
+
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let signature = header("X-KeyServer-Signature");
+    let timestamp = header("X-KeyServer-Timestamp");
+    let body = response
+        .text()
+        .await
+        .map_err(|e| error(Some(status.as_u16()), e.to_string()))?;
+    if !status.is_success() {
+        return Err(error(Some(status.as_u16()), body));
+    }
+    validate_response(&body).map_err(|e| error(Some(status.as_u16()), e))?;
+
+    Ok(ServerResponse {
+        url,
+        body,
+        signature,
+        timestamp,
+    })
+}
+
+/// Check that a response is well-formed: All encrypted keys must be valid group elements (checked when
+/// deserializing), ciphertexts may not be trivial and ids may not be repeated.
+fn validate_response(body: &str) -> Result<(), String> {
+    let response: FetchKeyResponse =
+        serde_json::from_str(body).map_err(|e| format!("Invalid response: {}", e))?;
+    if response.decryption_keys.is_empty() {
+        return Err("Response contains no keys".to_string());
+    }
+    let mut ids = HashSet::new();
+    for key in &response.decryption_keys {
+        if !ids.insert(&key.id) {
+            return Err(format!("Duplicate key id {}", hex::encode(&key.id)));
+        }
+        if key.encrypted_key.0 == G1Element::zero() || key.encrypted_key.1 == G1Element::zero() {
+            return Err(format!(
+                "Invalid encrypted key for id {}",
+                hex::encode(&key.id)
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::Router;
+    use crypto::elgamal::{encrypt, genkey, PublicKey, VerificationKey};
+    use fastcrypto::groups::bls12381::G2Element;
+    use rand::thread_rng;
+    use serde_json::json;
+    use std::time::Instant;
+
+    fn valid_body() -> String {
+        let (_, pk, _): (_, PublicKey<G1Element>, VerificationKey<G2Element>) =
+            genkey(&mut thread_rng());
+        let encrypted_key = encrypt(&mut thread_rng(), &G1Element::generator(), &pk);
+        json!({ "decryption_keys": [{ "id": [1, 2, 3], "encrypted_key": encrypted_key }] })
+            .to_string()
+    }
+
+    async fn mock_server(status: StatusCode, delay: Duration, body: String) -> String {
+        let app = Router::new().route(
+            "/v1/fetch_key",
+            post(move || {
+                let body = body.clone();
+                async move {
+                    sleep(delay).await;
+                    (status, body)
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    fn aggregator(servers: Vec<String>, threshold: usize) -> Aggregator {
+        Aggregator::new(
+            servers,
+            threshold,
+            Duration::from_secs(10),
+            Duration::from_millis(100),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failing_server() {
+        let failing =
+            mock_server(StatusCode::SERVICE_UNAVAILABLE, Duration::ZERO, "{}".into()).await;
+        let invalid = mock_server(StatusCode::OK, Duration::ZERO, "{}".into()).await;
+        let valid1 = mock_server(StatusCode::OK, Duration::ZERO, valid_body()).await;
+        let valid2 = mock_server(StatusCode::OK, Duration::ZERO, valid_body()).await;
+
+        let aggregator = aggregator(vec![failing, invalid, valid1.clone(), valid2.clone()], 2);
+        let response = aggregator
+            .fetch_key("/v1/fetch_key", &[], Bytes::from("{}"))
+            .await
+            .unwrap();
+        let mut urls = response
+            .responses
+            .iter()
+            .map(|r| r.url.clone())
+            .collect::<Vec<_>>();
+        urls.sort();
+        let mut expected = vec![valid1, valid2];
+        expected.sort();
+        assert_eq!(urls, expected);
+    }
+
+    #[tokio::test]
+    async fn test_hedging() {
+        let slow = mock_server(StatusCode::OK, Duration::from_secs(5), valid_body()).await;
+        let fast1 = mock_server(StatusCode::OK, Duration::ZERO, valid_body()).await;
+        let fast2 = mock_server(StatusCode::OK, Duration::ZERO, valid_body()).await;
+
+        let aggregator = aggregator(vec![slow.clone(), fast1, fast2], 2);
+        let start = Instant::now();
+        let response = aggregator
+            .fetch_key("/v1/fetch_key", &[], Bytes::from("{}"))
+            .await
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(response.responses.len(), 2);
+        assert!(response.responses.iter().all(|r| r.url != slow));
+    }
+
+    #[tokio::test]
+    async fn test_not_enough_responses() {
+        let denied = json!({ "error": "NoAccess", "message": "Access denied" }).to_string();
+        let denied1 = mock_server(StatusCode::FORBIDDEN, Duration::ZERO, denied.clone()).await;
+        let denied2 = mock_server(StatusCode::FORBIDDEN, Duration::ZERO, denied).await;
+        let valid = mock_server(StatusCode::OK, Duration::ZERO, valid_body()).await;
+
+        let aggregator = aggregator(vec![denied1, valid, denied2], 2);
+        match aggregator
+            .fetch_key("/v1/fetch_key", &[], Bytes::from("{}"))
+            .await
+        {
+            Err(AggregatorError::NotEnoughResponses(errors)) => {
+                assert_eq!(errors.len(), 2);
+                assert!(errors.iter().all(|e| e.status == Some(403)));
+            }
+            _ => panic!("Expected an error"),
+        }
+    }
+
+    #[test]
+    fn test_validate_response() {
+        assert!(validate_response(&valid_body()).is_ok());
+        assert!(validate_response("{}").is_err());
+        assert!(validate_response(&json!({ "decryption_keys": [] }).to_string()).is_err());
+
+        let (_, pk, _): (_, PublicKey<G1Element>, VerificationKey<G2Element>) =
+            genkey(&mut thread_rng());
+        let encrypted_key = encrypt(&mut thread_rng(), &G1Element::generator(), &pk);
+        let duplicate = json!({ "decryption_keys": [
+            { "id": [1], "encrypted_key": encrypted_key },
+            { "id": [1], "encrypted_key": encrypted_key },
+        ] });
+        assert!(validate_response(&duplicate.to_string()).is_err());
+
+        let trivial = Encryption(G1Element::zero(), G1Element::generator());
+        let trivial = json!({ "decryption_keys": [{ "id": [1], "encrypted_key": trivial }] });
+        assert!(validate_response(&trivial.to_string()).is_err());
+    }
+}
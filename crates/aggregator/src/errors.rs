@@ -0,0 +1,55 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+/// A failed request to a single key server.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServerError {
+    pub url: String,
+    /// The HTTP status returned by the key server, if any.
+    pub status: Option<u16>,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub enum AggregatorError {
+    /// Fewer than `threshold` key servers returned a valid response.
+    NotEnoughResponses(Vec<ServerError>),
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    error: &'static str,
+    message: String,
+    server_errors: Vec<ServerError>,
+}
+
+impl IntoResponse for AggregatorError {
+    fn into_response(self) -> Response {
+        match self {
+            AggregatorError::NotEnoughResponses(server_errors) => {
+                // If all key servers rejected the request for the same reason, e.g., because access was denied,
+                // the client gets that status. Otherwise, the key servers are considered unavailable.
+                let status = match server_errors.first().and_then(|e| e.status) {
+                    Some(status)
+                        if (400..500).contains(&status)
+                            && server_errors.iter().all(|e| e.status == Some(status)) =>
+                    {
+                        StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY)
+                    }
+                    _ => StatusCode::BAD_GATEWAY,
+                };
+                let error_response = ErrorResponse {
+                    error: "NotEnoughResponses",
+                    message: "Not enough key servers returned a valid response".to_string(),
+                    server_errors,
+                };
+                (status, Json(error_response)).into_response()
+            }
+        }
+    }
+}
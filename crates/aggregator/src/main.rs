@@ -0,0 +1,115 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A gateway which forwards fetch key requests to a set of key servers and returns the first `threshold` valid
+//! responses. The keys are still encrypted to the user's ElGamal key, so the aggregator does not learn them and the
+//! threshold trust model of the key servers is kept.
+
+use crate::aggregator::{AggregatedResponse, Aggregator, FORWARDED_HEADERS};
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::Json;
+use errors::AggregatorError;
+use mysten_service::get_mysten_service;
+use mysten_service::package_name;
+use mysten_service::package_version;
+use mysten_service::serve;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{Any, CorsLayer};
+use tracing::info;
+
+mod aggregator;
+mod errors;
+
+/// The default timeout for a request to a single key server.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The default time to wait for a response before sending the request to another key server.
+const DEFAULT_HEDGE_DELAY: Duration = Duration::from_millis(500);
+
+async fn handle_fetch_key(
+    State(aggregator): State<Arc<Aggregator>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<AggregatedResponse>, AggregatorError> {
+    aggregator
+        .fetch_key("/v1/fetch_key", &forwarded_headers(&headers), body)
+        .await
+        .map(Json)
+}
+
+async fn handle_fetch_key_ethereum(
+    State(aggregator): State<Arc<Aggregator>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<AggregatedResponse>, AggregatorError> {
+    aggregator
+        .fetch_key("/v1/fetch_key_ethereum", &forwarded_headers(&headers), body)
+        .await
+        .map(Json)
+}
+
+fn forwarded_headers(headers: &HeaderMap) -> Vec<(&'static str, String)> {
+    FORWARDED_HEADERS
+        .into_iter()
+        .filter_map(|name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| (name, v.to_string()))
+        })
+        .collect()
+}
+
+fn duration_from_env(name: &str, default: Duration) -> Duration {
+    env::var(name)
+        .map(|ms| Duration::from_millis(ms.parse().unwrap_or_else(|_| panic!("Invalid {}", name))))
+        .unwrap_or(default)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let servers = env::var("KEY_SERVER_URLS")
+        .expect("KEY_SERVER_URLS must be set")
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect::<Vec<_>>();
+    let threshold = env::var("THRESHOLD")
+        .expect("THRESHOLD must be set")
+        .parse()
+        .expect("Invalid THRESHOLD");
+    let timeout = duration_from_env("REQUEST_TIMEOUT_MS", DEFAULT_REQUEST_TIMEOUT);
+    let hedge_delay = duration_from_env("HEDGE_DELAY_MS", DEFAULT_HEDGE_DELAY);
+
+    let _guard = mysten_service::logging::init();
+    info!(
+        "Starting aggregator for {} key servers with threshold {}",
+        servers.len(),
+        threshold
+    );
+
+    let aggregator = Arc::new(Aggregator::new(servers, threshold, timeout, hedge_delay)?);
+
+    let cors = CorsLayer::new()
+        .allow_methods(Any)
+        .allow_origin(Any)
+        .allow_headers(Any)
+        .expose_headers(Any);
+
+    let app = get_mysten_service(package_name!(), package_version!())
+        .merge(
+            axum::Router::new()
+                .route("/v1/fetch_key", post(handle_fetch_key))
+                .route("/v1/fetch_key_ethereum", post(handle_fetch_key_ethereum))
+                .with_state(aggregator),
+        )
+        .layer(cors);
+
+    serve(app).await
+}
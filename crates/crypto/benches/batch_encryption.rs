@@ -0,0 +1,72 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compares [seal_encrypt_batch] with encrypting the items one by one. Since no per-server work is shared between the
+//! items of a batch, this measures the speedup from encrypting the items in parallel.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use crypto::{ibe, seal_encrypt, seal_encrypt_batch, EncryptionInput, IBEPublicKeys, ObjectID};
+
+fn items(count: usize) -> Vec<(Vec<u8>, EncryptionInput)> {
+    (0..count)
+        .map(|i| {
+            (
+                i.to_le_bytes().to_vec(),
+                EncryptionInput::Aes256Gcm {
+                    data: vec![0; 1024],
+                    aad: None,
+                },
+            )
+        })
+        .collect()
+}
+
+fn batch_encryption(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let package_id = ObjectID::random();
+    let services = (0..3).map(|_| ObjectID::random()).collect::<Vec<_>>();
+    let public_keys = IBEPublicKeys::BonehFranklinBLS12381(
+        (0..3).map(|_| ibe::generate_key_pair(&mut rng).1).collect(),
+    );
+
+    let mut group = c.benchmark_group("seal_encrypt");
+    for count in [1, 10, 100] {
+        group.bench_with_input(
+            BenchmarkId::new("sequential", count),
+            &count,
+            |b, &count| {
+                b.iter_batched(
+                    || items(count),
+                    |items| {
+                        for (id, encryption_input) in items {
+                            seal_encrypt(
+                                package_id,
+                                id,
+                                services.clone(),
+                                &public_keys,
+                                2,
+                                encryption_input,
+                            )
+                            .unwrap();
+                        }
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("batch", count), &count, |b, &count| {
+            b.iter_batched(
+                || items(count),
+                |items| {
+                    seal_encrypt_batch(package_id, items, services.clone(), &public_keys, 2)
+                        .unwrap()
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, batch_encryption);
+criterion_main!(benches);
@@ -0,0 +1,284 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Format of the tamper-evident audit log kept by key servers.
+//!
+//! The log is a file with one JSON encoded [AuditEntry] per line. Each entry contains the hash of the previous entry,
+//! so modifying, removing or reordering entries breaks the chain. Periodically, the key server appends a
+//! [AuditRecord::Checkpoint] entry which is a signature on the head of the chain using the server's audit key, so
+//! the log cannot be rewritten from scratch by anyone who does not hold the key.
+
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::error::FastCryptoError::GeneralError;
+use fastcrypto::error::FastCryptoResult;
+use fastcrypto::hash::{HashFunction, Sha3_256};
+use fastcrypto::traits::{KeyPair, Signer, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+
+/// The domain separation tag for checkpoint signatures.
+pub const DST_AUDIT_CHECKPOINT: &[u8] = b"SUI-SEAL-AUDIT-CHECKPOINT-00";
+
+/// The previous hash of the first entry in a log.
+pub const GENESIS_HASH: [u8; 32] = [0u8; 32];
+
+/// The evidence a policy was evaluated against.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum PolicyEvidence {
+    /// The timestamp of the latest checkpoint seen by the key server when the dry run was done.
+    SuiCheckpoint { timestamp_ms: u64 },
+    /// The block the simulation of the transactions was done on.
+    EthereumBlock {
+        network_id: String,
+        block_number: Option<u64>,
+    },
+}
+
+/// A release of one or more keys to a user.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyRelease {
+    pub timestamp_ms: u64,
+    pub chain: String,
+    /// The package id (Sui) or contract address (EVM).
+    pub namespace: String,
+    /// Hex encodings of the full ids of the released keys.
+    pub ids: Vec<String>,
+    pub user: String,
+    pub session_key: String,
+    pub request_id: Option<String>,
+    pub evidence: PolicyEvidence,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AuditRecord {
+    KeyRelease(KeyRelease),
+    /// A signature on the hash of the previous entry.
+    Checkpoint {
+        public_key: Ed25519PublicKey,
+        signature: Ed25519Signature,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub sequence_number: u64,
+    /// Hex encoding of the hash of the previous entry.
+    pub previous_hash: String,
+    pub record: AuditRecord,
+    /// Hex encoding of the hash of this entry.
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Create the entry following `previous`, or the first entry in a log if `previous` is `None`.
+    pub fn next(previous: Option<&AuditEntry>, record: AuditRecord) -> FastCryptoResult<Self> {
+        let (sequence_number, previous_hash) = match previous {
+            Some(previous) => (previous.sequence_number + 1, previous.hash_bytes()?),
+            None => (0, GENESIS_HASH),
+        };
+        let hash = compute_hash(sequence_number, &previous_hash, &record);
+        Ok(Self {
+            sequence_number,
+            previous_hash: hex::encode(previous_hash),
+            record,
+            hash: hex::encode(hash),
+        })
+    }
+
+    /// Create a checkpoint entry signing the head of the chain, e.g., `previous`.
+    pub fn checkpoint(previous: &AuditEntry, key_pair: &Ed25519KeyPair) -> FastCryptoResult<Self> {
+        let signature = key_pair.sign(&checkpoint_message(
+            previous.sequence_number + 1,
+            &previous.hash_bytes()?,
+        ));
+        Self::next(
+            Some(previous),
+            AuditRecord::Checkpoint {
+                public_key: key_pair.public().clone(),
+                signature,
+            },
+        )
+    }
+
+    fn hash_bytes(&self) -> FastCryptoResult<[u8; 32]> {
+        decode_hash(&self.hash)
+    }
+}
+
+/// Summary of a verified audit log.
+#[derive(Debug, Default, PartialEq)]
+pub struct AuditSummary {
+    pub entries: u64,
+    pub key_releases: u64,
+    pub checkpoints: u64,
+    /// Number of entries after the last checkpoint. These are chained but not yet signed.
+    pub unsigned_entries: u64,
+}
+
+/// Read an audit log with one JSON encoded entry per line.
+pub fn read_log(reader: impl BufRead) -> FastCryptoResult<Vec<AuditEntry>> {
+    reader
+        .lines()
+        .filter(|line| line.as_ref().map_or(true, |l| !l.trim().is_empty()))
+        .map(|line| {
+            let line = line.map_err(|e| GeneralError(format!("Failed to read log: {}", e)))?;
+            serde_json::from_str(&line)
+                .map_err(|e| GeneralError(format!("Invalid log entry: {}", e)))
+        })
+        .collect()
+}
+
+/// Verify that the given entries form a valid chain starting from the first entry of a log, and that all checkpoints
+/// are valid signatures. If `audit_key` is given, all checkpoints must be signed by this key.
+pub fn verify_log(
+    entries: &[AuditEntry],
+    audit_key: Option<&Ed25519PublicKey>,
+) -> FastCryptoResult<AuditSummary> {
+    let mut summary = AuditSummary::default();
+    let mut previous_hash = GENESIS_HASH;
+    for (expected_sequence_number, entry) in (0u64..).zip(entries) {
+        let invalid = |reason: &str| {
+            GeneralError(format!(
+                "Invalid entry {}: {}",
+                expected_sequence_number, reason
+            ))
+        };
+        if entry.sequence_number != expected_sequence_number {
+            return Err(invalid("unexpected sequence number"));
+        }
+        if decode_hash(&entry.previous_hash)? != previous_hash {
+            return Err(invalid("previous hash does not match"));
+        }
+        let hash = compute_hash(entry.sequence_number, &previous_hash, &entry.record);
+        if decode_hash(&entry.hash)? != hash {
+            return Err(invalid("hash does not match"));
+        }
+
+        match &entry.record {
+            AuditRecord::KeyRelease(_) => {
+                summary.key_releases += 1;
+                summary.unsigned_entries += 1;
+            }
+            AuditRecord::Checkpoint {
+                public_key,
+                signature,
+            } => {
+                if audit_key.is_some_and(|key| key != public_key) {
+                    return Err(invalid("checkpoint signed by an unexpected key"));
+                }
+                public_key
+                    .verify(
+                        &checkpoint_message(entry.sequence_number, &previous_hash),
+                        signature,
+                    )
+                    .map_err(|_| invalid("invalid checkpoint signature"))?;
+                summary.checkpoints += 1;
+                summary.unsigned_entries = 0;
+            }
+        }
+        summary.entries += 1;
+        previous_hash = hash;
+    }
+    Ok(summary)
+}
+
+fn compute_hash(sequence_number: u64, previous_hash: &[u8; 32], record: &AuditRecord) -> [u8; 32] {
+    let mut bytes = bcs::to_bytes(&sequence_number).expect("Never fails");
+    bytes.extend_from_slice(previous_hash);
+    bytes.extend(bcs::to_bytes(record).expect("Never fails"));
+    Sha3_256::digest(&bytes).digest
+}
+
+fn checkpoint_message(sequence_number: u64, previous_hash: &[u8; 32]) -> Vec<u8> {
+    let mut message = DST_AUDIT_CHECKPOINT.to_vec();
+    message.extend(bcs::to_bytes(&sequence_number).expect("Never fails"));
+    message.extend_from_slice(previous_hash);
+    message
+}
+
+fn decode_hash(hash: &str) -> FastCryptoResult<[u8; 32]> {
+    hex::decode(hash)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| GeneralError("Invalid hash encoding".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    fn release(i: u64) -> AuditRecord {
+        AuditRecord::KeyRelease(KeyRelease {
+            timestamp_ms: 1000 + i,
+            chain: "sui".to_string(),
+            namespace: "0x01".to_string(),
+            ids: vec![hex::encode([i as u8; 4])],
+            user: "0x02".to_string(),
+            session_key: "key".to_string(),
+            request_id: Some(format!("request {}", i)),
+            evidence: PolicyEvidence::SuiCheckpoint { timestamp_ms: i },
+        })
+    }
+
+    fn create_log(key_pair: &Ed25519KeyPair) -> Vec<AuditEntry> {
+        let mut entries: Vec<AuditEntry> = vec![];
+        for i in 0..5 {
+            let entry = AuditEntry::next(entries.last(), release(i)).unwrap();
+            entries.push(entry);
+            if i == 2 {
+                let checkpoint = AuditEntry::checkpoint(entries.last().unwrap(), key_pair).unwrap();
+                entries.push(checkpoint);
+            }
+        }
+        entries
+    }
+
+    #[test]
+    fn test_verify_log() {
+        let key_pair = Ed25519KeyPair::generate(&mut thread_rng());
+        let entries = create_log(&key_pair);
+
+        assert_eq!(
+            verify_log(&entries, Some(key_pair.public())).unwrap(),
+            AuditSummary {
+                entries: 6,
+                key_releases: 5,
+                checkpoints: 1,
+                unsigned_entries: 2,
+            }
+        );
+
+        // Round trip through the log format
+        let log = entries
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let parsed = read_log(log.as_bytes()).unwrap();
+        assert!(verify_log(&parsed, None).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_log() {
+        let key_pair = Ed25519KeyPair::generate(&mut thread_rng());
+        let entries = create_log(&key_pair);
+
+        // Modified entry
+        let mut modified = entries.clone();
+        modified[1].record = release(7);
+        assert!(verify_log(&modified, None).is_err());
+
+        // Removed entry
+        let mut removed = entries.clone();
+        removed.remove(1);
+        assert!(verify_log(&removed, None).is_err());
+
+        // Rewritten chain with valid hashes but signed with a different key
+        let other = Ed25519KeyPair::generate(&mut thread_rng());
+        let rewritten = create_log(&other);
+        assert!(verify_log(&rewritten, None).is_ok());
+        assert!(verify_log(&rewritten, Some(key_pair.public())).is_err());
+    }
+}
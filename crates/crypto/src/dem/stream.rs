@@ -0,0 +1,427 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Segmented authenticated encryption following the STREAM construction of Hoang, Reyhanitabar, Rogaway and Vizár,
+//! for messages that are too large to be held in memory.
+//!
+//! The message is split into chunks of `chunk_size` bytes and each chunk is encrypted using AES-256-GCM with the DEM
+//! key. The IV of a chunk is derived from the DEM key, the index of the chunk and a flag marking the final chunk, so
+//! chunks cannot be reordered, dropped or the ciphertext truncated without decryption failing. The ciphertext is the
+//! concatenation of the encrypted chunks, each of which is `chunk_size + TAG_SIZE` bytes except possibly the last.
+//! Only the final chunk can be shorter than `chunk_size` and it is only empty if the message is empty.
+
+use super::hmac_sha3_256;
+use crate::KEY_SIZE;
+use fastcrypto::aes::{
+    Aes256Gcm as ExternalAes256Gcm, AesKey, AuthenticatedCipher, GenericByteArray,
+    InitializationVector,
+};
+use fastcrypto::error::FastCryptoError::InvalidInput;
+use fastcrypto::error::FastCryptoResult;
+use fastcrypto::traits::ToFromBytes;
+use std::cmp::{max, min};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use typenum::U16;
+
+/// The size of the authentication tag appended to each encrypted chunk.
+pub const TAG_SIZE: usize = 16;
+
+/// A reasonable default for the size of the plaintext chunks.
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Returns the length of the ciphertext of a message of the given length.
+pub fn ciphertext_length(plaintext_length: u64, chunk_size: u32) -> u64 {
+    let number_of_chunks = max(1, plaintext_length.div_ceil(chunk_size as u64));
+    plaintext_length + number_of_chunks * TAG_SIZE as u64
+}
+
+/// Returns the length of the message of a ciphertext of the given length, or `None` if no valid ciphertext has this
+/// length.
+pub fn plaintext_length(ciphertext_length: u64, chunk_size: u32) -> Option<u64> {
+    let encrypted_chunk_size = chunk_size as u64 + TAG_SIZE as u64;
+    let number_of_chunks = max(1, ciphertext_length.div_ceil(encrypted_chunk_size));
+    let last_chunk_size =
+        ciphertext_length.checked_sub((number_of_chunks - 1) * encrypted_chunk_size)?;
+    if last_chunk_size < TAG_SIZE as u64
+        || (number_of_chunks > 1 && last_chunk_size == TAG_SIZE as u64)
+    {
+        return None;
+    }
+    Some(ciphertext_length - number_of_chunks * TAG_SIZE as u64)
+}
+
+/// Encrypts and decrypts single chunks.
+struct ChunkCipher {
+    cipher: ExternalAes256Gcm,
+    iv_key: [u8; KEY_SIZE],
+    aad: Vec<u8>,
+    chunk_size: usize,
+}
+
+impl ChunkCipher {
+    fn new(key: &[u8; KEY_SIZE], chunk_size: u32, aad: &[u8]) -> FastCryptoResult<Self> {
+        if chunk_size == 0 {
+            return Err(InvalidInput);
+        }
+        Ok(Self {
+            cipher: ExternalAes256Gcm::new(
+                AesKey::from_bytes(key).expect("Never fails for 32 byte input"),
+            ),
+            iv_key: hmac_sha3_256(key, &[3]),
+            aad: aad.to_vec(),
+            chunk_size: chunk_size as usize,
+        })
+    }
+
+    /// The IV of a chunk is the first 16 bytes of <i><b>hmac</b>(k<sub>iv</sub>, index || final)</i> where
+    /// <i>k<sub>iv</sub> = <b>hmac</b>(key, 3)</i>.
+    fn iv(&self, index: u64, is_final: bool) -> InitializationVector<U16> {
+        let digest = hmac_sha3_256(
+            &self.iv_key,
+            &[&index.to_le_bytes()[..], &[is_final as u8]].concat(),
+        );
+        GenericByteArray::from_bytes(&digest[..16]).expect("fixed length")
+    }
+
+    fn encrypt(&self, index: u64, is_final: bool, chunk: &[u8]) -> Vec<u8> {
+        self.cipher
+            .encrypt_authenticated(&self.iv(index, is_final), &self.aad, chunk)
+    }
+
+    fn decrypt(&self, index: u64, is_final: bool, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        self.cipher
+            .decrypt_authenticated(&self.iv(index, is_final), &self.aad, chunk)
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Failed to decrypt chunk {}", index),
+                )
+            })
+    }
+}
+
+/// Encrypts everything written to it and writes the ciphertext to the inner writer.
+///
+/// A full chunk is only encrypted once more data is written, because the final chunk must be flagged as such.
+/// [Encryptor::finish] must be called after the last write to encrypt the final chunk, otherwise the ciphertext is
+/// truncated and decryption will fail.
+pub struct Encryptor<W: Write> {
+    inner: W,
+    cipher: ChunkCipher,
+    buffer: Vec<u8>,
+    index: u64,
+}
+
+impl<W: Write> Encryptor<W> {
+    /// Create a new encryptor. Fails if `chunk_size` is zero.
+    pub fn new(
+        inner: W,
+        key: &[u8; KEY_SIZE],
+        chunk_size: u32,
+        aad: &[u8],
+    ) -> FastCryptoResult<Self> {
+        let cipher = ChunkCipher::new(key, chunk_size, aad)?;
+        Ok(Self {
+            inner,
+            buffer: Vec::with_capacity(cipher.chunk_size),
+            cipher,
+            index: 0,
+        })
+    }
+
+    /// Encrypt the final chunk and return the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_chunk(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_chunk(&mut self, is_final: bool) -> io::Result<()> {
+        let ciphertext = self.cipher.encrypt(self.index, is_final, &self.buffer);
+        self.inner.write_all(&ciphertext)?;
+        self.buffer.clear();
+        self.index += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Encryptor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.buffer.len() == self.cipher.chunk_size {
+            self.write_chunk(false)?;
+        }
+        let n = min(buf.len(), self.cipher.chunk_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    /// Flushes the inner writer. Note that buffered data of the current chunk is not written before the chunk is full
+    /// or [Encryptor::finish] is called.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts a ciphertext read from the inner reader. Each chunk is authenticated before any of its plaintext is
+/// returned, and reading fails with [io::ErrorKind::InvalidData] if a chunk is invalid or the ciphertext is truncated.
+pub struct Decryptor<R: Read> {
+    inner: R,
+    cipher: ChunkCipher,
+    /// Ciphertext read from the inner reader which has not been decrypted yet.
+    ciphertext: Vec<u8>,
+    plaintext: Vec<u8>,
+    position: usize,
+    index: u64,
+    done: bool,
+}
+
+impl<R: Read> Decryptor<R> {
+    /// Create a new decryptor. Fails if `chunk_size` is zero.
+    pub fn new(
+        inner: R,
+        key: &[u8; KEY_SIZE],
+        chunk_size: u32,
+        aad: &[u8],
+    ) -> FastCryptoResult<Self> {
+        Ok(Self {
+            inner,
+            cipher: ChunkCipher::new(key, chunk_size, aad)?,
+            ciphertext: vec![],
+            plaintext: vec![],
+            position: 0,
+            index: 0,
+            done: false,
+        })
+    }
+
+    fn decrypt_next_chunk(&mut self) -> io::Result<()> {
+        let encrypted_chunk_size = self.cipher.chunk_size + TAG_SIZE;
+
+        // Read one byte more than a chunk to know whether this is the final chunk.
+        let wanted = encrypted_chunk_size + 1 - self.ciphertext.len();
+        (&mut self.inner)
+            .take(wanted as u64)
+            .read_to_end(&mut self.ciphertext)?;
+        let is_final = self.ciphertext.len() <= encrypted_chunk_size;
+        let chunk_length = min(self.ciphertext.len(), encrypted_chunk_size);
+
+        self.plaintext =
+            self.cipher
+                .decrypt(self.index, is_final, &self.ciphertext[..chunk_length])?;
+        self.ciphertext.drain(..chunk_length);
+        self.position = 0;
+        self.index += 1;
+        self.done = is_final;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Only the final chunk can be empty, so a single chunk has to be decrypted at most.
+        if self.position == self.plaintext.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.decrypt_next_chunk()?;
+        }
+        let n = min(buf.len(), self.plaintext.len() - self.position);
+        buf[..n].copy_from_slice(&self.plaintext[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Decrypt the given byte range of the message from a seekable ciphertext. Only the chunks overlapping the range are
+/// read and authenticated.
+pub fn decrypt_range<R: Read + Seek>(
+    mut reader: R,
+    key: &[u8; KEY_SIZE],
+    chunk_size: u32,
+    aad: &[u8],
+    range: Range<u64>,
+) -> io::Result<Vec<u8>> {
+    let cipher = ChunkCipher::new(key, chunk_size, aad)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid chunk size"))?;
+
+    let ciphertext_length = reader.seek(SeekFrom::End(0))?;
+    let plaintext_length = plaintext_length(ciphertext_length, chunk_size)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid ciphertext length"))?;
+    if range.start > range.end || range.end > plaintext_length {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid range"));
+    }
+    if range.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let chunk_size = chunk_size as u64;
+    let encrypted_chunk_size = chunk_size + TAG_SIZE as u64;
+    let final_index = max(1, ciphertext_length.div_ceil(encrypted_chunk_size)) - 1;
+    let first_index = range.start / chunk_size;
+    let last_index = (range.end - 1) / chunk_size;
+
+    reader.seek(SeekFrom::Start(first_index * encrypted_chunk_size))?;
+    let mut plaintext = Vec::with_capacity(((last_index - first_index + 1) * chunk_size) as usize);
+    for index in first_index..=last_index {
+        let length = min(
+            encrypted_chunk_size,
+            ciphertext_length - index * encrypted_chunk_size,
+        );
+        let mut chunk = vec![0u8; length as usize];
+        reader.read_exact(&mut chunk)?;
+        plaintext.extend(cipher.decrypt(index, index == final_index, &chunk)?);
+    }
+
+    let offset = (range.start - first_index * chunk_size) as usize;
+    plaintext.truncate(offset + (range.end - range.start) as usize);
+    plaintext.drain(..offset);
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::generate_random_bytes;
+    use rand::{thread_rng, RngCore};
+    use std::io::Cursor;
+
+    const CHUNK_SIZE: u32 = 64;
+    const TEST_AAD: &[u8] = b"Walrus";
+
+    fn encrypt(msg: &[u8], key: &[u8; KEY_SIZE], write_size: usize) -> Vec<u8> {
+        let mut encryptor = Encryptor::new(vec![], key, CHUNK_SIZE, TEST_AAD).unwrap();
+        for part in msg.chunks(write_size) {
+            encryptor.write_all(part).unwrap();
+        }
+        encryptor.finish().unwrap()
+    }
+
+    fn decrypt(ciphertext: &[u8], key: &[u8; KEY_SIZE]) -> io::Result<Vec<u8>> {
+        let mut decrypted = vec![];
+        Decryptor::new(ciphertext, key, CHUNK_SIZE, TEST_AAD)
+            .unwrap()
+            .read_to_end(&mut decrypted)?;
+        Ok(decrypted)
+    }
+
+    fn random_message(length: usize) -> Vec<u8> {
+        let mut msg = vec![0u8; length];
+        thread_rng().fill_bytes(&mut msg);
+        msg
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let key = generate_random_bytes(&mut thread_rng());
+        for length in [0, 1, 63, 64, 65, 128, 1000] {
+            let msg = random_message(length);
+            for write_size in [1, 7, 64, 1000] {
+                let ciphertext = encrypt(&msg, &key, write_size);
+                assert_eq!(
+                    ciphertext.len() as u64,
+                    ciphertext_length(length as u64, CHUNK_SIZE)
+                );
+                assert_eq!(
+                    plaintext_length(ciphertext.len() as u64, CHUNK_SIZE),
+                    Some(length as u64)
+                );
+                assert_eq!(decrypt(&ciphertext, &key).unwrap(), msg);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_ciphertexts() {
+        let key = generate_random_bytes(&mut thread_rng());
+        let msg = random_message(200);
+        let ciphertext = encrypt(&msg, &key, 200);
+        let encrypted_chunk_size = CHUNK_SIZE as usize + TAG_SIZE;
+
+        // Wrong key or aad
+        let other_key = generate_random_bytes(&mut thread_rng());
+        assert!(decrypt(&ciphertext, &other_key).is_err());
+        let mut decrypted = vec![];
+        assert!(
+            Decryptor::new(ciphertext.as_slice(), &key, CHUNK_SIZE, b"Sui")
+                .unwrap()
+                .read_to_end(&mut decrypted)
+                .is_err()
+        );
+
+        // Modified chunk
+        let mut modified = ciphertext.clone();
+        modified[encrypted_chunk_size + 1] ^= 1;
+        assert!(decrypt(&modified, &key).is_err());
+
+        // Truncated at a chunk boundary
+        assert!(decrypt(&ciphertext[..2 * encrypted_chunk_size], &key).is_err());
+
+        // Swapped chunks
+        let mut swapped = ciphertext[encrypted_chunk_size..2 * encrypted_chunk_size].to_vec();
+        swapped.extend_from_slice(&ciphertext[..encrypted_chunk_size]);
+        swapped.extend_from_slice(&ciphertext[2 * encrypted_chunk_size..]);
+        assert!(decrypt(&swapped, &key).is_err());
+
+        // A full final chunk must not be accepted as a non-final chunk
+        let exact = encrypt(&msg[..128], &key, 128);
+        let mut extended = exact.clone();
+        extended.extend_from_slice(&encrypt(&[], &key, 1));
+        assert!(decrypt(&extended, &key).is_err());
+
+        assert!(Encryptor::new(vec![], &key, 0, TEST_AAD).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_range() {
+        let key = generate_random_bytes(&mut thread_rng());
+        let msg = random_message(1000);
+        let ciphertext = encrypt(&msg, &key, 1000);
+
+        for range in [
+            0..0,
+            0..1,
+            0..1000,
+            10..20,
+            60..70,
+            64..128,
+            999..1000,
+            130..900,
+        ] {
+            assert_eq!(
+                decrypt_range(
+                    Cursor::new(&ciphertext),
+                    &key,
+                    CHUNK_SIZE,
+                    TEST_AAD,
+                    range.clone()
+                )
+                .unwrap(),
+                msg[range.start as usize..range.end as usize]
+            );
+        }
+        assert!(decrypt_range(
+            Cursor::new(&ciphertext),
+            &key,
+            CHUNK_SIZE,
+            TEST_AAD,
+            0..1001
+        )
+        .is_err());
+
+        // Only the chunks in the range are authenticated, but the final chunk flag is checked
+        let mut modified = ciphertext.clone();
+        modified[0] ^= 1;
+        assert!(decrypt_range(Cursor::new(&modified), &key, CHUNK_SIZE, TEST_AAD, 0..10).is_err());
+        assert!(
+            decrypt_range(Cursor::new(&modified), &key, CHUNK_SIZE, TEST_AAD, 100..110).is_ok()
+        );
+        let truncated = &ciphertext[..3 * (CHUNK_SIZE as usize + TAG_SIZE)];
+        assert!(
+            decrypt_range(Cursor::new(truncated), &key, CHUNK_SIZE, TEST_AAD, 130..140).is_err()
+        );
+    }
+}
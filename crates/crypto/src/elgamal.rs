@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize)]
 pub struct SecretKey<G: GroupElement>(G::ScalarType);
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PublicKey<G: GroupElement>(G);
 
 #[derive(Serialize, Deserialize)]
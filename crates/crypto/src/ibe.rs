@@ -5,7 +5,7 @@
 //! It enables a symmetric key to be derived from the identity + the public key of a user and used to encrypt a fixed size message of length [KEY_LENGTH].
 
 use crate::utils::xor;
-use crate::{DST_POP, KEY_SIZE};
+use crate::{ObjectID, DST_POP, KEY_SIZE};
 use fastcrypto::error::FastCryptoError::{GeneralError, InvalidInput};
 use fastcrypto::error::FastCryptoResult;
 use fastcrypto::groups::bls12381::{G1Element, G2Element, GTElement, Scalar};
@@ -14,7 +14,6 @@ use fastcrypto::hmac::{hkdf_sha3_256, HkdfIkm};
 use fastcrypto::serde_helpers::ToFromByteArray;
 use fastcrypto::traits::AllowedRng;
 use fastcrypto::traits::ToFromBytes;
-use sui_types::base_types::ObjectID;
 
 pub type MasterKey = Scalar;
 pub type PublicKey = G2Element;
@@ -40,7 +39,17 @@ pub fn public_key_from_master_key(master_key: &MasterKey) -> PublicKey {
 
 /// Extract a user secret key from a master key and an id.
 pub fn extract(master_key: &MasterKey, id: &[u8]) -> UserSecretKey {
-    G1Element::hash_to_group_element(id) * master_key
+    extract_from_hashed_id(master_key, &hash_id(id))
+}
+
+/// Hash an id to G1. The result can be reused to extract keys for the same id, see [extract_from_hashed_id].
+pub fn hash_id(id: &[u8]) -> G1Element {
+    G1Element::hash_to_group_element(id)
+}
+
+/// Extract a user secret key from a master key and an id hashed with [hash_id].
+pub fn extract_from_hashed_id(master_key: &MasterKey, hashed_id: &G1Element) -> UserSecretKey {
+    *hashed_id * master_key
 }
 
 /// Verify that a user secret key is valid for a given public key and id.
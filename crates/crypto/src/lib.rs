@@ -1,7 +1,7 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::dem::Hmac256Ctr;
+use crate::dem::{ChaCha20Poly1305, Hmac256Ctr};
 use crate::ibe::{decrypt_deterministic, encrypt_batched_deterministic};
 use crate::tss::{combine, interpolate, SecretSharing};
 use dem::Aes256Gcm;
@@ -9,22 +9,27 @@ use fastcrypto::error::FastCryptoError::{GeneralError, InvalidInput};
 use fastcrypto::error::FastCryptoResult;
 use fastcrypto::groups::Scalar;
 use fastcrypto::hmac::{hmac_sha3_256, HmacKey};
+use fastcrypto::traits::{AllowedRng, ToFromBytes};
 use itertools::Itertools;
+pub use object_id::ObjectID;
 use rand::thread_rng;
-use serde::{Deserialize, Serialize};
+use rayon::prelude::*;
+use recovery::{RecipientPrivateKey, RecipientPublicKey};
+use serde::{Deserialize, Serialize, Serializer};
 use serde_with::serde_as;
 use std::collections::HashMap;
-pub use sui_types::base_types::ObjectID;
-use sui_types::crypto::ToFromBytes;
 use tss::split;
 use utils::generate_random_bytes;
-use hex;
 
+pub mod audit;
 pub mod dem;
 pub mod elgamal;
 pub mod gf256;
 pub mod ibe;
+mod object_id;
 mod polynomial;
+pub mod recovery;
+pub mod resharing;
 pub mod tss;
 mod utils;
 
@@ -34,21 +39,336 @@ pub const DST: &[u8] = b"SUI-SEAL-IBE-BLS12381-00";
 /// The domain separation tag for the hash-to-group function.
 pub const DST_POP: &[u8] = b"SUI-SEAL-IBE-BLS12381-POP-00";
 
+/// The domain separation tag for identities in an EVM namespace.
+pub const DST_EVM: &[u8] = b"EVM-SEAL-IBE-BLS12381-00";
+
+/// The domain separation tag for identities in a Solana namespace.
+pub const DST_SOLANA: &[u8] = b"SOLANA-SEAL-IBE-BLS12381-00";
+
 pub const KEY_SIZE: usize = 32;
 
 /// This represents an encrypted object.
 /// Must be aligned with TypeScript type.
-#[serde_as]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// Version 0 objects always belong to a Sui package and are serialized with the package id in place of the namespace.
+/// Version 1 objects may belong to any [Namespace].
+/// Version 2 objects may also have alternative policies, see [seal_encrypt_with_alternatives].
+/// Version 3 objects may also have recovery recipients, see [seal_encrypt_with_recovery].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "SerializedEncryptedObject")]
 pub struct EncryptedObject {
     pub version: u8,
-    pub package_id: ObjectID,
+    pub namespace: Namespace,
     pub id: Vec<u8>,
     // The address for a key server + the index of the share held by this server
     pub services: Vec<(ObjectID, u8)>,
     pub threshold: u8,
     pub encrypted_shares: IBEEncryptions,
     pub ciphertext: Ciphertext,
+    /// Other identities under which the base key is encrypted. Always empty for objects before version 2.
+    pub alternatives: Vec<AlternativePolicy>,
+    /// Recipients to which the base key is encrypted directly. Always empty for objects before version 3.
+    pub recovery: Vec<RecoveryRecipient>,
+}
+
+/// An alternative policy of an encrypted object. The base key of the object is also secret shared between the key
+/// servers of the policy and encrypted for its identity, so the object can be decrypted with keys for any policy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AlternativePolicy {
+    pub namespace: Namespace,
+    pub id: Vec<u8>,
+    pub services: Vec<(ObjectID, u8)>,
+    pub threshold: u8,
+    pub encrypted_shares: IBEEncryptions,
+}
+
+/// A recipient who can decrypt an encrypted object without the key servers, e.g., as a break-glass path if the key
+/// servers or the policy are no longer available.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RecoveryRecipient {
+    /// The base key encrypted with [recovery::encrypt].
+    EciesBLS12381 {
+        public_key: recovery::PublicKey,
+        ephemeral_key: recovery::EphemeralKey,
+        encrypted_key: Vec<u8>,
+    },
+    /// The base key encrypted with [recovery::x25519::encrypt].
+    EciesX25519 {
+        public_key: recovery::x25519::PublicKey,
+        ephemeral_key: recovery::x25519::EphemeralKey,
+        encrypted_key: Vec<u8>,
+    },
+    /// The base key encrypted with [recovery::secp256k1::encrypt].
+    EciesSecp256k1 {
+        #[serde_as(as = "[_; 33]")]
+        public_key: recovery::secp256k1::PublicKey,
+        #[serde_as(as = "[_; 33]")]
+        ephemeral_key: recovery::secp256k1::EphemeralKey,
+        encrypted_key: Vec<u8>,
+    },
+}
+
+impl RecoveryRecipient {
+    /// Encrypt the base key of an object with the given full id to a recipient.
+    fn new<R: AllowedRng>(
+        rng: &mut R,
+        base_key: &[u8; KEY_SIZE],
+        public_key: &RecipientPublicKey,
+        full_id: &[u8],
+    ) -> FastCryptoResult<Self> {
+        Ok(match public_key {
+            RecipientPublicKey::BLS12381(public_key) => {
+                let (ephemeral_key, encrypted_key) =
+                    recovery::encrypt(rng, base_key, public_key, full_id);
+                RecoveryRecipient::EciesBLS12381 {
+                    public_key: *public_key,
+                    ephemeral_key,
+                    encrypted_key,
+                }
+            }
+            RecipientPublicKey::X25519(public_key) => {
+                let (ephemeral_key, encrypted_key) =
+                    recovery::x25519::encrypt(rng, base_key, public_key, full_id)?;
+                RecoveryRecipient::EciesX25519 {
+                    public_key: *public_key,
+                    ephemeral_key,
+                    encrypted_key,
+                }
+            }
+            RecipientPublicKey::Secp256k1(public_key) => {
+                let (ephemeral_key, encrypted_key) =
+                    recovery::secp256k1::encrypt(rng, base_key, public_key, full_id)?;
+                RecoveryRecipient::EciesSecp256k1 {
+                    public_key: *public_key,
+                    ephemeral_key,
+                    encrypted_key,
+                }
+            }
+        })
+    }
+
+    /// The public key of the recipient.
+    pub fn public_key(&self) -> RecipientPublicKey {
+        match self {
+            RecoveryRecipient::EciesBLS12381 { public_key, .. } => {
+                RecipientPublicKey::BLS12381(*public_key)
+            }
+            RecoveryRecipient::EciesX25519 { public_key, .. } => {
+                RecipientPublicKey::X25519(*public_key)
+            }
+            RecoveryRecipient::EciesSecp256k1 { public_key, .. } => {
+                RecipientPublicKey::Secp256k1(*public_key)
+            }
+        }
+    }
+
+    /// Decrypt the base key with the private key of the recipient. Fails if the private key is for another curve.
+    fn decrypt(
+        &self,
+        private_key: &RecipientPrivateKey,
+        full_id: &[u8],
+    ) -> FastCryptoResult<[u8; KEY_SIZE]> {
+        match (self, private_key) {
+            (
+                RecoveryRecipient::EciesBLS12381 {
+                    ephemeral_key,
+                    encrypted_key,
+                    ..
+                },
+                RecipientPrivateKey::BLS12381(private_key),
+            ) => recovery::decrypt(private_key, ephemeral_key, encrypted_key, full_id),
+            (
+                RecoveryRecipient::EciesX25519 {
+                    ephemeral_key,
+                    encrypted_key,
+                    ..
+                },
+                RecipientPrivateKey::X25519(private_key),
+            ) => recovery::x25519::decrypt(private_key, ephemeral_key, encrypted_key, full_id),
+            (
+                RecoveryRecipient::EciesSecp256k1 {
+                    ephemeral_key,
+                    encrypted_key,
+                    ..
+                },
+                RecipientPrivateKey::Secp256k1(private_key),
+            ) => recovery::secp256k1::decrypt(private_key, ephemeral_key, encrypted_key, full_id),
+            _ => Err(InvalidInput),
+        }
+    }
+}
+
+/// The on-chain program whose policy decides who may access the keys of an identity.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Namespace {
+    SuiPackage(ObjectID),
+    Evm { chain_id: u64, address: [u8; 20] },
+    Solana { program: [u8; 32] },
+}
+
+impl Namespace {
+    /// Create the full id of an identity in this namespace. Sui packages use the same full ids as version 0 objects,
+    /// see [create_full_id]. Other namespaces use their own domain separation tag and have the format
+    /// [len(DST)][DST][namespace][id], where the namespace is the BCS serialization of the variant fields.
+    pub fn full_id(&self, id: &[u8]) -> Vec<u8> {
+        let (dst, namespace) = match self {
+            Namespace::SuiPackage(package_id) => return create_full_id(package_id, id),
+            Namespace::Evm { chain_id, address } => {
+                (DST_EVM, [&chain_id.to_le_bytes()[..], address].concat())
+            }
+            Namespace::Solana { program } => (DST_SOLANA, program.to_vec()),
+        };
+        let mut full_id = vec![dst.len() as u8];
+        full_id.extend_from_slice(dst);
+        full_id.extend_from_slice(&namespace);
+        full_id.extend_from_slice(id);
+        full_id
+    }
+}
+
+impl EncryptedObject {
+    /// The full id of the identity used to encrypt this object.
+    /// Fails if the version is unknown or if a version 0 object does not belong to a Sui package.
+    pub fn full_id(&self) -> FastCryptoResult<Vec<u8>> {
+        match (self.version, &self.namespace) {
+            (0, Namespace::SuiPackage(_)) | (1..=3, _) => Ok(self.namespace.full_id(&self.id)),
+            _ => Err(InvalidInput),
+        }
+    }
+
+    /// The full ids of all policies of this object, starting with the one returned by [EncryptedObject::full_id].
+    pub fn full_ids(&self) -> FastCryptoResult<Vec<Vec<u8>>> {
+        Ok(self.policies()?.into_iter().map(|p| p.full_id).collect())
+    }
+
+    /// The policies of this object, starting with the primary one.
+    fn policies(&self) -> FastCryptoResult<Vec<PolicyShares<'_>>> {
+        let mut policies = vec![PolicyShares {
+            full_id: self.full_id()?,
+            services: &self.services,
+            threshold: self.threshold,
+            encrypted_shares: &self.encrypted_shares,
+            randomness_purpose: KeyPurpose::EncryptedRandomness,
+        }];
+        for (i, policy) in self.alternatives.iter().enumerate() {
+            policies.push(PolicyShares {
+                full_id: policy.namespace.full_id(&policy.id),
+                services: &policy.services,
+                threshold: policy.threshold,
+                encrypted_shares: &policy.encrypted_shares,
+                randomness_purpose: KeyPurpose::AlternativeEncryptedRandomness(
+                    u8::try_from(i).map_err(|_| InvalidInput)?,
+                ),
+            });
+        }
+        Ok(policies)
+    }
+
+    /// The public keys for the shares of the primary policy, given the public keys of the key servers. A key server
+    /// holding several shares, see [seal_encrypt_weighted], has its public key repeated once per share. The result can
+    /// be passed to [seal_decrypt] to check the consistency of the shares.
+    pub fn share_public_keys(
+        &self,
+        public_keys: &HashMap<ObjectID, ibe::PublicKey>,
+    ) -> FastCryptoResult<IBEPublicKeys> {
+        self.services
+            .iter()
+            .map(|(id, _)| public_keys.get(id).copied().ok_or(InvalidInput))
+            .collect::<FastCryptoResult<_>>()
+            .map(IBEPublicKeys::BonehFranklinBLS12381)
+    }
+}
+
+/// The version and namespace of an encrypted object. The variant index is serialized as a single byte equal to the
+/// version, so version 0 objects are serialized exactly as before namespaces were introduced.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "VersionedNamespace")]
+enum VersionedNamespace<N, A, R> {
+    V0(ObjectID),
+    V1(N),
+    V2(N, A),
+    V3(N, A, R),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "EncryptedObject")]
+struct SerializedEncryptedObject {
+    namespace: VersionedNamespace<Namespace, Vec<AlternativePolicy>, Vec<RecoveryRecipient>>,
+    id: Vec<u8>,
+    services: Vec<(ObjectID, u8)>,
+    threshold: u8,
+    encrypted_shares: IBEEncryptions,
+    ciphertext: Ciphertext,
+}
+
+/// Same as [SerializedEncryptedObject], but borrowing the fields to avoid copying the ciphertext on serialization.
+#[derive(Serialize)]
+#[serde(rename = "EncryptedObject")]
+struct SerializedEncryptedObjectRef<'a> {
+    namespace:
+        VersionedNamespace<&'a Namespace, &'a Vec<AlternativePolicy>, &'a Vec<RecoveryRecipient>>,
+    id: &'a Vec<u8>,
+    services: &'a Vec<(ObjectID, u8)>,
+    threshold: u8,
+    encrypted_shares: &'a IBEEncryptions,
+    ciphertext: &'a Ciphertext,
+}
+
+impl From<SerializedEncryptedObject> for EncryptedObject {
+    fn from(object: SerializedEncryptedObject) -> Self {
+        let (version, namespace, alternatives, recovery) = match object.namespace {
+            VersionedNamespace::V0(package_id) => {
+                (0, Namespace::SuiPackage(package_id), vec![], vec![])
+            }
+            VersionedNamespace::V1(namespace) => (1, namespace, vec![], vec![]),
+            VersionedNamespace::V2(namespace, alternatives) => (2, namespace, alternatives, vec![]),
+            VersionedNamespace::V3(namespace, alternatives, recovery) => {
+                (3, namespace, alternatives, recovery)
+            }
+        };
+        Self {
+            version,
+            namespace,
+            id: object.id,
+            services: object.services,
+            threshold: object.threshold,
+            encrypted_shares: object.encrypted_shares,
+            ciphertext: object.ciphertext,
+            alternatives,
+            recovery,
+        }
+    }
+}
+
+impl Serialize for EncryptedObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let before_v2 = self.alternatives.is_empty() && self.recovery.is_empty();
+        let namespace = match (self.version, &self.namespace) {
+            (0, Namespace::SuiPackage(package_id)) if before_v2 => {
+                VersionedNamespace::V0(*package_id)
+            }
+            (1, namespace) if before_v2 => VersionedNamespace::V1(namespace),
+            (2, namespace) if self.recovery.is_empty() => {
+                VersionedNamespace::V2(namespace, &self.alternatives)
+            }
+            (3, namespace) => VersionedNamespace::V3(namespace, &self.alternatives, &self.recovery),
+            _ => {
+                return Err(<S::Error as serde::ser::Error>::custom(
+                    "Unsupported version or namespace",
+                ))
+            }
+        };
+        SerializedEncryptedObjectRef {
+            namespace,
+            id: &self.id,
+            services: &self.services,
+            threshold: self.threshold,
+            encrypted_shares: &self.encrypted_shares,
+            ciphertext: &self.ciphertext,
+        }
+        .serialize(serializer)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -63,6 +383,22 @@ pub enum Ciphertext {
         mac: [u8; KEY_SIZE],
     },
     Plain,
+    /// The message is encrypted using the segmented AEAD in [dem::stream] and the ciphertext is stored separately,
+    /// e.g., as a Walrus blob, since it may be too large to be held in memory.
+    Aes256GcmStream {
+        chunk_size: u32,
+        aad: Option<Vec<u8>>,
+    },
+    ChaCha20Poly1305 {
+        blob: Vec<u8>,
+        aad: Option<Vec<u8>>,
+    },
+    /// AES-256-GCM with a commitment to the base key, such that the ciphertext can only be decrypted with a single key.
+    CommittingAes256Gcm {
+        blob: Vec<u8>,
+        aad: Option<Vec<u8>>,
+        commitment: [u8; KEY_SIZE],
+    },
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -83,11 +419,41 @@ pub enum IBEUserSecretKeys {
     BonehFranklinBLS12381(HashMap<ObjectID, ibe::UserSecretKey>),
 }
 
+/// An alternative policy to encrypt an object for, see [seal_encrypt_with_alternatives].
+#[derive(Clone, Debug)]
+pub struct PolicyInput {
+    pub namespace: Namespace,
+    pub id: Vec<u8>,
+    pub key_servers: Vec<ObjectID>,
+    pub public_keys: IBEPublicKeys,
+    pub threshold: u8,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum EncryptionInput {
-    Aes256Gcm { data: Vec<u8>, aad: Option<Vec<u8>> },
-    Hmac256Ctr { data: Vec<u8>, aad: Option<Vec<u8>> },
+    Aes256Gcm {
+        data: Vec<u8>,
+        aad: Option<Vec<u8>>,
+    },
+    Hmac256Ctr {
+        data: Vec<u8>,
+        aad: Option<Vec<u8>>,
+    },
     Plain,
+    /// Only the parameters of the stream are given here. The message is encrypted using a [dem::stream::Encryptor]
+    /// created with the key returned by [seal_encrypt].
+    Aes256GcmStream {
+        chunk_size: u32,
+        aad: Option<Vec<u8>>,
+    },
+    ChaCha20Poly1305 {
+        data: Vec<u8>,
+        aad: Option<Vec<u8>>,
+    },
+    CommittingAes256Gcm {
+        data: Vec<u8>,
+        aad: Option<Vec<u8>>,
+    },
 }
 
 /// Encrypt the given plaintext. This is done as follows:
@@ -111,13 +477,292 @@ pub fn seal_encrypt(
     threshold: u8,
     encryption_input: EncryptionInput,
 ) -> FastCryptoResult<(EncryptedObject, [u8; KEY_SIZE])> {
-    let number_of_shares = key_servers.len() as u8;
-    if threshold > number_of_shares || threshold == 0 {
+    encrypt_object(
+        0,
+        Namespace::SuiPackage(package_id),
+        id,
+        key_servers,
+        public_keys,
+        threshold,
+        vec![],
+        &[],
+        encryption_input,
+    )
+}
+
+/// Same as [seal_encrypt], but for an identity in any namespace. This creates a version 1 object.
+///
+/// @param namespace The namespace of the id.
+/// @param id The id.
+/// @param key_servers The seal key services to use for the encryption.
+/// @param public_keys The public keys of the key servers.
+/// @param threshold The threshold for the TSS.
+/// @param encryption_input The encryption input.
+/// @return The encrypted object and the derived symmetric key used for the encryption.
+pub fn seal_encrypt_with_namespace(
+    namespace: Namespace,
+    id: Vec<u8>,
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    encryption_input: EncryptionInput,
+) -> FastCryptoResult<(EncryptedObject, [u8; KEY_SIZE])> {
+    encrypt_object(
+        1,
+        namespace,
+        id,
+        key_servers,
+        public_keys,
+        threshold,
+        vec![],
+        &[],
+        encryption_input,
+    )
+}
+
+/// Encrypt many items for the same package and key servers, e.g., the records of a dataset. The result is the same as
+/// calling [seal_encrypt] for each item, in the same order, but the items are encrypted in parallel.
+///
+/// No per-server work is shared between the items beyond validating the key servers once. Each object has its own id
+/// and IBE randomness, so every item needs its own hash to G1 and pairings with the public keys, and the curve library
+/// does not expose prepared (precomputed) G2 points for the pairings. The speedup over [seal_encrypt] is therefore
+/// only from parallelism.
+///
+/// @param package_id The package id.
+/// @param items The ids and encryption inputs of the items.
+/// @param key_servers The seal key services to use for the encryption.
+/// @param public_keys The public keys of the key servers.
+/// @param threshold The threshold for the TSS.
+/// @return The encrypted objects and the derived symmetric keys used for the encryption.
+pub fn seal_encrypt_batch(
+    package_id: ObjectID,
+    items: Vec<(Vec<u8>, EncryptionInput)>,
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+) -> FastCryptoResult<Vec<(EncryptedObject, [u8; KEY_SIZE])>> {
+    // Fail once instead of for every item if the key servers are invalid
+    let IBEPublicKeys::BonehFranklinBLS12381(keys) = public_keys;
+    if keys.len() != key_servers.len() || threshold == 0 || threshold as usize > keys.len() {
+        return Err(InvalidInput);
+    }
+
+    items
+        .into_par_iter()
+        .map(|(id, encryption_input)| {
+            seal_encrypt(
+                package_id,
+                id,
+                key_servers.clone(),
+                public_keys,
+                threshold,
+                encryption_input,
+            )
+        })
+        .collect()
+}
+
+/// Same as [seal_encrypt], but key servers may hold several shares of the key. A key server with weight `w` is assigned
+/// `w` shares, each encrypted under its public key with a different share index, so the threshold is a threshold on
+/// the total weight of the key servers used for decryption. Decryption needs only one user secret key per key server.
+///
+/// @param package_id The package id.
+/// @param id The id.
+/// @param key_servers The seal key services to use for the encryption.
+/// @param weights The number of shares given to each key server. The total weight must be at most 255.
+/// @param public_keys The public keys of the key servers.
+/// @param threshold The threshold for the TSS. This is at most the total weight.
+/// @param encryption_input The encryption input.
+/// @return The encrypted object and the derived symmetric key used for the encryption.
+pub fn seal_encrypt_weighted(
+    package_id: ObjectID,
+    id: Vec<u8>,
+    key_servers: Vec<ObjectID>,
+    weights: &[u8],
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    encryption_input: EncryptionInput,
+) -> FastCryptoResult<(EncryptedObject, [u8; KEY_SIZE])> {
+    let (key_servers, public_keys) = expand_weights(key_servers, weights, public_keys)?;
+    encrypt_object(
+        0,
+        Namespace::SuiPackage(package_id),
+        id,
+        key_servers,
+        &public_keys,
+        threshold,
+        vec![],
+        &[],
+        encryption_input,
+    )
+}
+
+/// Repeat each key server and its public key as many times as its weight.
+fn expand_weights(
+    key_servers: Vec<ObjectID>,
+    weights: &[u8],
+    public_keys: &IBEPublicKeys,
+) -> FastCryptoResult<(Vec<ObjectID>, IBEPublicKeys)> {
+    if weights.len() != key_servers.len()
+        || weights.contains(&0)
+        || weights.iter().map(|w| *w as usize).sum::<usize>() > u8::MAX as usize
+    {
         return Err(InvalidInput);
     }
+    let expanded_key_servers = key_servers
+        .into_iter()
+        .zip(weights)
+        .flat_map(|(key_server, weight)| std::iter::repeat(key_server).take(*weight as usize))
+        .collect();
+    let expanded_public_keys = match public_keys {
+        IBEPublicKeys::BonehFranklinBLS12381(public_keys) => {
+            if public_keys.len() != weights.len() {
+                return Err(InvalidInput);
+            }
+            IBEPublicKeys::BonehFranklinBLS12381(
+                public_keys
+                    .iter()
+                    .zip(weights)
+                    .flat_map(|(pk, weight)| std::iter::repeat(*pk).take(*weight as usize))
+                    .collect(),
+            )
+        }
+    };
+    Ok((expanded_key_servers, expanded_public_keys))
+}
+
+/// Same as [seal_encrypt_with_namespace], but the base key is also encrypted under the given alternative policies, so
+/// keys for the identity of any of the policies can decrypt the object. This creates a version 2 object.
+///
+/// @param namespace The namespace of the id.
+/// @param id The id.
+/// @param key_servers The seal key services to use for the encryption.
+/// @param public_keys The public keys of the key servers.
+/// @param threshold The threshold for the TSS.
+/// @param alternatives The alternative policies. At most 256 are allowed.
+/// @param encryption_input The encryption input.
+/// @return The encrypted object and the derived symmetric key used for the encryption.
+pub fn seal_encrypt_with_alternatives(
+    namespace: Namespace,
+    id: Vec<u8>,
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    alternatives: Vec<PolicyInput>,
+    encryption_input: EncryptionInput,
+) -> FastCryptoResult<(EncryptedObject, [u8; KEY_SIZE])> {
+    encrypt_object(
+        2,
+        namespace,
+        id,
+        key_servers,
+        public_keys,
+        threshold,
+        alternatives,
+        &[],
+        encryption_input,
+    )
+}
+
+/// Encrypt the given plaintext like [seal_encrypt_with_alternatives], and also encrypt the base key to the given
+/// recovery recipients using [recovery::encrypt]. A recipient can decrypt the object with its private key using
+/// [seal_decrypt_with_recovery_key], without any key servers. The result is a version 3 object.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_encrypt_with_recovery(
+    namespace: Namespace,
+    id: Vec<u8>,
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    alternatives: Vec<PolicyInput>,
+    recovery_keys: &[RecipientPublicKey],
+    encryption_input: EncryptionInput,
+) -> FastCryptoResult<(EncryptedObject, [u8; KEY_SIZE])> {
+    encrypt_object(
+        3,
+        namespace,
+        id,
+        key_servers,
+        public_keys,
+        threshold,
+        alternatives,
+        recovery_keys,
+        encryption_input,
+    )
+}
+
+/// Same as [seal_encrypt_with_recovery], but also returns the base key of the object. The base key can be used to
+/// re-seal the object using [reseal] without the key servers, so it must be kept as secret as the message. The object
+/// is created with the lowest version supporting the namespace and recovery keys.
+///
+/// @return The encrypted object, the derived symmetric key used for the encryption and the base key.
+pub fn seal_encrypt_with_base_key(
+    namespace: Namespace,
+    id: Vec<u8>,
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    recovery_keys: &[RecipientPublicKey],
+    encryption_input: EncryptionInput,
+) -> FastCryptoResult<(EncryptedObject, [u8; KEY_SIZE], [u8; KEY_SIZE])> {
+    let version = match namespace {
+        _ if !recovery_keys.is_empty() => 3,
+        Namespace::SuiPackage(_) => 0,
+        _ => 1,
+    };
+    encrypt_object_with_base_key(
+        version,
+        namespace,
+        id,
+        key_servers,
+        public_keys,
+        threshold,
+        vec![],
+        recovery_keys,
+        encryption_input,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn encrypt_object(
+    version: u8,
+    namespace: Namespace,
+    id: Vec<u8>,
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    alternatives: Vec<PolicyInput>,
+    recovery_keys: &[RecipientPublicKey],
+    encryption_input: EncryptionInput,
+) -> FastCryptoResult<(EncryptedObject, [u8; KEY_SIZE])> {
+    encrypt_object_with_base_key(
+        version,
+        namespace,
+        id,
+        key_servers,
+        public_keys,
+        threshold,
+        alternatives,
+        recovery_keys,
+        encryption_input,
+    )
+    .map(|(encrypted_object, dem_key, _)| (encrypted_object, dem_key))
+}
 
+#[allow(clippy::too_many_arguments)]
+fn encrypt_object_with_base_key(
+    version: u8,
+    namespace: Namespace,
+    id: Vec<u8>,
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    alternatives: Vec<PolicyInput>,
+    recovery_keys: &[RecipientPublicKey],
+    encryption_input: EncryptionInput,
+) -> FastCryptoResult<(EncryptedObject, [u8; KEY_SIZE], [u8; KEY_SIZE])> {
     let mut rng = thread_rng();
-    let full_id = create_full_id(&package_id, &id);
+    let full_id = namespace.full_id(&id);
 
     // Generate a random base key
     let base_key = generate_random_bytes(&mut rng);
@@ -134,55 +779,78 @@ pub fn seal_encrypt(
             Ciphertext::Hmac256Ctr { blob, mac, aad }
         }
         EncryptionInput::Plain => Ciphertext::Plain,
+        EncryptionInput::Aes256GcmStream { chunk_size, aad } => {
+            if chunk_size == 0 {
+                return Err(InvalidInput);
+            }
+            Ciphertext::Aes256GcmStream { chunk_size, aad }
+        }
+        EncryptionInput::ChaCha20Poly1305 { data, aad } => Ciphertext::ChaCha20Poly1305 {
+            blob: ChaCha20Poly1305::encrypt(&data, aad.as_ref().unwrap_or(&vec![]), &dem_key),
+            aad,
+        },
+        EncryptionInput::CommittingAes256Gcm { data, aad } => Ciphertext::CommittingAes256Gcm {
+            blob: Aes256Gcm::encrypt(&data, aad.as_ref().unwrap_or(&vec![]), &dem_key),
+            aad,
+            commitment: derive_key(KeyPurpose::KeyCommitment, &base_key),
+        },
     };
 
-    // Secret share the derived key
-    let SecretSharing {
-        indices, shares, ..
-    } = split(&mut rng, base_key, threshold, number_of_shares)?;
-
-    let services = key_servers.into_iter().zip(indices).collect::<Vec<_>>();
+    let (services, encrypted_shares) = encrypt_shares(
+        &mut rng,
+        &base_key,
+        &full_id,
+        key_servers,
+        public_keys,
+        threshold,
+        KeyPurpose::EncryptedRandomness,
+    )?;
+
+    // Each alternative policy uses its own randomness, encrypted with a separate key
+    let alternatives = alternatives
+        .into_iter()
+        .enumerate()
+        .map(|(i, policy)| {
+            let (services, encrypted_shares) = encrypt_shares(
+                &mut rng,
+                &base_key,
+                &policy.namespace.full_id(&policy.id),
+                policy.key_servers,
+                &policy.public_keys,
+                policy.threshold,
+                KeyPurpose::AlternativeEncryptedRandomness(
+                    u8::try_from(i).map_err(|_| InvalidInput)?,
+                ),
+            )?;
+            Ok(AlternativePolicy {
+                namespace: policy.namespace,
+                id: policy.id,
+                services,
+                threshold: policy.threshold,
+                encrypted_shares,
+            })
+        })
+        .collect::<FastCryptoResult<_>>()?;
 
-    let encrypted_shares = match public_keys {
-        IBEPublicKeys::BonehFranklinBLS12381(public_keys) => {
-            if public_keys.len() != number_of_shares as usize {
-                return Err(InvalidInput);
-            }
-            let randomness = ibe::Randomness::rand(&mut rng);
-
-            // Encrypt the shares using the IBE keys.
-            // Use the share index as the `index` parameter for the IBE decryption, allowing to encrypt shares for the same identity to the same public key.
-            let (nonce, encrypted_shares) = encrypt_batched_deterministic(
-                &randomness,
-                &shares,
-                public_keys,
-                &full_id,
-                &services,
-            )?;
-
-            let encrypted_randomness = ibe::encrypt_randomness(
-                &randomness,
-                &derive_key(KeyPurpose::EncryptedRandomness, &base_key),
-            );
-            IBEEncryptions::BonehFranklinBLS12381 {
-                nonce,
-                encrypted_shares,
-                encrypted_randomness,
-            }
-        }
-    };
+    let recovery = recovery_keys
+        .iter()
+        .map(|public_key| RecoveryRecipient::new(&mut rng, &base_key, public_key, &full_id))
+        .collect::<FastCryptoResult<_>>()?;
 
     Ok((
         EncryptedObject {
-            version: 0,
-            package_id,
+            version,
+            namespace,
             id,
             services,
             threshold,
             encrypted_shares,
             ciphertext,
+            alternatives,
+            recovery,
         },
         dem_key,
+        base_key,
     ))
 }
 
@@ -194,132 +862,538 @@ pub fn seal_encrypt(
 ///
 /// @param encrypted_object The encrypted object. See `seal_encrypt`.
 /// @param user_secret_keys The user secret keys. It's assumed that these are validated. Otherwise, the decryption will fail or, eg. in the case of using `Plain` mode, the derived key will be wrong.
-/// @param public_keys The public keys of the key servers. If provided, all shares will be decrypted and checked for consistency.
-/// @return The decrypted plaintext or, if `Plain` mode was used, the derived key.
+/// @param public_keys The public keys of the key servers, one per entry in `services`. If provided, all shares will be decrypted and checked for consistency. See [EncryptedObject::share_public_keys].
+/// @return The decrypted plaintext or, if `Plain` or `Aes256GcmStream` mode was used, the derived key. For the latter, the key is used to create a [dem::stream::Decryptor] for the separately stored ciphertext.
 pub fn seal_decrypt(
     encrypted_object: &EncryptedObject,
     user_secret_keys: &IBEUserSecretKeys,
     public_keys: Option<&IBEPublicKeys>,
 ) -> FastCryptoResult<Vec<u8>> {
-    let EncryptedObject {
-        version,
-        package_id,
-        id,
-        encrypted_shares,
-        services,
-        threshold,
-        ciphertext,
-        ..
-    } = encrypted_object;
+    let base_key = recover_base_key(encrypted_object, user_secret_keys, public_keys)?;
+    decrypt_ciphertext(&encrypted_object.ciphertext, &base_key)
+}
 
-    if *version != 0 {
-        return Err(InvalidInput);
+/// The result of [seal_decrypt_robust].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RobustDecryption {
+    /// The decrypted plaintext or derived key, see [seal_decrypt].
+    pub plaintext: Vec<u8>,
+    /// The key servers whose user secret keys were invalid or gave shares that are not consistent with the base key.
+    pub faulty_key_servers: Vec<ObjectID>,
+}
+
+/// Decrypt the given ciphertext like [seal_decrypt], but tolerate invalid user secret keys from some of the key servers
+/// as long as the valid ones reach the threshold. This is done as follows:
+///  - If the public keys are given, drop the user secret keys that are not valid for the full id,
+///  - Skip the policy if the remaining key servers do not hold enough shares,
+///  - Try all remaining shares at once, and otherwise subsets of the remaining key servers with enough shares, smallest
+///    first, until the shares reconstruct a base key that decrypts the encrypted randomness to the nonce,
+///  - Mark the key servers whose shares are not on the polynomial defined by that subset as faulty,
+///  - Decrypt the ciphertext using the base key.
+///
+/// The number of subsets grows exponentially with the number of faulty key servers, so this is only meant for a few of
+/// them, e.g., after [seal_decrypt] failed.
+///
+/// @param encrypted_object The encrypted object. See `seal_encrypt`.
+/// @param user_secret_keys The user secret keys. These are not assumed to be valid.
+/// @param public_keys The public keys of the key servers, one per entry in `services`. If provided, the user secret keys are verified and all shares are checked for consistency.
+/// @return The decrypted plaintext and the faulty key servers.
+pub fn seal_decrypt_robust(
+    encrypted_object: &EncryptedObject,
+    user_secret_keys: &IBEUserSecretKeys,
+    public_keys: Option<&IBEPublicKeys>,
+) -> FastCryptoResult<RobustDecryption> {
+    let (base_key, faulty_key_servers) = try_policies(encrypted_object, |policy| {
+        policy.recover_base_key_robust(user_secret_keys, public_keys)
+    })?;
+    verify_key_commitment(&encrypted_object.ciphertext, &base_key)?;
+
+    Ok(RobustDecryption {
+        plaintext: decrypt_ciphertext(&encrypted_object.ciphertext, &base_key)?,
+        faulty_key_servers,
+    })
+}
+
+/// Apply the given function to the policies of an encrypted object in order and return the first successful result or
+/// the last error. If the object has alternative policies, the user secret keys may be for any of them.
+fn try_policies<T>(
+    encrypted_object: &EncryptedObject,
+    f: impl Fn(&PolicyShares) -> FastCryptoResult<T>,
+) -> FastCryptoResult<T> {
+    let mut result = Err(InvalidInput);
+    for policy in encrypted_object.policies()? {
+        result = f(&policy);
+        if result.is_ok() {
+            break;
+        }
     }
+    result
+}
 
-    let full_id = create_full_id(package_id, id);
+/// Derive the DEM key from the base key and decrypt the ciphertext.
+fn decrypt_ciphertext(
+    ciphertext: &Ciphertext,
+    base_key: &[u8; KEY_SIZE],
+) -> FastCryptoResult<Vec<u8>> {
+    let dem_key = derive_key(KeyPurpose::DEM, base_key);
+    match ciphertext {
+        Ciphertext::Aes256Gcm { blob, aad } => {
+            Aes256Gcm::decrypt(blob, aad.as_ref().map_or(&[], |v| v), &dem_key)
+        }
+        Ciphertext::Hmac256Ctr { blob, aad, mac } => {
+            Hmac256Ctr::decrypt(blob, mac, aad.as_ref().map_or(&[], |v| v), &dem_key)
+        }
+        Ciphertext::ChaCha20Poly1305 { blob, aad } => {
+            ChaCha20Poly1305::decrypt(blob, aad.as_ref().map_or(&[], |v| v), &dem_key)
+        }
+        Ciphertext::CommittingAes256Gcm { blob, aad, .. } => {
+            Aes256Gcm::decrypt(blob, aad.as_ref().map_or(&[], |v| v), &dem_key)
+        }
+        Ciphertext::Plain | Ciphertext::Aes256GcmStream { .. } => Ok(dem_key.to_vec()),
+    }
+}
 
-    // Decap IBE keys and decrypt shares
-    let shares = match (&encrypted_shares, user_secret_keys) {
-        (
-            IBEEncryptions::BonehFranklinBLS12381 {
-                nonce,
-                encrypted_shares,
-                ..
-            },
-            IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
-        ) => {
-            // Check that the encrypted object is valid,
-            // e.g., that there is an encrypted share of the key per service
-            if encrypted_shares.len() != services.len() {
-                return Err(InvalidInput);
-            }
+/// Reconstruct the base key of an encrypted object from the shares of the given user secret keys. The base key can be
+/// used to re-seal the object using [reseal].
+///
+/// @param encrypted_object The encrypted object. See `seal_encrypt`.
+/// @param user_secret_keys The user secret keys. See `seal_decrypt`.
+/// @param public_keys The public keys of the key servers. If provided, all shares will be decrypted and checked for consistency.
+/// @return The base key.
+pub fn recover_base_key(
+    encrypted_object: &EncryptedObject,
+    user_secret_keys: &IBEUserSecretKeys,
+    public_keys: Option<&IBEPublicKeys>,
+) -> FastCryptoResult<[u8; KEY_SIZE]> {
+    let has_alternatives = !encrypted_object.alternatives.is_empty();
+    let base_key = try_policies(encrypted_object, |policy| {
+        let base_key = policy.recover_base_key(user_secret_keys, public_keys)?;
+
+        // The user secret keys may be for another policy with the same key servers, so the base key is verified
+        if has_alternatives {
+            policy.verify_base_key(&base_key)?;
+        }
+        Ok(base_key)
+    })?;
 
-            // The indices of the services for which we have a secret key
-            let service_indices: Vec<usize> = services
-                .iter()
-                .enumerate()
-                .filter(|(_, (id, _))| user_secret_keys.contains_key(id))
-                .map(|(i, _)| i)
-                .collect();
-            if service_indices.len() < *threshold as usize {
-                return Err(InvalidInput);
+    verify_key_commitment(&encrypted_object.ciphertext, &base_key)?;
+
+    Ok(base_key)
+}
+
+/// Decrypt an encrypted object with the private key of one of its recovery recipients, see [seal_encrypt_with_recovery].
+/// No key servers are involved.
+///
+/// @param encrypted_object The encrypted object. See `seal_encrypt_with_recovery`.
+/// @param private_key The private key of a recovery recipient of the object.
+/// @return The decrypted plaintext or, if `Plain` or `Aes256GcmStream` mode was used, the derived key.
+pub fn seal_decrypt_with_recovery_key(
+    encrypted_object: &EncryptedObject,
+    private_key: &RecipientPrivateKey,
+) -> FastCryptoResult<Vec<u8>> {
+    let base_key = recover_base_key_with_recovery_key(encrypted_object, private_key)?;
+    decrypt_ciphertext(&encrypted_object.ciphertext, &base_key)
+}
+
+/// Decrypt the base key of an encrypted object with the private key of one of its recovery recipients. The base key is
+/// checked against the primary policy, so a recipient cannot be given a different key than the key servers. The base
+/// key can be used to re-seal the object using [reseal].
+pub fn recover_base_key_with_recovery_key(
+    encrypted_object: &EncryptedObject,
+    private_key: &RecipientPrivateKey,
+) -> FastCryptoResult<[u8; KEY_SIZE]> {
+    let policies = encrypted_object.policies()?;
+    let public_key = private_key.public_key()?;
+    let base_key = encrypted_object
+        .recovery
+        .iter()
+        .find(|recipient| recipient.public_key() == public_key)
+        .ok_or_else(|| GeneralError("Not a recovery recipient".to_string()))?
+        .decrypt(private_key, &policies[0].full_id)?;
+
+    policies[0].verify_base_key(&base_key)?;
+    verify_key_commitment(&encrypted_object.ciphertext, &base_key)?;
+    Ok(base_key)
+}
+
+/// The parts of an encrypted object needed to recover the base key under one of its policies.
+struct PolicyShares<'a> {
+    full_id: Vec<u8>,
+    services: &'a [(ObjectID, u8)],
+    threshold: u8,
+    encrypted_shares: &'a IBEEncryptions,
+    /// The purpose of the key used to encrypt the randomness of the IBE encryptions.
+    randomness_purpose: KeyPurpose,
+}
+
+impl PolicyShares<'_> {
+    /// Reconstruct the base key from the shares of the key servers with user secret keys. See [recover_base_key].
+    fn recover_base_key(
+        &self,
+        user_secret_keys: &IBEUserSecretKeys,
+        public_keys: Option<&IBEPublicKeys>,
+    ) -> FastCryptoResult<[u8; KEY_SIZE]> {
+        let PolicyShares {
+            full_id,
+            services,
+            threshold,
+            encrypted_shares,
+            randomness_purpose,
+        } = self;
+
+        // Decap IBE keys and decrypt shares
+        let shares = match (encrypted_shares, user_secret_keys) {
+            (
+                IBEEncryptions::BonehFranklinBLS12381 {
+                    nonce,
+                    encrypted_shares,
+                    ..
+                },
+                IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
+            ) => {
+                // Check that the encrypted object is valid,
+                // e.g., that there is an encrypted share of the key per service
+                if encrypted_shares.len() != services.len() {
+                    return Err(InvalidInput);
+                }
+
+                // The indices of the services for which we have a secret key
+                let service_indices: Vec<usize> = services
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (id, _))| user_secret_keys.contains_key(id))
+                    .map(|(i, _)| i)
+                    .collect();
+                if service_indices.len() < *threshold as usize {
+                    return Err(InvalidInput);
+                }
+
+                service_indices
+                    .into_iter()
+                    .map(|i| {
+                        let index = services[i].1;
+                        (index, ibe::decrypt(
+                            nonce,
+                            &encrypted_shares[i],
+                            user_secret_keys
+                                .get(&services[i].0)
+                                .expect("This shouldn't happen: It's checked above that this secret key is available"),
+                            full_id,
+                            &services[i],
+                        ))
+                    })
+                    .collect_vec()
             }
+        };
 
-            service_indices
-                .into_iter()
-                .map(|i| {
-                    let index = services[i].1;
-                    (index, ibe::decrypt(
-                        nonce,
-                        &encrypted_shares[i],
-                        user_secret_keys
-                            .get(&services[i].0)
-                            .expect("This shouldn't happen: It's checked above that this secret key is available"),
-                        &full_id,
-                        &services[i],
-                    ))
-                })
-                .collect_vec()
+        // Create the base key from the shares
+        let base_key = combine(&shares)?;
+
+        // If the public keys are given, we can decrypt all shares and check for consistency
+        if let Some(public_keys) = public_keys {
+            encrypted_shares.check_share_consistency(
+                &shares,
+                full_id,
+                services,
+                public_keys,
+                &base_key,
+                *randomness_purpose,
+            )?;
         }
-    };
 
-    // Create the base key from the shares
-    let base_key = combine(&shares)?;
+        Ok(base_key)
+    }
 
-    // If the public keys are given, we can decrypt all shares and check for consistency
-    if let Some(public_keys) = public_keys {
-        encrypted_shares.check_share_consistency(
-            &shares,
-            &full_id,
+    /// Reconstruct the base key even if some user secret keys are invalid. See [seal_decrypt_robust].
+    fn recover_base_key_robust(
+        &self,
+        user_secret_keys: &IBEUserSecretKeys,
+        public_keys: Option<&IBEPublicKeys>,
+    ) -> FastCryptoResult<([u8; KEY_SIZE], Vec<ObjectID>)> {
+        let PolicyShares {
+            full_id,
             services,
-            public_keys,
-            &base_key,
-        )?;
+            threshold,
+            encrypted_shares,
+            randomness_purpose,
+        } = self;
+        let mut faulty_key_servers = vec![];
+
+        let shares = match (encrypted_shares, user_secret_keys) {
+            (
+                IBEEncryptions::BonehFranklinBLS12381 {
+                    nonce,
+                    encrypted_shares,
+                    ..
+                },
+                IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
+            ) => {
+                if encrypted_shares.len() != services.len() {
+                    return Err(InvalidInput);
+                }
+
+                // Drop the user secret keys that cannot be verified against the public key of the key server
+                let mut user_secret_keys = user_secret_keys.clone();
+                if let Some(IBEPublicKeys::BonehFranklinBLS12381(public_keys)) = public_keys {
+                    if public_keys.len() != services.len() {
+                        return Err(InvalidInput);
+                    }
+                    for ((id, _), public_key) in services.iter().zip(public_keys) {
+                        if let Some(user_secret_key) = user_secret_keys.get(id) {
+                            if ibe::verify_user_secret_key(user_secret_key, full_id, public_key)
+                                .is_err()
+                            {
+                                user_secret_keys.remove(id);
+                                faulty_key_servers.push(*id);
+                            }
+                        }
+                    }
+                }
+
+                // Decrypt the shares of the remaining key servers
+                services
+                    .iter()
+                    .zip(encrypted_shares)
+                    .filter_map(|(service, encrypted_share)| {
+                        user_secret_keys.get(&service.0).map(|user_secret_key| {
+                            (
+                                service.0,
+                                service.1,
+                                ibe::decrypt(
+                                    nonce,
+                                    encrypted_share,
+                                    user_secret_key,
+                                    full_id,
+                                    service,
+                                ),
+                            )
+                        })
+                    })
+                    .collect_vec()
+            }
+        };
+
+        // The keys may be for another policy, or too many may have been dropped
+        if shares.len() < *threshold as usize {
+            return Err(GeneralError("Not enough valid shares".to_string()));
+        }
+
+        let shares_of = |subset: &[ObjectID]| {
+            shares
+                .iter()
+                .filter(|(id, _, _)| subset.contains(id))
+                .map(|(_, index, share)| (*index, *share))
+                .collect_vec()
+        };
+        let reconstruct = |subset_shares: Vec<(u8, [u8; KEY_SIZE])>| {
+            let base_key = combine(&subset_shares).ok()?;
+            self.verify_base_key(&base_key).ok()?;
+            Some((base_key, subset_shares))
+        };
+        let key_servers = shares.iter().map(|(id, _, _)| *id).unique().collect_vec();
+
+        // Usually all key servers are honest, so try all shares before searching for a subset
+        let (base_key, subset_shares) = match reconstruct(shares_of(&key_servers)) {
+            Some(result) => result,
+            None => {
+                // The smallest number of key servers that can hold enough shares, see [seal_encrypt_weighted]
+                let mut share_counts = key_servers
+                    .iter()
+                    .map(|id| shares.iter().filter(|(s, _, _)| s == id).count())
+                    .collect_vec();
+                share_counts.sort_unstable_by(|a, b| b.cmp(a));
+                let min_size = share_counts
+                    .iter()
+                    .scan(0, |total, count| {
+                        *total += count;
+                        Some(*total)
+                    })
+                    .position(|total| total >= *threshold as usize)
+                    .map_or(key_servers.len(), |i| i + 1);
+
+                // Find the smallest subset of key servers whose shares reconstruct the base key
+                (min_size..=key_servers.len())
+                    .flat_map(|k| key_servers.iter().copied().combinations(k))
+                    .map(|subset| shares_of(&subset))
+                    .filter(|subset_shares| subset_shares.len() >= *threshold as usize)
+                    .find_map(reconstruct)
+                    .ok_or_else(|| GeneralError("Not enough valid shares".to_string()))?
+            }
+        };
+
+        // The shares of the other key servers must be on the same polynomial
+        let polynomial = interpolate(&subset_shares)?;
+        faulty_key_servers.extend(
+            shares
+                .iter()
+                .filter(|(_, index, share)| polynomial(*index) != *share)
+                .map(|(id, _, _)| *id)
+                .unique(),
+        );
+
+        if let Some(public_keys) = public_keys {
+            encrypted_shares.check_share_consistency(
+                &subset_shares,
+                full_id,
+                services,
+                public_keys,
+                &base_key,
+                *randomness_purpose,
+            )?;
+        }
+
+        Ok((base_key, faulty_key_servers))
     }
 
-    // Derive symmetric key and decrypt the ciphertext
-    let dem_key = derive_key(KeyPurpose::DEM, &base_key);
-    match ciphertext {
-        Ciphertext::Aes256Gcm { blob, aad } => {
-            Aes256Gcm::decrypt(blob, aad.as_ref().map_or(&[], |v| v), &dem_key)
+    /// Check that the base key decrypts the encrypted randomness to the randomness used for the nonce.
+    fn verify_base_key(&self, base_key: &[u8; KEY_SIZE]) -> FastCryptoResult<()> {
+        match self.encrypted_shares {
+            IBEEncryptions::BonehFranklinBLS12381 {
+                nonce,
+                encrypted_randomness,
+                ..
+            } => ibe::decrypt_and_verify_nonce(
+                encrypted_randomness,
+                &derive_key(self.randomness_purpose, base_key),
+                nonce,
+            )
+            .map(|_| ()),
         }
-        Ciphertext::Hmac256Ctr { blob, aad, mac } => {
-            Hmac256Ctr::decrypt(blob, mac, aad.as_ref().map_or(&[], |v| v), &dem_key)
+    }
+}
+
+/// If the ciphertext commits to a key, check that the reconstructed key is the committed one.
+fn verify_key_commitment(
+    ciphertext: &Ciphertext,
+    base_key: &[u8; KEY_SIZE],
+) -> FastCryptoResult<()> {
+    if let Ciphertext::CommittingAes256Gcm { commitment, .. } = ciphertext {
+        if commitment != &derive_key(KeyPurpose::KeyCommitment, base_key) {
+            return Err(GeneralError("Invalid key commitment".to_string()));
         }
-        Ciphertext::Plain => Ok(dem_key.to_vec()),
     }
+    Ok(())
+}
+
+/// Re-seal an encrypted object for a new set of key servers and threshold without re-encrypting the ciphertext. The
+/// base key is secret shared again between the new key servers, and the ciphertext is left untouched.
+///
+/// Only the shares of the primary policy are replaced. Alternative policies and recovery recipients are kept as they
+/// are and still wrap the same base key, so they can decrypt the re-sealed object. To revoke access for any of them,
+/// decrypt the object and encrypt it again with a fresh base key instead, e.g., with [seal_encrypt_with_recovery].
+/// Anyone who kept a copy of the original object can also still decrypt it with the old key servers.
+///
+/// @param encrypted_object The encrypted object. See `seal_encrypt`.
+/// @param base_key The base key of the encrypted object. See `recover_base_key`.
+/// @param key_servers The seal key services to use for the new encryption.
+/// @param public_keys The public keys of the new key servers.
+/// @param threshold The new threshold for the TSS.
+/// @return The re-sealed encrypted object.
+pub fn reseal(
+    encrypted_object: &EncryptedObject,
+    base_key: &[u8; KEY_SIZE],
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+) -> FastCryptoResult<EncryptedObject> {
+    let policies = encrypted_object.policies()?;
+
+    // Check that the base key is the one used for the encrypted object
+    policies[0]
+        .verify_base_key(base_key)
+        .map_err(|_| GeneralError("Base key does not match the object".to_string()))?;
+
+    let (services, encrypted_shares) = encrypt_shares(
+        &mut thread_rng(),
+        base_key,
+        &policies[0].full_id,
+        key_servers,
+        public_keys,
+        threshold,
+        KeyPurpose::EncryptedRandomness,
+    )?;
+
+    Ok(EncryptedObject {
+        services,
+        threshold,
+        encrypted_shares,
+        ..encrypted_object.clone()
+    })
+}
+
+/// Secret share the base key between the given key servers and encrypt the shares using IBE.
+fn encrypt_shares<R: AllowedRng>(
+    rng: &mut R,
+    base_key: &[u8; KEY_SIZE],
+    full_id: &[u8],
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    randomness_purpose: KeyPurpose,
+) -> FastCryptoResult<(Vec<(ObjectID, u8)>, IBEEncryptions)> {
+    let number_of_shares = u8::try_from(key_servers.len()).map_err(|_| InvalidInput)?;
+    if threshold > number_of_shares || threshold == 0 {
+        return Err(InvalidInput);
+    }
+
+    // Secret share the derived key
+    let SecretSharing {
+        indices, shares, ..
+    } = split(rng, *base_key, threshold, number_of_shares)?;
+
+    let services = key_servers.into_iter().zip(indices).collect::<Vec<_>>();
+
+    let encrypted_shares = match public_keys {
+        IBEPublicKeys::BonehFranklinBLS12381(public_keys) => {
+            if public_keys.len() != number_of_shares as usize {
+                return Err(InvalidInput);
+            }
+            let randomness = ibe::Randomness::rand(rng);
+
+            // Encrypt the shares using the IBE keys.
+            // Use the share index as the `index` parameter for the IBE decryption, allowing to encrypt shares for the same identity to the same public key.
+            let (nonce, encrypted_shares) = encrypt_batched_deterministic(
+                &randomness,
+                &shares,
+                public_keys,
+                full_id,
+                &services,
+            )?;
+
+            let encrypted_randomness =
+                ibe::encrypt_randomness(&randomness, &derive_key(randomness_purpose, base_key));
+            IBEEncryptions::BonehFranklinBLS12381 {
+                nonce,
+                encrypted_shares,
+                encrypted_randomness,
+            }
+        }
+    };
+    Ok((services, encrypted_shares))
 }
 
 /// Create a full id from the [DST], a package id and an inner id. The result has the following format:
 /// [len(DST)][DST][package_id][id]
 pub fn create_full_id(package_id: &[u8; 32], id: &[u8]) -> Vec<u8> {
     assert!(DST.len() < 256);
-    
-    // Debug logging
-    println!("create_full_id - Input:");
-    println!("  DST: {:?}", hex::encode(DST));
-    println!("  DST length: {}", DST.len());
-    println!("  package_id: {:?}", hex::encode(package_id));
-    println!("  id: {:?}", hex::encode(id));
-    
     let mut full_id = vec![DST.len() as u8];
     full_id.extend_from_slice(DST);
     full_id.extend_from_slice(package_id);
     full_id.extend_from_slice(id);
-    
-    // Debug logging for result
-    println!("create_full_id - Result:");
-    println!("  full_id: {:?}", hex::encode(&full_id));
-    
     full_id
 }
 
 /// An enum representing the different purposes of the derived key.
+#[derive(Clone, Copy)]
 pub enum KeyPurpose {
     /// The key used to encrypt the encryption randomness.
     EncryptedRandomness,
     /// The key used by the DEM.
     DEM,
+    /// The commitment to the base key used by the committing DEMs.
+    KeyCommitment,
+    /// The key used to encrypt the encryption randomness of the alternative policy with the given index.
+    AlternativeEncryptedRandomness(u8),
 }
 
 /// Derive a key for a specific purpose from the base key.
@@ -328,6 +1402,10 @@ fn derive_key(purpose: KeyPurpose, derived_key: &[u8; KEY_SIZE]) -> [u8; KEY_SIZ
     match purpose {
         KeyPurpose::EncryptedRandomness => hmac_sha3_256(&hmac_key, &[0]).digest,
         KeyPurpose::DEM => hmac_sha3_256(&hmac_key, &[1]).digest,
+        KeyPurpose::KeyCommitment => hmac_sha3_256(&hmac_key, &[2]).digest,
+        KeyPurpose::AlternativeEncryptedRandomness(index) => {
+            hmac_sha3_256(&hmac_key, &[3, index]).digest
+        }
     }
 }
 
@@ -341,12 +1419,14 @@ impl IBEEncryptions {
         services: &[(ObjectID, u8)],
         public_keys: &IBEPublicKeys,
         base_key: &[u8; KEY_SIZE],
+        randomness_purpose: KeyPurpose,
     ) -> FastCryptoResult<()> {
         // Compute the entire polynomial from the given shares. Note that polynomial(0) = base_key.
         let polynomial = interpolate(shares)?;
 
         // Decrypt all shares using the derived key
-        let all_shares = self.decrypt_all_shares(full_id, services, public_keys, base_key)?;
+        let all_shares =
+            self.decrypt_all_shares(full_id, services, public_keys, base_key, randomness_purpose)?;
 
         // Check that all shares are points on the reconstructed polynomials
         if all_shares
@@ -365,6 +1445,7 @@ impl IBEEncryptions {
         services: &[(ObjectID, u8)],
         public_keys: &IBEPublicKeys,
         base_key: &[u8; KEY_SIZE],
+        randomness_purpose: KeyPurpose,
     ) -> FastCryptoResult<Vec<(u8, [u8; KEY_SIZE])>> {
         match self {
             IBEEncryptions::BonehFranklinBLS12381 {
@@ -375,7 +1456,7 @@ impl IBEEncryptions {
                 // Decrypt encrypted nonce,
                 let nonce = ibe::decrypt_and_verify_nonce(
                     encrypted_randomness,
-                    &derive_key(KeyPurpose::EncryptedRandomness, base_key),
+                    &derive_key(randomness_purpose, base_key),
                     nonce,
                 )?;
 
@@ -408,10 +1489,11 @@ mod tests {
         encoding::{Base64, Encoding},
         groups::{
             bls12381::{G1Element, Scalar},
-            HashToGroupElement,
+            GroupElement, HashToGroupElement,
         },
         serde_helpers::ToFromByteArray,
     };
+    use std::io::{Read, Write};
     use std::str::FromStr;
 
     #[test]
@@ -485,157 +1567,984 @@ mod tests {
     }
 
     #[test]
-    fn test_encryption_round_trip_hmac() {
+    fn test_encryption_round_trip_hmac() {
+        let data = b"Hello, World!";
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3, 4];
+
+        let full_id = create_full_id(&package_id, &id);
+
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..3)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+
+        let threshold = 2;
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
+
+        let encrypted = seal_encrypt(
+            package_id,
+            id,
+            services.clone(),
+            &public_keys,
+            threshold,
+            EncryptionInput::Hmac256Ctr {
+                data: data.to_vec(),
+                aad: Some(b"something".to_vec()),
+            },
+        )
+        .unwrap()
+        .0;
+
+        let user_secret_keys = IBEUserSecretKeys::BonehFranklinBLS12381(
+            services
+                .into_iter()
+                .zip(keypairs)
+                .map(|(s, kp)| (s, ibe::extract(&kp.0, &full_id)))
+                .collect(),
+        );
+        let decrypted = seal_decrypt(&encrypted, &user_secret_keys, Some(&public_keys)).unwrap();
+
+        assert_eq!(data, decrypted.as_slice());
+
+        // Check that decryption fails with different aad
+        let mut modified_encrypted = encrypted.clone();
+        match modified_encrypted.ciphertext {
+            Ciphertext::Hmac256Ctr { ref mut aad, .. } => {
+                match aad {
+                    None => panic!(),
+                    Some(ref mut aad) => aad.push(0),
+                }
+                assert!(
+                    seal_decrypt(&modified_encrypted, &user_secret_keys, Some(&public_keys))
+                        .is_err()
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_encryption_round_trip_chacha20_poly1305() {
+        let data = b"Hello, World!";
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3, 4];
+
+        let full_id = create_full_id(&package_id, &id);
+
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..3)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+
+        let threshold = 2;
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
+
+        let encrypted = seal_encrypt(
+            package_id,
+            id,
+            services.clone(),
+            &public_keys,
+            threshold,
+            EncryptionInput::ChaCha20Poly1305 {
+                data: data.to_vec(),
+                aad: Some(b"something".to_vec()),
+            },
+        )
+        .unwrap()
+        .0;
+
+        let user_secret_keys = IBEUserSecretKeys::BonehFranklinBLS12381(
+            services
+                .into_iter()
+                .zip(keypairs)
+                .map(|(s, kp)| (s, ibe::extract(&kp.0, &full_id)))
+                .collect(),
+        );
+        let decrypted = seal_decrypt(&encrypted, &user_secret_keys, Some(&public_keys)).unwrap();
+
+        assert_eq!(data, decrypted.as_slice());
+
+        // Check that decryption fails with different aad
+        let mut modified_encrypted = encrypted.clone();
+        match modified_encrypted.ciphertext {
+            Ciphertext::ChaCha20Poly1305 { ref mut aad, .. } => {
+                match aad {
+                    None => panic!(),
+                    Some(ref mut aad) => aad.push(0),
+                }
+                assert!(
+                    seal_decrypt(&modified_encrypted, &user_secret_keys, Some(&public_keys))
+                        .is_err()
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    /// The serialization of the ciphertext variants must match the `Ciphertext` enum in the TypeScript SDK.
+    #[test]
+    fn typescript_ciphertext_alignment() {
+        let ciphertexts = [
+            (
+                Ciphertext::Aes256Gcm {
+                    blob: vec![1, 2, 3],
+                    aad: Some(vec![4]),
+                },
+                "0003010203010104",
+            ),
+            (Ciphertext::Plain, "02"),
+            (
+                Ciphertext::Aes256GcmStream {
+                    chunk_size: 65536,
+                    aad: None,
+                },
+                "030000010000",
+            ),
+            (
+                Ciphertext::ChaCha20Poly1305 {
+                    blob: vec![1, 2, 3],
+                    aad: Some(vec![4]),
+                },
+                "0403010203010104",
+            ),
+            (
+                Ciphertext::CommittingAes256Gcm {
+                    blob: vec![1, 2, 3],
+                    aad: None,
+                    commitment: [5; KEY_SIZE],
+                },
+                "0503010203000505050505050505050505050505050505050505050505050505050505050505",
+            ),
+        ];
+        for (ciphertext, expected) in ciphertexts {
+            assert_eq!(hex::encode(bcs::to_bytes(&ciphertext).unwrap()), expected);
+        }
+    }
+
+    #[test]
+    fn typescript_encrypted_object_alignment() {
+        // The version 0 object of test_encrypted_object_versions with the namespace of the other versions
+        let v0 = Base64::decode("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAECAwQDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE4AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAALKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM3AgCEgtXcUe2iGMS8zEMEB9YVJo4WbdUuW7uqNBLEJc+xA0pnC6TNep2SGpudVO3gXtAG7W4lSNmc/xMhFv9WDfaTZfppIk7H6IXEmM8aUfjk6TyXtMO2D5T0PzB3HhTNIo4De81Z5tb7mnshJWTjJtHBoeWWUpoSunAGQQAWsGFQ5NK9AnAugziSj/SnS5I042nRGswaeMmTBG5+FyLP1FJPSadWZGTQSZzQGcRVVefDJw5gUxUVMhT+CfesAVHHZKkanKv0UhCEy3EnKc6Bkrl09fSLqo7hTKwqNxCJf9oaHhkAJ81y6phEffQ8F4xsbi87mpR05qGNtzvbyh/Y4PLhhL8yQyy4gxhPHwEEAQIDBA==").unwrap();
+        let mut object: EncryptedObject = bcs::from_bytes(&v0).unwrap();
+        object.namespace = Namespace::Evm {
+            chain_id: 84532,
+            address: [1; 20],
+        };
+
+        object.version = 1;
+        assert_eq!(
+            hex::encode(bcs::to_bytes(&object).unwrap()),
+            "0101344a01000000000001010101010101010101010101010101010101010401020304030000000000000000000000000000000000000000000000000000000000000001380000000000000000000000000000000000000000000000000000000000000002ca00000000000000000000000000000000000000000000000000000000000000033702008482d5dc51eda218c4bccc430407d615268e166dd52e5bbbaa3412c425cfb1034a670ba4cd7a9d921a9b9d54ede05ed006ed6e2548d99cff132116ff560df69365fa69224ec7e885c498cf1a51f8e4e93c97b4c3b60f94f43f30771e14cd228e037bcd59e6d6fb9a7b212564e326d1c1a1e596529a12ba7006410016b06150e4d2bd02702e8338928ff4a74b9234e369d11acc1a78c993046e7e1722cfd4524f49a7566464d0499cd019c45555e7c3270e605315153214fe09f7ac0151c764a91a9cabf4521084cb712729ce8192b974f5f48baa8ee14cac2a3710897fda1a1e190027cd72ea98447df43c178c6c6e2f3b9a9474e6a18db73bdbca1fd8e0f2e184bf32432cb883184f1f010401020304"
+        );
+
+        object.version = 2;
+        object.alternatives = vec![AlternativePolicy {
+            namespace: Namespace::SuiPackage(ObjectID::new([2; 32])),
+            id: vec![5],
+            services: vec![],
+            threshold: 1,
+            encrypted_shares: object.encrypted_shares.clone(),
+        }];
+        assert_eq!(
+            hex::encode(bcs::to_bytes(&object).unwrap()),
+            "0201344a01000000000001010101010101010101010101010101010101010100020202020202020202020202020202020202020202020202020202020202020201050001008482d5dc51eda218c4bccc430407d615268e166dd52e5bbbaa3412c425cfb1034a670ba4cd7a9d921a9b9d54ede05ed006ed6e2548d99cff132116ff560df69365fa69224ec7e885c498cf1a51f8e4e93c97b4c3b60f94f43f30771e14cd228e037bcd59e6d6fb9a7b212564e326d1c1a1e596529a12ba7006410016b06150e4d2bd02702e8338928ff4a74b9234e369d11acc1a78c993046e7e1722cfd4524f49a7566464d0499cd019c45555e7c3270e605315153214fe09f7ac0151c764a91a9cabf4521084cb712729ce8192b974f5f48baa8ee14cac2a3710897fda1a1e190401020304030000000000000000000000000000000000000000000000000000000000000001380000000000000000000000000000000000000000000000000000000000000002ca00000000000000000000000000000000000000000000000000000000000000033702008482d5dc51eda218c4bccc430407d615268e166dd52e5bbbaa3412c425cfb1034a670ba4cd7a9d921a9b9d54ede05ed006ed6e2548d99cff132116ff560df69365fa69224ec7e885c498cf1a51f8e4e93c97b4c3b60f94f43f30771e14cd228e037bcd59e6d6fb9a7b212564e326d1c1a1e596529a12ba7006410016b06150e4d2bd02702e8338928ff4a74b9234e369d11acc1a78c993046e7e1722cfd4524f49a7566464d0499cd019c45555e7c3270e605315153214fe09f7ac0151c764a91a9cabf4521084cb712729ce8192b974f5f48baa8ee14cac2a3710897fda1a1e190027cd72ea98447df43c178c6c6e2f3b9a9474e6a18db73bdbca1fd8e0f2e184bf32432cb883184f1f010401020304"
+        );
+
+        object.version = 3;
+        object.alternatives = vec![];
+        object.recovery = vec![RecoveryRecipient::EciesBLS12381 {
+            public_key: G1Element::generator(),
+            ephemeral_key: G1Element::generator(),
+            encrypted_key: vec![6],
+        }];
+        assert_eq!(
+            hex::encode(bcs::to_bytes(&object).unwrap()),
+            "0301344a010000000000010101010101010101010101010101010101010100010097f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb01060401020304030000000000000000000000000000000000000000000000000000000000000001380000000000000000000000000000000000000000000000000000000000000002ca00000000000000000000000000000000000000000000000000000000000000033702008482d5dc51eda218c4bccc430407d615268e166dd52e5bbbaa3412c425cfb1034a670ba4cd7a9d921a9b9d54ede05ed006ed6e2548d99cff132116ff560df69365fa69224ec7e885c498cf1a51f8e4e93c97b4c3b60f94f43f30771e14cd228e037bcd59e6d6fb9a7b212564e326d1c1a1e596529a12ba7006410016b06150e4d2bd02702e8338928ff4a74b9234e369d11acc1a78c993046e7e1722cfd4524f49a7566464d0499cd019c45555e7c3270e605315153214fe09f7ac0151c764a91a9cabf4521084cb712729ce8192b974f5f48baa8ee14cac2a3710897fda1a1e190027cd72ea98447df43c178c6c6e2f3b9a9474e6a18db73bdbca1fd8e0f2e184bf32432cb883184f1f010401020304"
+        );
+    }
+
+    #[test]
+    fn test_plain_round_trip() {
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3, 4];
+        let full_id = create_full_id(&package_id, &id);
+
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..3)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+
+        let threshold = 2;
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
+
+        let (encrypted, key) = seal_encrypt(
+            package_id,
+            id,
+            services.clone(),
+            &public_keys,
+            threshold,
+            EncryptionInput::Plain,
+        )
+        .unwrap();
+
+        let user_secret_keys = services
+            .into_iter()
+            .zip(keypairs)
+            .map(|(s, kp)| (s, ibe::extract(&kp.0, &full_id)))
+            .collect();
+
+        assert_eq!(
+            key.to_vec(),
+            seal_decrypt(
+                &encrypted,
+                &IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
+                Some(&public_keys),
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encryption_round_trip_stream() {
+        let data = vec![7u8; 1000];
+        let aad = vec![0, 1, 2];
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3, 4];
+        let full_id = create_full_id(&package_id, &id);
+
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..3)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+
+        let threshold = 2;
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
+
+        let (encrypted, key) = seal_encrypt(
+            package_id,
+            id,
+            services.clone(),
+            &public_keys,
+            threshold,
+            EncryptionInput::Aes256GcmStream {
+                chunk_size: 64,
+                aad: Some(aad.clone()),
+            },
+        )
+        .unwrap();
+        let mut encryptor = dem::stream::Encryptor::new(vec![], &key, 64, &aad).unwrap();
+        encryptor.write_all(&data).unwrap();
+        let blob = encryptor.finish().unwrap();
+
+        let user_secret_keys = services
+            .into_iter()
+            .zip(keypairs)
+            .map(|(s, kp)| (s, ibe::extract(&kp.0, &full_id)))
+            .collect();
+        let decrypted_key: [u8; KEY_SIZE] = seal_decrypt(
+            &encrypted,
+            &IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
+            Some(&public_keys),
+        )
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+        let (chunk_size, aad) = match encrypted.ciphertext {
+            Ciphertext::Aes256GcmStream { chunk_size, aad } => (chunk_size, aad.unwrap()),
+            _ => panic!(),
+        };
+        let mut decrypted = vec![];
+        dem::stream::Decryptor::new(blob.as_slice(), &decrypted_key, chunk_size, &aad)
+            .unwrap()
+            .read_to_end(&mut decrypted)
+            .unwrap();
+        assert_eq!(data, decrypted);
+    }
+
+    #[test]
+    fn typescript_test_vector() {
+        let package_id = [0u8; 32];
+        let inner_id = [1, 2, 3, 4];
+
+        let master_keys = [
+            "KPUXJQxoijA276hI6XhNVgIewyaija8UABeFTwEeD6k=",
+            "AwuqCSqP/vHF+/roqrhjzKj070ouLFGWkYr9msDv9eQ=",
+            "JyScQKCG091JJvmedlGFO+lBmsZKynKe3h8jbUlCA7o=",
+        ]
+        .iter()
+        .map(|key| {
+            Scalar::from_byte_array(&Base64::decode(key).unwrap().try_into().unwrap()).unwrap()
+        })
+        .collect::<Vec<_>>();
+        let public_keys = master_keys
+            .iter()
+            .map(ibe::public_key_from_master_key)
+            .collect_vec();
+
+        let encryption = Base64::decode("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAECAwQDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE4AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAALKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM3AgCEgtXcUe2iGMS8zEMEB9YVJo4WbdUuW7uqNBLEJc+xA0pnC6TNep2SGpudVO3gXtAG7W4lSNmc/xMhFv9WDfaTZfppIk7H6IXEmM8aUfjk6TyXtMO2D5T0PzB3HhTNIo4De81Z5tb7mnshJWTjJtHBoeWWUpoSunAGQQAWsGFQ5NK9AnAugziSj/SnS5I042nRGswaeMmTBG5+FyLP1FJPSadWZGTQSZzQGcRVVefDJw5gUxUVMhT+CfesAVHHZKkanKv0UhCEy3EnKc6Bkrl09fSLqo7hTKwqNxCJf9oaHhkAJ81y6phEffQ8F4xsbi87mpR05qGNtzvbyh/Y4PLhhL8yQyy4gxhPHwEEAQIDBA==").unwrap();
+        let encryption: EncryptedObject = bcs::from_bytes(&encryption).unwrap();
+
+        let object_ids = [
+            "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "0x0000000000000000000000000000000000000000000000000000000000000002",
+            "0x0000000000000000000000000000000000000000000000000000000000000003",
+        ]
+        .iter()
+        .map(|id| ObjectID::from_str(id).unwrap())
+        .collect::<Vec<_>>();
+
+        let full_id = create_full_id(&package_id, &inner_id);
+        let user_secret_keys = object_ids
+            .into_iter()
+            .zip(master_keys)
+            .map(|(s, k)| (s, ibe::extract(&k, &full_id)))
+            .collect();
+
+        let decrypted = seal_decrypt(
+            &encryption,
+            &IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
+            Some(&IBEPublicKeys::BonehFranklinBLS12381(public_keys)),
+        )
+        .unwrap();
+
+        assert_eq!(decrypted, b"My super secret message");
+    }
+
+    #[test]
+    fn test_encrypted_object_versions() {
+        // Version 0 objects are serialized as before
+        let v0 = Base64::decode("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAECAwQDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE4AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAALKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM3AgCEgtXcUe2iGMS8zEMEB9YVJo4WbdUuW7uqNBLEJc+xA0pnC6TNep2SGpudVO3gXtAG7W4lSNmc/xMhFv9WDfaTZfppIk7H6IXEmM8aUfjk6TyXtMO2D5T0PzB3HhTNIo4De81Z5tb7mnshJWTjJtHBoeWWUpoSunAGQQAWsGFQ5NK9AnAugziSj/SnS5I042nRGswaeMmTBG5+FyLP1FJPSadWZGTQSZzQGcRVVefDJw5gUxUVMhT+CfesAVHHZKkanKv0UhCEy3EnKc6Bkrl09fSLqo7hTKwqNxCJf9oaHhkAJ81y6phEffQ8F4xsbi87mpR05qGNtzvbyh/Y4PLhhL8yQyy4gxhPHwEEAQIDBA==").unwrap();
+        let object: EncryptedObject = bcs::from_bytes(&v0).unwrap();
+        assert_eq!(object.version, 0);
+        assert_eq!(
+            object.namespace,
+            Namespace::SuiPackage(ObjectID::new([0; 32]))
+        );
+        assert_eq!(
+            object.full_id().unwrap(),
+            create_full_id(&[0; 32], &object.id)
+        );
+        assert_eq!(bcs::to_bytes(&object).unwrap(), v0);
+
+        // Version 1 objects
+        let mut object = object;
+        object.version = 1;
+        object.namespace = Namespace::Evm {
+            chain_id: 84532,
+            address: [1; 20],
+        };
+        let v1 = bcs::to_bytes(&object).unwrap();
+        assert_eq!(v1[..3], [1, 1, 0x34]);
+        let parsed: EncryptedObject = bcs::from_bytes(&v1).unwrap();
+        assert_eq!(parsed.version, 1);
+        assert_eq!(parsed.namespace, object.namespace);
+        assert_eq!(bcs::to_bytes(&parsed).unwrap(), v1);
+
+        // Version 2 objects have alternative policies after the namespace
+        object.version = 2;
+        object.alternatives = vec![AlternativePolicy {
+            namespace: Namespace::SuiPackage(ObjectID::new([2; 32])),
+            id: vec![5],
+            services: vec![],
+            threshold: 1,
+            encrypted_shares: object.encrypted_shares.clone(),
+        }];
+        let v2 = bcs::to_bytes(&object).unwrap();
+        assert_eq!(v2[..3], [2, 1, 0x34]);
+        assert_eq!(v2[30..33], [1, 0, 2]);
+        let parsed: EncryptedObject = bcs::from_bytes(&v2).unwrap();
+        assert_eq!(parsed.version, 2);
+        assert_eq!(parsed.alternatives[0].id, vec![5]);
+        assert_eq!(bcs::to_bytes(&parsed).unwrap(), v2);
+
+        // Version 3 objects have recovery recipients after the alternative policies
+        object.version = 3;
+        let alternatives = std::mem::take(&mut object.alternatives);
+        let (_, public_key) = recovery::generate_key_pair(&mut thread_rng());
+        object.recovery = vec![RecoveryRecipient::EciesBLS12381 {
+            public_key,
+            ephemeral_key: public_key,
+            encrypted_key: vec![6],
+        }];
+        let v3 = bcs::to_bytes(&object).unwrap();
+        assert_eq!(v3[..3], [3, 1, 0x34]);
+        assert_eq!(v3[30..33], [0, 1, 0]);
+        let parsed: EncryptedObject = bcs::from_bytes(&v3).unwrap();
+        assert_eq!(parsed.version, 3);
+        assert_eq!(parsed.recovery.len(), 1);
+        assert_eq!(bcs::to_bytes(&parsed).unwrap(), v3);
+
+        // Only version 3 objects may have recovery recipients
+        object.version = 2;
+        assert!(bcs::to_bytes(&object).is_err());
+        object.recovery = vec![];
+
+        // Only version 2 and 3 objects may have alternatives
+        object.version = 1;
+        object.alternatives = alternatives;
+        assert!(bcs::to_bytes(&object).is_err());
+        object.alternatives = vec![];
+
+        // Only Sui packages are supported in version 0, and there are no other versions
+        object.version = 0;
+        assert!(bcs::to_bytes(&object).is_err());
+        assert!(object.full_id().is_err());
+        object.version = 4;
+        assert!(bcs::to_bytes(&object).is_err());
+        let mut v4 = v1.clone();
+        v4[0] = 4;
+        assert!(bcs::from_bytes::<EncryptedObject>(&v4).is_err());
+    }
+
+    #[test]
+    fn test_namespace_full_ids() {
+        let id = [1, 2, 3, 4];
+        let package_id = ObjectID::random();
+        let evm = Namespace::Evm {
+            chain_id: 1,
+            address: [7; 20],
+        };
+        let solana = Namespace::Solana { program: [7; 32] };
+
+        assert_eq!(
+            Namespace::SuiPackage(package_id).full_id(&id),
+            create_full_id(&package_id, &id)
+        );
+        assert_eq!(
+            evm.full_id(&id),
+            [
+                &[DST_EVM.len() as u8],
+                DST_EVM,
+                &1u64.to_le_bytes(),
+                &[7; 20],
+                &id
+            ]
+            .concat()
+        );
+        assert_ne!(
+            evm.full_id(&id),
+            Namespace::Evm {
+                chain_id: 2,
+                address: [7; 20]
+            }
+            .full_id(&id)
+        );
+        assert_ne!(solana.full_id(&id), evm.full_id(&id));
+
+        // Round trip for an object in a Solana namespace
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..2)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
+        let (encrypted, _) = seal_encrypt_with_namespace(
+            solana.clone(),
+            id.to_vec(),
+            services.clone(),
+            &public_keys,
+            2,
+            EncryptionInput::Aes256Gcm {
+                data: b"Hello, World!".to_vec(),
+                aad: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(encrypted.version, 1);
+        let full_id = solana.full_id(&id);
+        let user_secret_keys = services
+            .into_iter()
+            .zip(keypairs)
+            .map(|(s, kp)| (s, ibe::extract(&kp.0, &full_id)))
+            .collect();
+        assert_eq!(
+            seal_decrypt(
+                &encrypted,
+                &IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
+                Some(&public_keys),
+            )
+            .unwrap(),
+            b"Hello, World!"
+        );
+    }
+
+    #[test]
+    fn test_weighted_encryption() {
+        let data = b"Hello, World!";
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3, 4];
+        let full_id = create_full_id(&package_id, &id);
+
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..3)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
+        let user_secret_keys: HashMap<_, _> = services
+            .iter()
+            .zip(&keypairs)
+            .map(|(s, kp)| (*s, ibe::extract(&kp.0, &full_id)))
+            .collect();
+
+        let (encrypted, _) = seal_encrypt_weighted(
+            package_id,
+            id.clone(),
+            services.clone(),
+            &[3, 1, 1],
+            &public_keys,
+            3,
+            EncryptionInput::Aes256Gcm {
+                data: data.to_vec(),
+                aad: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            encrypted.services.iter().map(|(_, i)| *i).collect_vec(),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            encrypted.services.iter().map(|(s, _)| *s).collect_vec(),
+            vec![
+                services[0],
+                services[0],
+                services[0],
+                services[1],
+                services[2]
+            ]
+        );
+
+        // The heavy key server alone reaches the threshold
+        let keys_of = |indices: &[usize]| {
+            IBEUserSecretKeys::BonehFranklinBLS12381(
+                indices
+                    .iter()
+                    .map(|i| (services[*i], user_secret_keys[&services[*i]]))
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            seal_decrypt(&encrypted, &keys_of(&[0]), None).unwrap(),
+            data
+        );
+
+        // The two light key servers do not
+        assert!(seal_decrypt(&encrypted, &keys_of(&[1, 2]), None).is_err());
+
+        // Consistency check with the public keys of each share
+        let share_public_keys = encrypted
+            .share_public_keys(
+                &services
+                    .iter()
+                    .zip(&keypairs)
+                    .map(|(s, (_, pk))| (*s, *pk))
+                    .collect(),
+            )
+            .unwrap();
+        assert_eq!(
+            seal_decrypt(&encrypted, &keys_of(&[1, 2, 0]), Some(&share_public_keys)).unwrap(),
+            data
+        );
+        assert!(seal_decrypt(&encrypted, &keys_of(&[0]), Some(&public_keys)).is_err());
+
+        // Invalid weights
+        for weights in [&[3, 1][..], &[3, 0, 1], &[200, 50, 6]] {
+            assert!(seal_encrypt_weighted(
+                package_id,
+                id.clone(),
+                services.clone(),
+                weights,
+                &public_keys,
+                2,
+                EncryptionInput::Plain,
+            )
+            .is_err());
+        }
+        assert!(seal_encrypt_weighted(
+            package_id,
+            id.clone(),
+            services.clone(),
+            &[1, 1, 1],
+            &public_keys,
+            4,
+            EncryptionInput::Plain,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_robust_decryption() {
+        let data = b"Hello, World!";
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3, 4];
+        let full_id = create_full_id(&package_id, &id);
+
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..4)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
+
+        let (encrypted, _) = seal_encrypt(
+            package_id,
+            id,
+            services.clone(),
+            &public_keys,
+            2,
+            EncryptionInput::Aes256Gcm {
+                data: data.to_vec(),
+                aad: None,
+            },
+        )
+        .unwrap();
+
+        // The second and fourth key servers return keys for another master key
+        let user_secret_keys = |servers: &[usize]| {
+            IBEUserSecretKeys::BonehFranklinBLS12381(
+                servers
+                    .iter()
+                    .map(|i| {
+                        let master_key = if i % 2 == 1 {
+                            ibe::generate_key_pair(&mut rand::thread_rng()).0
+                        } else {
+                            keypairs[*i].0
+                        };
+                        (services[*i], ibe::extract(&master_key, &full_id))
+                    })
+                    .collect(),
+            )
+        };
+        let all_keys = user_secret_keys(&[0, 1, 2, 3]);
+        assert!(seal_decrypt(&encrypted, &all_keys, None).is_err());
+
+        let expected = RobustDecryption {
+            plaintext: data.to_vec(),
+            faulty_key_servers: vec![services[1], services[3]],
+        };
+        assert_eq!(
+            seal_decrypt_robust(&encrypted, &all_keys, None).unwrap(),
+            expected
+        );
+        assert_eq!(
+            seal_decrypt_robust(&encrypted, &all_keys, Some(&public_keys)).unwrap(),
+            expected
+        );
+
+        // No faulty key servers
+        assert_eq!(
+            seal_decrypt_robust(&encrypted, &user_secret_keys(&[0, 2]), None)
+                .unwrap()
+                .faulty_key_servers,
+            vec![]
+        );
+
+        // Not enough valid keys
+        assert!(seal_decrypt_robust(&encrypted, &user_secret_keys(&[0, 1, 3]), None).is_err());
+        assert!(
+            seal_decrypt_robust(&encrypted, &user_secret_keys(&[0, 1]), Some(&public_keys))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_robust_decryption_many_key_servers() {
         let data = b"Hello, World!";
         let package_id = ObjectID::random();
         let id = vec![1, 2, 3, 4];
-
         let full_id = create_full_id(&package_id, &id);
 
         let mut rng = rand::thread_rng();
-        let keypairs = (0..3)
+        let keypairs = (0..12)
             .map(|_| ibe::generate_key_pair(&mut rng))
             .collect_vec();
-
         let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
-
-        let threshold = 2;
         let public_keys =
             IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
 
-        let encrypted = seal_encrypt(
+        let (encrypted, _) = seal_encrypt(
             package_id,
             id,
             services.clone(),
             &public_keys,
-            threshold,
-            EncryptionInput::Hmac256Ctr {
+            8,
+            EncryptionInput::Aes256Gcm {
                 data: data.to_vec(),
-                aad: Some(b"something".to_vec()),
+                aad: None,
             },
         )
-        .unwrap()
-        .0;
+        .unwrap();
 
+        // The first key server returns a key for another master key, so the subsets of size 8 containing it are tried
+        // first. Those are C(11, 7) = 330 subsets.
+        let faulty_master_key = ibe::generate_key_pair(&mut rng).0;
         let user_secret_keys = IBEUserSecretKeys::BonehFranklinBLS12381(
             services
-                .into_iter()
-                .zip(keypairs)
-                .map(|(s, kp)| (s, ibe::extract(&kp.0, &full_id)))
+                .iter()
+                .zip(&keypairs)
+                .enumerate()
+                .map(|(i, (service, (master_key, _)))| {
+                    let master_key = if i == 0 {
+                        &faulty_master_key
+                    } else {
+                        master_key
+                    };
+                    (*service, ibe::extract(master_key, &full_id))
+                })
                 .collect(),
         );
-        let decrypted = seal_decrypt(&encrypted, &user_secret_keys, Some(&public_keys)).unwrap();
-
-        assert_eq!(data, decrypted.as_slice());
-
-        // Check that decryption fails with different aad
-        let mut modified_encrypted = encrypted.clone();
-        match modified_encrypted.ciphertext {
-            Ciphertext::Hmac256Ctr { ref mut aad, .. } => {
-                match aad {
-                    None => panic!(),
-                    Some(ref mut aad) => aad.push(0),
-                }
-                assert!(
-                    seal_decrypt(&modified_encrypted, &user_secret_keys, Some(&public_keys))
-                        .is_err()
-                );
+        assert_eq!(
+            seal_decrypt_robust(&encrypted, &user_secret_keys, None).unwrap(),
+            RobustDecryption {
+                plaintext: data.to_vec(),
+                faulty_key_servers: vec![services[0]],
             }
-            _ => panic!(),
-        }
+        );
+
+        // With the public keys, the faulty key is dropped before combining the shares
+        assert_eq!(
+            seal_decrypt_robust(&encrypted, &user_secret_keys, Some(&public_keys))
+                .unwrap()
+                .faulty_key_servers,
+            vec![services[0]]
+        );
     }
 
     #[test]
-    fn test_plain_round_trip() {
-        let package_id = ObjectID::random();
-        let id = vec![1, 2, 3, 4];
-        let full_id = create_full_id(&package_id, &id);
-
+    fn test_alternative_policies() {
+        let data = b"Hello, World!";
         let mut rng = rand::thread_rng();
         let keypairs = (0..3)
             .map(|_| ibe::generate_key_pair(&mut rng))
             .collect_vec();
-
         let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+        let public_keys_of = |servers: &[usize]| {
+            IBEPublicKeys::BonehFranklinBLS12381(servers.iter().map(|i| keypairs[*i].1).collect())
+        };
 
-        let threshold = 2;
-        let public_keys =
-            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
-
-        let (encrypted, key) = seal_encrypt(
-            package_id,
-            id,
-            services.clone(),
-            &public_keys,
-            threshold,
-            EncryptionInput::Plain,
+        // The primary policy uses the first two key servers and the alternative the last two
+        let subscription = Namespace::SuiPackage(ObjectID::random());
+        let owner = Namespace::Evm {
+            chain_id: 1,
+            address: [7; 20],
+        };
+        let (encrypted, _) = seal_encrypt_with_alternatives(
+            subscription.clone(),
+            vec![1],
+            vec![services[0], services[1]],
+            &public_keys_of(&[0, 1]),
+            2,
+            vec![PolicyInput {
+                namespace: owner.clone(),
+                id: vec![2],
+                key_servers: vec![services[1], services[2]],
+                public_keys: public_keys_of(&[1, 2]),
+                threshold: 2,
+            }],
+            EncryptionInput::Aes256Gcm {
+                data: data.to_vec(),
+                aad: None,
+            },
         )
         .unwrap();
-
-        let user_secret_keys = services
-            .into_iter()
-            .zip(keypairs)
-            .map(|(s, kp)| (s, ibe::extract(&kp.0, &full_id)))
-            .collect();
-
+        assert_eq!(encrypted.version, 2);
+        let full_ids = encrypted.full_ids().unwrap();
         assert_eq!(
-            key.to_vec(),
-            seal_decrypt(
-                &encrypted,
-                &IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
-                Some(&public_keys),
-            )
-            .unwrap()
+            full_ids,
+            vec![subscription.full_id(&[1]), owner.full_id(&[2])]
         );
+
+        let encrypted: EncryptedObject =
+            bcs::from_bytes(&bcs::to_bytes(&encrypted).unwrap()).unwrap();
+        let user_secret_keys = |full_id: &[u8], servers: &[usize]| {
+            IBEUserSecretKeys::BonehFranklinBLS12381(
+                servers
+                    .iter()
+                    .map(|i| (services[*i], ibe::extract(&keypairs[*i].0, full_id)))
+                    .collect(),
+            )
+        };
+
+        // Keys for either policy decrypt the object
+        for (full_id, servers) in [(&full_ids[0], [0, 1]), (&full_ids[1], [1, 2])] {
+            let keys = user_secret_keys(full_id, &servers);
+            assert_eq!(seal_decrypt(&encrypted, &keys, None).unwrap(), data);
+            assert_eq!(
+                seal_decrypt(&encrypted, &keys, Some(&public_keys_of(&servers))).unwrap(),
+                data
+            );
+            assert_eq!(
+                seal_decrypt_robust(&encrypted, &keys, None)
+                    .unwrap()
+                    .plaintext,
+                data
+            );
+        }
+
+        // Keys for the wrong identity or from different policies do not
+        assert!(seal_decrypt(&encrypted, &user_secret_keys(&full_ids[0], &[1, 2]), None).is_err());
+        let mixed = IBEUserSecretKeys::BonehFranklinBLS12381(HashMap::from([
+            (services[0], ibe::extract(&keypairs[0].0, &full_ids[0])),
+            (services[2], ibe::extract(&keypairs[2].0, &full_ids[1])),
+        ]));
+        assert!(seal_decrypt(&encrypted, &mixed, None).is_err());
     }
 
     #[test]
-    fn typescript_test_vector() {
-        let package_id = [0u8; 32];
-        let inner_id = [1, 2, 3, 4];
-
-        let master_keys = [
-            "KPUXJQxoijA276hI6XhNVgIewyaija8UABeFTwEeD6k=",
-            "AwuqCSqP/vHF+/roqrhjzKj070ouLFGWkYr9msDv9eQ=",
-            "JyScQKCG091JJvmedlGFO+lBmsZKynKe3h8jbUlCA7o=",
-        ]
-        .iter()
-        .map(|key| {
-            Scalar::from_byte_array(&Base64::decode(key).unwrap().try_into().unwrap()).unwrap()
-        })
-        .collect::<Vec<_>>();
-        let public_keys = master_keys
-            .iter()
-            .map(ibe::public_key_from_master_key)
+    fn test_batch_encryption() {
+        let mut rng = rand::thread_rng();
+        let package_id = ObjectID::random();
+        let keypairs = (0..3)
+            .map(|_| ibe::generate_key_pair(&mut rng))
             .collect_vec();
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
 
-        let encryption = Base64::decode("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAECAwQDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE4AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAALKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM3AgCEgtXcUe2iGMS8zEMEB9YVJo4WbdUuW7uqNBLEJc+xA0pnC6TNep2SGpudVO3gXtAG7W4lSNmc/xMhFv9WDfaTZfppIk7H6IXEmM8aUfjk6TyXtMO2D5T0PzB3HhTNIo4De81Z5tb7mnshJWTjJtHBoeWWUpoSunAGQQAWsGFQ5NK9AnAugziSj/SnS5I042nRGswaeMmTBG5+FyLP1FJPSadWZGTQSZzQGcRVVefDJw5gUxUVMhT+CfesAVHHZKkanKv0UhCEy3EnKc6Bkrl09fSLqo7hTKwqNxCJf9oaHhkAJ81y6phEffQ8F4xsbi87mpR05qGNtzvbyh/Y4PLhhL8yQyy4gxhPHwEEAQIDBA==").unwrap();
-        let encryption: EncryptedObject = bcs::from_bytes(&encryption).unwrap();
+        let items = (0..20u8)
+            .map(|i| {
+                (
+                    vec![i],
+                    EncryptionInput::Aes256Gcm {
+                        data: vec![i; 10],
+                        aad: None,
+                    },
+                )
+            })
+            .collect_vec();
+        let encrypted =
+            seal_encrypt_batch(package_id, items, services.clone(), &public_keys, 2).unwrap();
+        assert_eq!(encrypted.len(), 20);
+        assert_eq!(
+            encrypted.iter().map(|(_, key)| key).unique().count(),
+            encrypted.len()
+        );
 
-        let object_ids = [
-            "0x0000000000000000000000000000000000000000000000000000000000000001",
-            "0x0000000000000000000000000000000000000000000000000000000000000002",
-            "0x0000000000000000000000000000000000000000000000000000000000000003",
-        ]
-        .iter()
-        .map(|id| ObjectID::from_str(id).unwrap())
-        .collect::<Vec<_>>();
+        for (i, (object, _)) in encrypted.iter().enumerate() {
+            assert_eq!(object.id, vec![i as u8]);
+            let full_id = object.full_id().unwrap();
+            let user_secret_keys = IBEUserSecretKeys::BonehFranklinBLS12381(
+                services
+                    .iter()
+                    .zip(&keypairs)
+                    .take(2)
+                    .map(|(id, (sk, _))| (*id, ibe::extract(sk, &full_id)))
+                    .collect(),
+            );
+            assert_eq!(
+                seal_decrypt(object, &user_secret_keys, Some(&public_keys)).unwrap(),
+                vec![i as u8; 10]
+            );
+        }
 
-        let full_id = create_full_id(&package_id, &inner_id);
-        let user_secret_keys = object_ids
-            .into_iter()
-            .zip(master_keys)
-            .map(|(s, k)| (s, ibe::extract(&k, &full_id)))
-            .collect();
+        // Invalid key servers are rejected
+        assert!(seal_encrypt_batch(package_id, vec![], services.clone(), &public_keys, 4).is_err());
+        assert!(
+            seal_encrypt_batch(package_id, vec![], services[..2].to_vec(), &public_keys, 2)
+                .is_err()
+        );
+    }
 
-        let decrypted = seal_decrypt(
-            &encryption,
-            &IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
-            Some(&IBEPublicKeys::BonehFranklinBLS12381(public_keys)),
+    #[test]
+    fn test_recovery() {
+        let data = b"Hello, World!";
+        let mut rng = rand::thread_rng();
+        let (master_key, public_key) = ibe::generate_key_pair(&mut rng);
+        let service = ObjectID::random();
+        let namespace = Namespace::SuiPackage(ObjectID::random());
+        // The recipients may hold keys on any of the supported curves
+        let (owner_key, owner_public_key) = recovery::generate_key_pair(&mut rng);
+        let (escrow_key, escrow_public_key) = recovery::x25519::generate_key_pair(&mut rng);
+        let (wallet_key, wallet_public_key) = recovery::secp256k1::generate_key_pair(&mut rng);
+        let owner_key = RecipientPrivateKey::BLS12381(owner_key);
+        let escrow_key = RecipientPrivateKey::X25519(escrow_key);
+        let wallet_key = RecipientPrivateKey::Secp256k1(wallet_key);
+
+        let (encrypted, _) = seal_encrypt_with_recovery(
+            namespace.clone(),
+            vec![1],
+            vec![service],
+            &IBEPublicKeys::BonehFranklinBLS12381(vec![public_key]),
+            1,
+            vec![],
+            &[
+                RecipientPublicKey::BLS12381(owner_public_key),
+                RecipientPublicKey::X25519(escrow_public_key),
+                RecipientPublicKey::Secp256k1(wallet_public_key),
+            ],
+            EncryptionInput::CommittingAes256Gcm {
+                data: data.to_vec(),
+                aad: None,
+            },
         )
         .unwrap();
+        assert_eq!(encrypted.version, 3);
+        let encrypted: EncryptedObject =
+            bcs::from_bytes(&bcs::to_bytes(&encrypted).unwrap()).unwrap();
+
+        // All recipients and the key server can decrypt the object, and they recover the same base key
+        for private_key in [&owner_key, &escrow_key, &wallet_key] {
+            assert_eq!(
+                seal_decrypt_with_recovery_key(&encrypted, private_key).unwrap(),
+                data
+            );
+        }
+        let user_secret_keys = IBEUserSecretKeys::BonehFranklinBLS12381(HashMap::from([(
+            service,
+            ibe::extract(&master_key, &namespace.full_id(&[1])),
+        )]));
+        assert_eq!(
+            seal_decrypt(&encrypted, &user_secret_keys, None).unwrap(),
+            data
+        );
+        assert_eq!(
+            recover_base_key_with_recovery_key(&encrypted, &owner_key).unwrap(),
+            recover_base_key(&encrypted, &user_secret_keys, None).unwrap()
+        );
 
-        assert_eq!(decrypted, b"My super secret message");
+        // Others cannot
+        let (other_key, _) = recovery::x25519::generate_key_pair(&mut rng);
+        assert!(seal_decrypt_with_recovery_key(
+            &encrypted,
+            &RecipientPrivateKey::X25519(other_key)
+        )
+        .is_err());
+
+        // A recipient given another key than the key servers is rejected
+        let mut tampered = encrypted.clone();
+        let RecoveryRecipient::EciesBLS12381 {
+            ephemeral_key,
+            encrypted_key,
+            ..
+        } = &mut tampered.recovery[0]
+        else {
+            panic!("The first recipient uses BLS12-381");
+        };
+        (*ephemeral_key, *encrypted_key) = recovery::encrypt(
+            &mut rng,
+            &[0; KEY_SIZE],
+            &owner_public_key,
+            &namespace.full_id(&[1]),
+        );
+        assert!(seal_decrypt_with_recovery_key(&tampered, &owner_key).is_err());
     }
 
     #[test]
@@ -714,4 +2623,174 @@ mod tests {
         assert!(seal_decrypt(&encrypted, &usks, Some(&public_keys),)
             .is_err_and(|e| e == GeneralError("Inconsistent shares".to_string())));
     }
+
+    #[test]
+    fn test_reseal() {
+        let data = b"Hello, World!";
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3, 4];
+        let full_id = create_full_id(&package_id, &id);
+
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..5)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+        let user_secret_keys = |range: std::ops::Range<usize>| {
+            IBEUserSecretKeys::BonehFranklinBLS12381(
+                range
+                    .map(|i| (services[i], ibe::extract(&keypairs[i].0, &full_id)))
+                    .collect(),
+            )
+        };
+        let public_keys = |range: std::ops::Range<usize>| {
+            IBEPublicKeys::BonehFranklinBLS12381(range.map(|i| keypairs[i].1).collect_vec())
+        };
+
+        // Encrypt for the first three servers with threshold 2
+        let encrypted = seal_encrypt(
+            package_id,
+            id,
+            services[0..3].to_vec(),
+            &public_keys(0..3),
+            2,
+            EncryptionInput::Aes256Gcm {
+                data: data.to_vec(),
+                aad: Some(b"something".to_vec()),
+            },
+        )
+        .unwrap()
+        .0;
+
+        // Re-seal for the last four servers with threshold 3
+        let base_key = recover_base_key(
+            &encrypted,
+            &user_secret_keys(0..2),
+            Some(&public_keys(0..3)),
+        )
+        .unwrap();
+        let resealed = reseal(
+            &encrypted,
+            &base_key,
+            services[1..5].to_vec(),
+            &public_keys(1..5),
+            3,
+        )
+        .unwrap();
+        assert_eq!(resealed.threshold, 3);
+        assert_eq!(
+            bcs::to_bytes(&resealed.ciphertext).unwrap(),
+            bcs::to_bytes(&encrypted.ciphertext).unwrap()
+        );
+
+        assert_eq!(
+            seal_decrypt(&resealed, &user_secret_keys(2..5), Some(&public_keys(1..5))).unwrap(),
+            data
+        );
+        assert!(seal_decrypt(&resealed, &user_secret_keys(0..3), None).is_err());
+
+        // Re-sealing fails with a wrong base key
+        assert!(reseal(
+            &encrypted,
+            &[0; KEY_SIZE],
+            services[1..5].to_vec(),
+            &public_keys(1..5),
+            3,
+        )
+        .is_err_and(|e| e == GeneralError("Base key does not match the object".to_string())));
+
+        // The base key returned on encryption can be used to re-seal the object
+        let (encrypted, dem_key, base_key) = seal_encrypt_with_base_key(
+            Namespace::SuiPackage(package_id),
+            vec![1, 2, 3, 4],
+            services[0..3].to_vec(),
+            &public_keys(0..3),
+            2,
+            &[],
+            EncryptionInput::Plain,
+        )
+        .unwrap();
+        assert_eq!(encrypted.version, 0);
+        let resealed = reseal(
+            &encrypted,
+            &base_key,
+            services[1..5].to_vec(),
+            &public_keys(1..5),
+            3,
+        )
+        .unwrap();
+        assert_eq!(
+            seal_decrypt(&resealed, &user_secret_keys(2..5), None).unwrap(),
+            dem_key
+        );
+    }
+
+    #[test]
+    fn test_key_commitment() {
+        let data = b"Hello, World!";
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3, 4];
+
+        let full_id = create_full_id(&package_id, &id);
+
+        let mut rng = rand::thread_rng();
+        let keypairs = (0..3)
+            .map(|_| ibe::generate_key_pair(&mut rng))
+            .collect_vec();
+
+        let services = keypairs.iter().map(|_| ObjectID::random()).collect_vec();
+
+        let threshold = 2;
+        let public_keys =
+            IBEPublicKeys::BonehFranklinBLS12381(keypairs.iter().map(|(_, pk)| *pk).collect_vec());
+
+        let mut encrypted = seal_encrypt(
+            package_id,
+            id.clone(),
+            services.clone(),
+            &public_keys,
+            threshold,
+            EncryptionInput::CommittingAes256Gcm {
+                data: data.to_vec(),
+                aad: Some(b"something".to_vec()),
+            },
+        )
+        .unwrap()
+        .0;
+
+        let usks: [_; 3] = services
+            .iter()
+            .zip(&keypairs)
+            .map(|(s, kp)| (*s, ibe::extract(&kp.0, &full_id)))
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let all_usks = IBEUserSecretKeys::BonehFranklinBLS12381(HashMap::from(usks));
+        assert_eq!(seal_decrypt(&encrypted, &all_usks, None).unwrap(), data);
+
+        // Modify the last share
+        if let IBEEncryptions::BonehFranklinBLS12381 {
+            encrypted_shares, ..
+        } = &mut encrypted.encrypted_shares
+        {
+            encrypted_shares[2][0] = encrypted_shares[2][0].wrapping_add(1);
+        }
+
+        // The first two shares give the committed key, but a subset including the last share does not, even if the
+        // share consistency is not checked.
+        let valid_usks =
+            IBEUserSecretKeys::BonehFranklinBLS12381(HashMap::from([usks[0], usks[1]]));
+        assert_eq!(seal_decrypt(&encrypted, &valid_usks, None).unwrap(), data);
+        let invalid_usks =
+            IBEUserSecretKeys::BonehFranklinBLS12381(HashMap::from([usks[0], usks[2]]));
+        assert!(seal_decrypt(&encrypted, &invalid_usks, None)
+            .is_err_and(|e| e == GeneralError("Invalid key commitment".to_string())));
+
+        // Modify the commitment
+        if let Ciphertext::CommittingAes256Gcm { commitment, .. } = &mut encrypted.ciphertext {
+            commitment[0] ^= 1;
+        }
+        assert!(seal_decrypt(&encrypted, &valid_usks, None)
+            .is_err_and(|e| e == GeneralError("Invalid key commitment".to_string())));
+    }
 }
@@ -0,0 +1,181 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A chain agnostic 32-byte identifier used for key servers and Sui packages.
+//!
+//! The serialized format is identical to `sui_types::base_types::ObjectID`: a fixed 32-byte array
+//! in binary formats like BCS and a `0x`-prefixed hex string in human-readable formats. With the
+//! `sui` feature enabled, the type converts to and from the Sui type.
+
+use rand::{thread_rng, Rng};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Deref;
+use std::str::FromStr;
+
+pub const OBJECT_ID_LENGTH: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ObjectID([u8; OBJECT_ID_LENGTH]);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectIDParseError(String);
+
+impl Display for ObjectIDParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid object id: {}", self.0)
+    }
+}
+
+impl std::error::Error for ObjectIDParseError {}
+
+impl ObjectID {
+    pub const fn new(bytes: [u8; OBJECT_ID_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn random() -> Self {
+        Self(thread_rng().gen())
+    }
+
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, ObjectIDParseError> {
+        let bytes = bytes.as_ref();
+        <[u8; OBJECT_ID_LENGTH]>::try_from(bytes)
+            .map(Self)
+            .map_err(|_| ObjectIDParseError(format!("expected 32 bytes, got {}", bytes.len())))
+    }
+
+    pub fn into_bytes(self) -> [u8; OBJECT_ID_LENGTH] {
+        self.0
+    }
+
+    /// Hex representation with a `0x` prefix and all 64 hex digits.
+    pub fn to_hex_literal(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+}
+
+impl Deref for ObjectID {
+    type Target = [u8; OBJECT_ID_LENGTH];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for ObjectID {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; OBJECT_ID_LENGTH]> for ObjectID {
+    fn from(bytes: [u8; OBJECT_ID_LENGTH]) -> Self {
+        Self(bytes)
+    }
+}
+
+/// Parses hex with or without a `0x` prefix. Short forms like `0x2` are left-padded with zeros.
+impl FromStr for ObjectID {
+    type Err = ObjectIDParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        if digits.is_empty() || digits.len() > 2 * OBJECT_ID_LENGTH {
+            return Err(ObjectIDParseError(s.to_string()));
+        }
+        let padded = format!("{:0>width$}", digits, width = 2 * OBJECT_ID_LENGTH);
+        hex::decode(padded)
+            .map_err(|_| ObjectIDParseError(s.to_string()))
+            .and_then(Self::from_bytes)
+    }
+}
+
+impl Display for ObjectID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_hex_literal())
+    }
+}
+
+impl Debug for ObjectID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_hex_literal())
+    }
+}
+
+impl Serialize for ObjectID {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.to_hex_literal().serialize(serializer)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ObjectID {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            Self::from_str(&s).map_err(D::Error::custom)
+        } else {
+            <[u8; OBJECT_ID_LENGTH]>::deserialize(deserializer).map(Self)
+        }
+    }
+}
+
+#[cfg(feature = "sui")]
+impl From<sui_types::base_types::ObjectID> for ObjectID {
+    fn from(id: sui_types::base_types::ObjectID) -> Self {
+        Self(id.into_bytes())
+    }
+}
+
+#[cfg(feature = "sui")]
+impl From<ObjectID> for sui_types::base_types::ObjectID {
+    fn from(id: ObjectID) -> Self {
+        Self::new(id.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let id = ObjectID::from_str("0x2").unwrap();
+        let mut expected = [0u8; 32];
+        expected[31] = 2;
+        assert_eq!(id.into_bytes(), expected);
+        assert_eq!(
+            id.to_string(),
+            "0x0000000000000000000000000000000000000000000000000000000000000002"
+        );
+        assert_eq!(ObjectID::from_str(&id.to_string()).unwrap(), id);
+        assert_eq!(
+            ObjectID::from_str(&hex::encode(id.into_bytes())).unwrap(),
+            id
+        );
+
+        assert!(ObjectID::from_str("").is_err());
+        assert!(ObjectID::from_str("0x").is_err());
+        assert!(ObjectID::from_str("0xzz").is_err());
+        assert!(ObjectID::from_str(&format!("0x{}", "0".repeat(65))).is_err());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let id = ObjectID::new([7u8; 32]);
+
+        // No length prefix in BCS, matching the Sui ObjectID.
+        let bytes = bcs::to_bytes(&id).unwrap();
+        assert_eq!(bytes, [7u8; 32]);
+        assert_eq!(bcs::from_bytes::<ObjectID>(&bytes).unwrap(), id);
+
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"0x{}\"", "07".repeat(32)));
+        assert_eq!(serde_json::from_str::<ObjectID>(&json).unwrap(), id);
+    }
+}
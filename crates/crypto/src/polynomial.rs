@@ -3,29 +3,115 @@
 
 use crate::gf256::GF256;
 use fastcrypto::error::FastCryptoResult;
+use fastcrypto::groups::bls12381::Scalar;
+use fastcrypto::groups::{GroupElement, Scalar as GenericScalar};
+use fastcrypto::traits::AllowedRng;
 use itertools::Itertools;
-use std::iter::{Product, Sum};
+use std::fmt::Debug;
+use std::iter::{repeat_with, Product, Sum};
 use std::ops::{Add, Div, Mul};
 use std::{unreachable, vec};
 
-/// This represents a polynomial over the Galois Field GF256.
+/// The field operations needed to do polynomial arithmetic and interpolation.
+/// This is implemented for [GF256], which is used for the secret sharing in [tss](crate::tss), and
+/// for the scalar field of BLS12-381, which is used for sharing master keys among a committee.
+pub trait FieldElement: Copy + Debug + PartialEq {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn add(&self, other: &Self) -> Self;
+    fn sub(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+    fn neg(&self) -> Self;
+    fn inverse(&self) -> FastCryptoResult<Self>;
+    fn rand<R: AllowedRng>(rng: &mut R) -> Self;
+}
+
+impl FieldElement for GF256 {
+    fn zero() -> Self {
+        GF256::zero()
+    }
+
+    fn one() -> Self {
+        GF256::one()
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self - other
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self * other
+    }
+
+    fn neg(&self) -> Self {
+        -self
+    }
+
+    fn inverse(&self) -> FastCryptoResult<Self> {
+        &GF256::one() / self
+    }
+
+    fn rand<R: AllowedRng>(rng: &mut R) -> Self {
+        GF256::rand(rng)
+    }
+}
+
+impl FieldElement for Scalar {
+    fn zero() -> Self {
+        <Scalar as GroupElement>::zero()
+    }
+
+    fn one() -> Self {
+        Scalar::from(1u128)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        *self + *other
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        *self - *other
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        *self * *other
+    }
+
+    fn neg(&self) -> Self {
+        -*self
+    }
+
+    fn inverse(&self) -> FastCryptoResult<Self> {
+        GenericScalar::inverse(self)
+    }
+
+    fn rand<R: AllowedRng>(rng: &mut R) -> Self {
+        <Scalar as GenericScalar>::rand(rng)
+    }
+}
+
+/// This represents a polynomial over a finite field, by default the Galois Field GF256.
 /// See [gf256](crate::gf256) for more details.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Polynomial(pub(crate) Vec<GF256>);
+pub struct Polynomial<F: FieldElement = GF256>(pub(crate) Vec<F>);
 
-impl Polynomial {
+impl<F: FieldElement> Polynomial<F> {
     /// Returns the degree of this polynomial.
     pub fn degree(&self) -> usize {
         self.0.len() - 1
     }
 
     /// Evaluate this polynomial at a given point <i>x</i>.
-    pub fn evaluate(&self, x: &GF256) -> GF256 {
+    pub fn evaluate(&self, x: &F) -> F {
         // Horner's method to evaluate the polynomial at x
         self.0
             .iter()
             .rev()
-            .fold(GF256::zero(), |sum, coefficient| &(&sum * x) + coefficient)
+            .fold(F::zero(), |sum, coefficient| sum.mul(x).add(coefficient))
     }
 
     /// Return the zero polynomial.
@@ -35,26 +121,43 @@ impl Polynomial {
 
     /// Return the one polynomial.
     pub fn one() -> Self {
-        Self(vec![GF256::one()])
+        Self(vec![F::one()])
+    }
+
+    /// Return the coefficients of this polynomial, starting with the constant term.
+    pub fn coefficients(&self) -> &[F] {
+        &self.0
+    }
+
+    /// Sample a uniformly random polynomial of the given degree with the given constant term.
+    pub fn rand_with_constant_term<R: AllowedRng>(
+        rng: &mut R,
+        constant_term: F,
+        degree: usize,
+    ) -> Self {
+        let mut coefficients = Vec::with_capacity(degree + 1);
+        coefficients.push(constant_term);
+        coefficients.extend(repeat_with(|| F::rand(rng)).take(degree));
+        Self(coefficients)
     }
 
     /// Strip trailing zeros to create a unique representation of the polynomial.
     fn strip_trailing_zeros(mut self) -> Self {
-        while self.0.last() == Some(&GF256::zero()) {
+        while self.0.last() == Some(&F::zero()) {
             self.0.pop();
         }
         self
     }
 
     /// Return a polynomial of the form x + constant
-    fn monic_linear(constant: GF256) -> Self {
-        Self(vec![constant, GF256::one()])
+    fn monic_linear(constant: F) -> Self {
+        Self(vec![constant, F::one()])
     }
 
     /// Create a polynomial `p` given a set of `points` such that `p(x) = y` for all `(x,y)` in `points`.
     /// The degree will be at most points.len() - 1.
     /// It is assumed that the x-values are distinct, otherwise the function will panic.
-    pub fn interpolate(points: &[(GF256, GF256)]) -> Self {
+    pub fn interpolate(points: &[(F, F)]) -> Self {
         // Lagrangian interpolation, see e.g. https://en.wikipedia.org/wiki/Lagrange_polynomial
         points
             .iter()
@@ -65,25 +168,47 @@ impl Polynomial {
                     .enumerate()
                     .filter(|(i, _)| *i != j)
                     .map(|(_, (x_i, _))| {
-                        (Self::monic_linear(-x_i) / &(x_j - x_i)).expect("Divisor is never zero")
+                        (Self::monic_linear(x_i.neg()) / &x_j.sub(x_i))
+                            .expect("Divisor is never zero")
                     })
-                    .product::<Polynomial>()
+                    .product::<Polynomial<F>>()
                     * y_j
             })
             .sum()
     }
 }
 
-impl Add for &Polynomial {
-    type Output = Polynomial;
+/// Compute the Lagrange coefficients for evaluating the polynomial interpolating the given x-values at zero.
+/// Given points (x<sub>i</sub>, y<sub>i</sub>), the constant term of the interpolating polynomial is the sum of
+/// y<sub>i</sub> times the i'th coefficient returned here.
+/// Returns an error if the x-values are not distinct or if any of them is zero.
+pub fn lagrange_coefficients_at_zero<F: FieldElement>(xs: &[F]) -> FastCryptoResult<Vec<F>> {
+    if xs.iter().any(|x| *x == F::zero()) || xs.iter().tuple_combinations().any(|(a, b)| a == b) {
+        return Err(fastcrypto::error::FastCryptoError::InvalidInput);
+    }
+    xs.iter()
+        .map(|x_i| {
+            let (numerator, denominator) = xs.iter().filter(|x_j| *x_j != x_i).fold(
+                (F::one(), F::one()),
+                |(numerator, denominator), x_j| {
+                    (numerator.mul(x_j), denominator.mul(&x_j.sub(x_i)))
+                },
+            );
+            denominator.inverse().map(|inverse| numerator.mul(&inverse))
+        })
+        .collect()
+}
+
+impl<F: FieldElement> Add for &Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn add(self, other: &Polynomial) -> Self::Output {
+    fn add(self, other: &Polynomial<F>) -> Self::Output {
         Polynomial(
             self.0
                 .iter()
                 .zip_longest(other.0.iter())
                 .map(|p| match p.left_and_right() {
-                    (Some(a), Some(b)) => a + b,
+                    (Some(a), Some(b)) => a.add(b),
                     (Some(a), None) => *a,
                     (None, Some(b)) => *b,
                     _ => unreachable!(),
@@ -94,33 +219,33 @@ impl Add for &Polynomial {
     }
 }
 
-impl Sum for Polynomial {
+impl<F: FieldElement> Sum for Polynomial<F> {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
         iter.fold(Polynomial::zero(), |sum, term| &sum + &term)
     }
 }
 
-impl Mul<&GF256> for Polynomial {
-    type Output = Polynomial;
+impl<F: FieldElement> Mul<&F> for Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn mul(self, s: &GF256) -> Self::Output {
-        Polynomial(self.0.into_iter().map(|a| &a * s).collect()).strip_trailing_zeros()
+    fn mul(self, s: &F) -> Self::Output {
+        Polynomial(self.0.into_iter().map(|a| a.mul(s)).collect()).strip_trailing_zeros()
     }
 }
 
 #[allow(clippy::suspicious_arithmetic_impl)]
-impl Mul for &Polynomial {
-    type Output = Polynomial;
+impl<F: FieldElement> Mul for &Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn mul(self, other: &Polynomial) -> Self::Output {
+    fn mul(self, other: &Polynomial<F>) -> Self::Output {
         let degree = self.degree() + other.degree();
         Polynomial(
             (0..=degree)
                 .map(|i| {
                     (0..=i)
                         .filter(|j| j <= &self.degree() && i - j <= other.degree())
-                        .map(|j| &self.0[j] * &other.0[i - j])
-                        .sum()
+                        .map(|j| self.0[j].mul(&other.0[i - j]))
+                        .fold(F::zero(), |sum, term| sum.add(&term))
                 })
                 .collect(),
         )
@@ -128,16 +253,16 @@ impl Mul for &Polynomial {
 }
 
 #[allow(clippy::suspicious_arithmetic_impl)]
-impl Div<&GF256> for Polynomial {
-    type Output = FastCryptoResult<Polynomial>;
+impl<F: FieldElement> Div<&F> for Polynomial<F> {
+    type Output = FastCryptoResult<Polynomial<F>>;
 
-    fn div(self, divisor: &GF256) -> Self::Output {
-        let inverse = (&GF256::one() / divisor)?;
-        Ok(Polynomial(self.0.iter().map(|a| a * &inverse).collect()).strip_trailing_zeros())
+    fn div(self, divisor: &F) -> Self::Output {
+        let inverse = divisor.inverse()?;
+        Ok(Polynomial(self.0.iter().map(|a| a.mul(&inverse)).collect()).strip_trailing_zeros())
     }
 }
 
-impl Product for Polynomial {
+impl<F: FieldElement> Product for Polynomial<F> {
     fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
         iter.fold(Self::one(), |product, factor| &product * &factor)
     }
@@ -146,7 +271,9 @@ impl Product for Polynomial {
 #[cfg(test)]
 mod tests {
     use crate::gf256::GF256;
-    use crate::polynomial::Polynomial;
+    use crate::polynomial::{lagrange_coefficients_at_zero, Polynomial};
+    use fastcrypto::groups::bls12381::Scalar;
+    use fastcrypto::groups::GroupElement;
 
     #[test]
     fn test_polynomial_evaluation() {
@@ -197,4 +324,31 @@ mod tests {
             assert_eq!(y, p.evaluate(&x));
         }
     }
+
+    #[test]
+    fn test_interpolation_scalar() {
+        let points = [(1u128, 7u128), (2, 11), (3, 17), (5, 23)]
+            .into_iter()
+            .map(|(x, y)| (Scalar::from(x), Scalar::from(y)))
+            .collect::<Vec<_>>();
+        let p = Polynomial::interpolate(&points);
+        for (x, y) in &points {
+            assert_eq!(*y, p.evaluate(x));
+        }
+
+        // The Lagrange coefficients at zero give the constant term
+        let coefficients =
+            lagrange_coefficients_at_zero(&points.iter().map(|(x, _)| *x).collect::<Vec<_>>())
+                .unwrap();
+        let constant_term = points
+            .iter()
+            .zip(coefficients)
+            .fold(Scalar::zero(), |sum, ((_, y), l)| sum + *y * l);
+        assert_eq!(constant_term, p.evaluate(&Scalar::zero()));
+
+        assert!(
+            lagrange_coefficients_at_zero(&[Scalar::from(1u128), Scalar::from(1u128)]).is_err()
+        );
+        assert!(lagrange_coefficients_at_zero(&[Scalar::zero(), Scalar::from(1u128)]).is_err());
+    }
 }
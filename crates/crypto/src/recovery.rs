@@ -0,0 +1,193 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! ECIES over the G1 group of BLS12-381, used to encrypt the base key of an encrypted object to recovery recipients,
+//! e.g., the data owner or an escrow service, such that it can be decrypted without the key servers. The key used to
+//! encrypt the base key with AES-256-GCM is derived from the Diffie-Hellman shared secret, the ephemeral key and the
+//! public key of the recipient, and the full id of the object is used as associated data.
+//!
+//! Recipients may also hold X25519 or secp256k1 keys, see [x25519] and [secp256k1], so existing keys of a data owner or
+//! an escrow service can be used.
+
+use crate::dem::Aes256Gcm;
+use crate::KEY_SIZE;
+use fastcrypto::error::FastCryptoError::InvalidInput;
+use fastcrypto::error::FastCryptoResult;
+use fastcrypto::groups::bls12381::{G1Element, Scalar};
+use fastcrypto::groups::{GroupElement, Scalar as GenericScalar};
+use fastcrypto::hmac::{hkdf_sha3_256, HkdfIkm};
+use fastcrypto::serde_helpers::ToFromByteArray;
+use fastcrypto::traits::{AllowedRng, ToFromBytes};
+
+pub mod secp256k1;
+pub mod x25519;
+
+pub type PrivateKey = Scalar;
+pub type PublicKey = G1Element;
+pub type EphemeralKey = G1Element;
+
+/// Domain separation of the key derivation.
+const KDF_INFO: &[u8] = b"SEAL-RECOVERY-BLS12381-00";
+
+/// The public key of a recovery recipient on any of the supported curves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecipientPublicKey {
+    BLS12381(PublicKey),
+    X25519(x25519::PublicKey),
+    Secp256k1(secp256k1::PublicKey),
+}
+
+impl RecipientPublicKey {
+    /// Parse a public key. The curve is given by the length: 48 bytes for a compressed BLS12-381 G1 element, 32 bytes
+    /// for X25519 and 33 bytes for a compressed secp256k1 point.
+    pub fn from_bytes(bytes: &[u8]) -> FastCryptoResult<Self> {
+        match bytes.len() {
+            48 => G1Element::from_byte_array(bytes.try_into().expect("fixed length"))
+                .map(Self::BLS12381),
+            32 => Ok(Self::X25519(bytes.try_into().expect("fixed length"))),
+            33 => Ok(Self::Secp256k1(bytes.try_into().expect("fixed length"))),
+            _ => Err(InvalidInput),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::BLS12381(public_key) => public_key.to_byte_array().to_vec(),
+            Self::X25519(public_key) => public_key.to_vec(),
+            Self::Secp256k1(public_key) => public_key.to_vec(),
+        }
+    }
+}
+
+/// The private key of a recovery recipient on any of the supported curves.
+#[derive(Clone, Debug)]
+pub enum RecipientPrivateKey {
+    BLS12381(PrivateKey),
+    X25519(x25519::PrivateKey),
+    Secp256k1(secp256k1::PrivateKey),
+}
+
+impl RecipientPrivateKey {
+    /// The public key of the recipient. Fails if a secp256k1 private key is not a valid scalar.
+    pub fn public_key(&self) -> FastCryptoResult<RecipientPublicKey> {
+        Ok(match self {
+            Self::BLS12381(private_key) => {
+                RecipientPublicKey::BLS12381(public_key_from_private_key(private_key))
+            }
+            Self::X25519(private_key) => {
+                RecipientPublicKey::X25519(x25519::public_key_from_private_key(private_key))
+            }
+            Self::Secp256k1(private_key) => {
+                RecipientPublicKey::Secp256k1(secp256k1::public_key_from_private_key(private_key)?)
+            }
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::BLS12381(private_key) => private_key.to_byte_array().to_vec(),
+            Self::X25519(private_key) | Self::Secp256k1(private_key) => private_key.to_vec(),
+        }
+    }
+}
+
+/// Generate a key pair for a recovery recipient.
+pub fn generate_key_pair<R: AllowedRng>(rng: &mut R) -> (PrivateKey, PublicKey) {
+    let sk = Scalar::rand(rng);
+    (sk, public_key_from_private_key(&sk))
+}
+
+/// Derive a public key from a private key.
+pub fn public_key_from_private_key(private_key: &PrivateKey) -> PublicKey {
+    G1Element::generator() * private_key
+}
+
+/// Encrypt a key to a recovery recipient. Returns the ephemeral key and the encrypted key.
+pub fn encrypt<R: AllowedRng>(
+    rng: &mut R,
+    key: &[u8; KEY_SIZE],
+    public_key: &PublicKey,
+    full_id: &[u8],
+) -> (EphemeralKey, Vec<u8>) {
+    let r = Scalar::rand(rng);
+    let ephemeral_key = G1Element::generator() * r;
+    let encryption_key = kdf(&(*public_key * r), &ephemeral_key, public_key);
+    (
+        ephemeral_key,
+        Aes256Gcm::encrypt(key, full_id, &encryption_key),
+    )
+}
+
+/// Decrypt a key encrypted with [encrypt]. Fails if the key was encrypted to another recipient or for another full id.
+pub fn decrypt(
+    private_key: &PrivateKey,
+    ephemeral_key: &EphemeralKey,
+    encrypted_key: &[u8],
+    full_id: &[u8],
+) -> FastCryptoResult<[u8; KEY_SIZE]> {
+    let public_key = public_key_from_private_key(private_key);
+    let encryption_key = kdf(&(*ephemeral_key * *private_key), ephemeral_key, &public_key);
+    Aes256Gcm::decrypt(encrypted_key, full_id, &encryption_key)?
+        .try_into()
+        .map_err(|_| InvalidInput)
+}
+
+/// Derive the encryption key from the shared secret.
+fn kdf(
+    shared_secret: &G1Element,
+    ephemeral_key: &EphemeralKey,
+    public_key: &PublicKey,
+) -> [u8; KEY_SIZE] {
+    derive_encryption_key(
+        &shared_secret.to_byte_array(),
+        &ephemeral_key.to_byte_array(),
+        &public_key.to_byte_array(),
+        KDF_INFO,
+    )
+}
+
+/// Derive the encryption key from the encodings of the shared secret, the ephemeral key and the public key of the
+/// recipient, using the domain separation of the curve.
+fn derive_encryption_key(
+    shared_secret: &[u8],
+    ephemeral_key: &[u8],
+    public_key: &[u8],
+    info: &[u8],
+) -> [u8; KEY_SIZE] {
+    let bytes = [shared_secret, ephemeral_key, public_key].concat();
+
+    hkdf_sha3_256(
+        &HkdfIkm::from_bytes(&bytes).expect("not fixed length"),
+        &[], // no salt
+        info,
+        KEY_SIZE,
+    )
+    .expect("kdf should not fail")
+    .try_into()
+    .expect("same length")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut rng = thread_rng();
+        let (private_key, public_key) = generate_key_pair(&mut rng);
+        let key = [7u8; KEY_SIZE];
+        let full_id = b"full id";
+
+        let (ephemeral_key, encrypted_key) = encrypt(&mut rng, &key, &public_key, full_id);
+        assert_eq!(
+            decrypt(&private_key, &ephemeral_key, &encrypted_key, full_id).unwrap(),
+            key
+        );
+
+        // Wrong recipient or full id
+        let (other_private_key, _) = generate_key_pair(&mut rng);
+        assert!(decrypt(&other_private_key, &ephemeral_key, &encrypted_key, full_id).is_err());
+        assert!(decrypt(&private_key, &ephemeral_key, &encrypted_key, b"other id").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! ECIES over secp256k1, for recovery recipients who hold secp256k1 keys, e.g., Ethereum or Bitcoin accounts, instead
+//! of BLS12-381 keys. The construction is the same as for BLS12-381, see [super], with its own domain separation. The
+//! shared secret is the x-coordinate of the Diffie-Hellman point, and the keys are SEC1 compressed points.
+
+use super::derive_encryption_key;
+use crate::dem::Aes256Gcm;
+use crate::KEY_SIZE;
+use fastcrypto::error::FastCryptoError::InvalidInput;
+use fastcrypto::error::FastCryptoResult;
+use fastcrypto::traits::AllowedRng;
+use k256::ecdh::{diffie_hellman, EphemeralSecret};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::{PublicKey as K256PublicKey, SecretKey};
+
+pub type PrivateKey = [u8; 32];
+pub type PublicKey = [u8; 33];
+pub type EphemeralKey = [u8; 33];
+
+/// Domain separation of the key derivation.
+const KDF_INFO: &[u8] = b"SEAL-RECOVERY-SECP256K1-00";
+
+/// Generate a key pair for a recovery recipient.
+pub fn generate_key_pair<R: AllowedRng>(rng: &mut R) -> (PrivateKey, PublicKey) {
+    let sk = SecretKey::random(rng);
+    (sk.to_bytes().into(), compress(&sk.public_key()))
+}
+
+/// Derive a public key from a private key. Fails if the private key is not a valid scalar.
+pub fn public_key_from_private_key(private_key: &PrivateKey) -> FastCryptoResult<PublicKey> {
+    Ok(compress(&secret_key(private_key)?.public_key()))
+}
+
+/// Encrypt a key to a recovery recipient. Returns the ephemeral key and the encrypted key. Fails if the public key is
+/// not a valid point.
+pub fn encrypt<R: AllowedRng>(
+    rng: &mut R,
+    key: &[u8; KEY_SIZE],
+    public_key: &PublicKey,
+    full_id: &[u8],
+) -> FastCryptoResult<(EphemeralKey, Vec<u8>)> {
+    let recipient = K256PublicKey::from_sec1_bytes(public_key).map_err(|_| InvalidInput)?;
+    let r = EphemeralSecret::random(rng);
+    let ephemeral_key = compress(&r.public_key());
+    let shared_secret = r.diffie_hellman(&recipient);
+    let encryption_key = derive_encryption_key(
+        shared_secret.raw_secret_bytes(),
+        &ephemeral_key,
+        public_key,
+        KDF_INFO,
+    );
+    Ok((
+        ephemeral_key,
+        Aes256Gcm::encrypt(key, full_id, &encryption_key),
+    ))
+}
+
+/// Decrypt a key encrypted with [encrypt]. Fails if the key was encrypted to another recipient or for another full id.
+pub fn decrypt(
+    private_key: &PrivateKey,
+    ephemeral_key: &EphemeralKey,
+    encrypted_key: &[u8],
+    full_id: &[u8],
+) -> FastCryptoResult<[u8; KEY_SIZE]> {
+    let sk = secret_key(private_key)?;
+    let ephemeral_point =
+        K256PublicKey::from_sec1_bytes(ephemeral_key).map_err(|_| InvalidInput)?;
+    let shared_secret = diffie_hellman(sk.to_nonzero_scalar(), ephemeral_point.as_affine());
+    let encryption_key = derive_encryption_key(
+        shared_secret.raw_secret_bytes(),
+        ephemeral_key,
+        &compress(&sk.public_key()),
+        KDF_INFO,
+    );
+    Aes256Gcm::decrypt(encrypted_key, full_id, &encryption_key)?
+        .try_into()
+        .map_err(|_| InvalidInput)
+}
+
+fn secret_key(private_key: &PrivateKey) -> FastCryptoResult<SecretKey> {
+    SecretKey::from_slice(private_key).map_err(|_| InvalidInput)
+}
+
+fn compress(public_key: &K256PublicKey) -> PublicKey {
+    public_key
+        .to_encoded_point(true)
+        .as_bytes()
+        .try_into()
+        .expect("compressed points are 33 bytes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut rng = thread_rng();
+        let (private_key, public_key) = generate_key_pair(&mut rng);
+        assert_eq!(
+            public_key_from_private_key(&private_key).unwrap(),
+            public_key
+        );
+        let key = [7u8; KEY_SIZE];
+        let full_id = b"full id";
+
+        let (ephemeral_key, encrypted_key) = encrypt(&mut rng, &key, &public_key, full_id).unwrap();
+        assert_eq!(
+            decrypt(&private_key, &ephemeral_key, &encrypted_key, full_id).unwrap(),
+            key
+        );
+
+        // Wrong recipient or full id
+        let (other_private_key, _) = generate_key_pair(&mut rng);
+        assert!(decrypt(&other_private_key, &ephemeral_key, &encrypted_key, full_id).is_err());
+        assert!(decrypt(&private_key, &ephemeral_key, &encrypted_key, b"other id").is_err());
+
+        // Invalid keys are rejected
+        assert!(encrypt(&mut rng, &key, &[0; 33], full_id).is_err());
+        assert!(public_key_from_private_key(&[0; 32]).is_err());
+    }
+}
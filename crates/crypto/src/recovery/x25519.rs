@@ -0,0 +1,110 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! ECIES over X25519, for recovery recipients who hold X25519 keys, e.g., those used by age or libsodium, instead of
+//! BLS12-381 keys. The construction is the same as for BLS12-381, see [super], with its own domain separation.
+
+use super::derive_encryption_key;
+use crate::dem::Aes256Gcm;
+use crate::KEY_SIZE;
+use fastcrypto::error::FastCryptoError::InvalidInput;
+use fastcrypto::error::FastCryptoResult;
+use fastcrypto::traits::AllowedRng;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+
+pub type PrivateKey = [u8; 32];
+pub type PublicKey = [u8; 32];
+pub type EphemeralKey = [u8; 32];
+
+/// Domain separation of the key derivation.
+const KDF_INFO: &[u8] = b"SEAL-RECOVERY-X25519-00";
+
+/// Generate a key pair for a recovery recipient.
+pub fn generate_key_pair<R: AllowedRng>(rng: &mut R) -> (PrivateKey, PublicKey) {
+    let sk = StaticSecret::random_from_rng(rng);
+    (sk.to_bytes(), X25519PublicKey::from(&sk).to_bytes())
+}
+
+/// Derive a public key from a private key.
+pub fn public_key_from_private_key(private_key: &PrivateKey) -> PublicKey {
+    X25519PublicKey::from(&StaticSecret::from(*private_key)).to_bytes()
+}
+
+/// Encrypt a key to a recovery recipient. Returns the ephemeral key and the encrypted key. Fails if the public key has
+/// a small order, since the shared secret would not depend on the ephemeral key.
+pub fn encrypt<R: AllowedRng>(
+    rng: &mut R,
+    key: &[u8; KEY_SIZE],
+    public_key: &PublicKey,
+    full_id: &[u8],
+) -> FastCryptoResult<(EphemeralKey, Vec<u8>)> {
+    let r = StaticSecret::random_from_rng(rng);
+    let ephemeral_key = X25519PublicKey::from(&r).to_bytes();
+    let shared_secret = r.diffie_hellman(&X25519PublicKey::from(*public_key));
+    if !shared_secret.was_contributory() {
+        return Err(InvalidInput);
+    }
+    let encryption_key = derive_encryption_key(
+        shared_secret.as_bytes(),
+        &ephemeral_key,
+        public_key,
+        KDF_INFO,
+    );
+    Ok((
+        ephemeral_key,
+        Aes256Gcm::encrypt(key, full_id, &encryption_key),
+    ))
+}
+
+/// Decrypt a key encrypted with [encrypt]. Fails if the key was encrypted to another recipient or for another full id.
+pub fn decrypt(
+    private_key: &PrivateKey,
+    ephemeral_key: &EphemeralKey,
+    encrypted_key: &[u8],
+    full_id: &[u8],
+) -> FastCryptoResult<[u8; KEY_SIZE]> {
+    let sk = StaticSecret::from(*private_key);
+    let public_key = X25519PublicKey::from(&sk).to_bytes();
+    let shared_secret = sk.diffie_hellman(&X25519PublicKey::from(*ephemeral_key));
+    if !shared_secret.was_contributory() {
+        return Err(InvalidInput);
+    }
+    let encryption_key = derive_encryption_key(
+        shared_secret.as_bytes(),
+        ephemeral_key,
+        &public_key,
+        KDF_INFO,
+    );
+    Aes256Gcm::decrypt(encrypted_key, full_id, &encryption_key)?
+        .try_into()
+        .map_err(|_| InvalidInput)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut rng = thread_rng();
+        let (private_key, public_key) = generate_key_pair(&mut rng);
+        assert_eq!(public_key_from_private_key(&private_key), public_key);
+        let key = [7u8; KEY_SIZE];
+        let full_id = b"full id";
+
+        let (ephemeral_key, encrypted_key) = encrypt(&mut rng, &key, &public_key, full_id).unwrap();
+        assert_eq!(
+            decrypt(&private_key, &ephemeral_key, &encrypted_key, full_id).unwrap(),
+            key
+        );
+
+        // Wrong recipient or full id
+        let (other_private_key, _) = generate_key_pair(&mut rng);
+        assert!(decrypt(&other_private_key, &ephemeral_key, &encrypted_key, full_id).is_err());
+        assert!(decrypt(&private_key, &ephemeral_key, &encrypted_key, b"other id").is_err());
+
+        // Small order public keys are rejected
+        assert!(encrypt(&mut rng, &key, &[0; 32], full_id).is_err());
+    }
+}
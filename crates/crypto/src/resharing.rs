@@ -0,0 +1,449 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Threshold sharing of an IBE master key among a committee of key servers, and a resharing protocol which moves
+//! the shared master key from a committee with parameters (t, n) to a committee with parameters (t', n') without
+//! changing the public key.
+//!
+//! The master key is shared using Shamir's secret sharing over the scalar field of BLS12-381, and each dealing is
+//! accompanied by a Feldman commitment to the polynomial so recipients can verify the shares they receive.
+//!
+//! Resharing works as follows (see e.g. Desmedt and Jajodia, "Redistributing secret shares to new access structures"):
+//!  - Each member <i>i</i> of a qualified subset <i>S</i> of the old committee shares its own share <i>s<sub>i</sub></i>
+//!    with a random polynomial <i>q<sub>i</sub></i> of degree <i>t' - 1</i>, and sends <i>q<sub>i</sub>(j)</i> privately to
+//!    each member <i>j</i> of the new committee along with a public commitment to <i>q<sub>i</sub></i>.
+//!  - Each new member verifies the shares it receives against the commitments and the known public shares of the
+//!    senders, and computes its new share as <i>Σ<sub>i ∈ S</sub> λ<sub>i</sub> q<sub>i</sub>(j)</i> where
+//!    <i>λ<sub>i</sub></i> are the Lagrange coefficients for <i>S</i> at zero.
+//!
+//! The new shares lie on a fresh random polynomial with the same constant term, so the public key is unchanged, all
+//! existing encryptions stay decryptable, and shares from before the resharing cannot be combined with new shares.
+//! A proactive refresh of the shares is a resharing to the same committee.
+
+use crate::ibe::{MasterKey, PublicKey, UserSecretKey};
+use crate::polynomial::{lagrange_coefficients_at_zero, Polynomial};
+use fastcrypto::error::FastCryptoError::{GeneralError, InvalidInput};
+use fastcrypto::error::FastCryptoResult;
+use fastcrypto::groups::bls12381::{G2Element, Scalar};
+use fastcrypto::groups::GroupElement;
+use fastcrypto::traits::AllowedRng;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// The index of a committee member. Indices must be non-zero and unique within a committee.
+pub type ShareIndex = u16;
+
+/// A share of a master key held by a committee member.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MasterKeyShare {
+    pub index: ShareIndex,
+    pub share: MasterKey,
+}
+
+/// The public counterpart of a [MasterKeyShare], i.e., <i>g<sub>2</sub><sup>share</sup></i>.
+pub type PublicKeyShare = PublicKey;
+
+/// A Feldman commitment to a polynomial, <i>(g<sub>2</sub><sup>a<sub>0</sub></sup>, ..., g<sub>2</sub><sup>a<sub>t-1</sub></sup>)</i>.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolynomialCommitment(pub Vec<G2Element>);
+
+impl PolynomialCommitment {
+    fn new(polynomial: &Polynomial<Scalar>) -> Self {
+        Self(
+            polynomial
+                .coefficients()
+                .iter()
+                .map(|c| G2Element::generator() * *c)
+                .collect(),
+        )
+    }
+
+    /// The threshold of the sharing this is a commitment to, i.e., the number of coefficients.
+    pub fn threshold(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The commitment to the constant term of the polynomial.
+    pub fn constant_term(&self) -> G2Element {
+        self.0.first().copied().unwrap_or(G2Element::zero())
+    }
+
+    /// Evaluate the committed polynomial at the given index in the exponent.
+    /// This gives the public key share of the committee member with the given index.
+    pub fn evaluate(&self, index: ShareIndex) -> PublicKeyShare {
+        let x = to_scalar(index);
+        self.0
+            .iter()
+            .rev()
+            .fold(G2Element::zero(), |sum, coefficient| sum * x + *coefficient)
+    }
+
+    /// Verify that the given share is the committed polynomial evaluated at its index.
+    pub fn verify_share(&self, share: &MasterKeyShare) -> FastCryptoResult<()> {
+        if share.index == 0 || self.evaluate(share.index) != G2Element::generator() * share.share {
+            return Err(GeneralError("Invalid share".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// A dealing from a single member of the old committee. The commitment is public, but each share must be sent to
+/// its recipient over a private, authenticated channel.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dealing {
+    pub sender: ShareIndex,
+    pub commitment: PolynomialCommitment,
+    pub shares: Vec<MasterKeyShare>,
+}
+
+impl Dealing {
+    /// Get the share for the given recipient, if any.
+    pub fn share_for(&self, recipient: ShareIndex) -> Option<&MasterKeyShare> {
+        self.shares.iter().find(|s| s.index == recipient)
+    }
+}
+
+/// Share a master key with a trusted dealer, such that `threshold` of the `number_of_shares` shares are needed to
+/// use the key. The committee members get indices `1..=number_of_shares`.
+/// Returns the shares and a commitment from which the public key and all public key shares can be derived.
+pub fn split_master_key<R: AllowedRng>(
+    rng: &mut R,
+    master_key: &MasterKey,
+    threshold: u16,
+    number_of_shares: u16,
+) -> FastCryptoResult<(Vec<MasterKeyShare>, PolynomialCommitment)> {
+    let indices = (1..=number_of_shares).collect_vec();
+    deal(rng, master_key, threshold, &indices)
+}
+
+/// Create a dealing which reshares the given share of the old committee to a new committee with the given threshold
+/// and indices.
+pub fn create_dealing<R: AllowedRng>(
+    rng: &mut R,
+    share: &MasterKeyShare,
+    new_threshold: u16,
+    new_indices: &[ShareIndex],
+) -> FastCryptoResult<Dealing> {
+    let (shares, commitment) = deal(rng, &share.share, new_threshold, new_indices)?;
+    Ok(Dealing {
+        sender: share.index,
+        commitment,
+        shares,
+    })
+}
+
+/// Verify a dealing from a member of the old committee given its public key share, e.g., that it commits to a
+/// polynomial of the right degree with the sender's share as constant term and that the share for `recipient` is
+/// consistent with the commitment.
+pub fn verify_dealing(
+    dealing: &Dealing,
+    sender_public_key_share: &PublicKeyShare,
+    new_threshold: u16,
+    recipient: ShareIndex,
+) -> FastCryptoResult<()> {
+    if dealing.commitment.threshold() != new_threshold as usize
+        || dealing.commitment.constant_term() != *sender_public_key_share
+    {
+        return Err(GeneralError("Invalid commitment".to_string()));
+    }
+    let share = dealing.share_for(recipient).ok_or(InvalidInput)?;
+    dealing.commitment.verify_share(share)
+}
+
+/// Compute the share of a member of the new committee from the dealings of a qualified subset of the old committee.
+/// It is assumed that the dealings have been verified with [verify_dealing]. Fails if there are fewer dealings than
+/// the threshold of the old committee or if a sender has more than one dealing.
+pub fn complete_resharing(
+    recipient: ShareIndex,
+    dealings: &[Dealing],
+    old_threshold: u16,
+) -> FastCryptoResult<MasterKeyShare> {
+    let coefficients = sender_coefficients(dealings, old_threshold)?;
+    let share = dealings
+        .iter()
+        .zip(coefficients)
+        .map(|(dealing, l)| {
+            dealing
+                .share_for(recipient)
+                .map(|s| s.share * l)
+                .ok_or(InvalidInput)
+        })
+        .fold_ok(Scalar::zero(), |sum, term| sum + term)?;
+    Ok(MasterKeyShare {
+        index: recipient,
+        share,
+    })
+}
+
+/// Compute the commitment to the polynomial of the new committee from the dealings of a qualified subset of the old
+/// committee. This can be computed by anyone and gives the public key shares of the new committee.
+/// Fails if there are fewer dealings than the threshold of the old committee, if a sender has more than one dealing,
+/// or if the constant term of the result is not the given public key.
+pub fn combine_commitments(
+    dealings: &[Dealing],
+    old_threshold: u16,
+    public_key: &PublicKey,
+) -> FastCryptoResult<PolynomialCommitment> {
+    let coefficients = sender_coefficients(dealings, old_threshold)?;
+    let threshold = dealings
+        .iter()
+        .map(|d| d.commitment.threshold())
+        .all_equal_value()
+        .map_err(|_| InvalidInput)?;
+    let commitment = PolynomialCommitment(
+        (0..threshold)
+            .map(|k| {
+                dealings
+                    .iter()
+                    .zip(&coefficients)
+                    .fold(G2Element::zero(), |sum, (d, l)| {
+                        sum + d.commitment.0[k] * *l
+                    })
+            })
+            .collect(),
+    );
+    if commitment.constant_term() != *public_key {
+        return Err(GeneralError("Public key changed".to_string()));
+    }
+    Ok(commitment)
+}
+
+/// Combine partial user secret keys, e.g., [extract](crate::ibe::extract) for the same id using at least `threshold`
+/// different master key shares, into the user secret key for the shared master key.
+pub fn combine_user_secret_keys(
+    partial_keys: &[(ShareIndex, UserSecretKey)],
+) -> FastCryptoResult<UserSecretKey> {
+    let coefficients = lagrange_coefficients_at_zero(
+        &partial_keys
+            .iter()
+            .map(|(i, _)| to_scalar(*i))
+            .collect_vec(),
+    )?;
+    Ok(partial_keys
+        .iter()
+        .zip(coefficients)
+        .fold(UserSecretKey::zero(), |sum, ((_, key), l)| sum + *key * l))
+}
+
+/// Recover the master key from at least `threshold` shares. This should only be used for testing or disaster
+/// recovery since it defeats the purpose of sharing the key.
+pub fn recover_master_key(shares: &[MasterKeyShare]) -> FastCryptoResult<MasterKey> {
+    let coefficients =
+        lagrange_coefficients_at_zero(&shares.iter().map(|s| to_scalar(s.index)).collect_vec())?;
+    Ok(shares
+        .iter()
+        .zip(coefficients)
+        .fold(Scalar::zero(), |sum, (s, l)| sum + s.share * l))
+}
+
+/// Share a secret with a random polynomial of degree `threshold - 1` and evaluate it at the given indices.
+fn deal<R: AllowedRng>(
+    rng: &mut R,
+    secret: &Scalar,
+    threshold: u16,
+    indices: &[ShareIndex],
+) -> FastCryptoResult<(Vec<MasterKeyShare>, PolynomialCommitment)> {
+    if threshold == 0
+        || threshold as usize > indices.len()
+        || indices.iter().any(|i| *i == 0)
+        || !indices.iter().all_unique()
+    {
+        return Err(InvalidInput);
+    }
+    let polynomial = Polynomial::rand_with_constant_term(rng, *secret, (threshold - 1) as usize);
+    let shares = indices
+        .iter()
+        .map(|&index| MasterKeyShare {
+            index,
+            share: polynomial.evaluate(&to_scalar(index)),
+        })
+        .collect();
+    Ok((shares, PolynomialCommitment::new(&polynomial)))
+}
+
+/// The Lagrange coefficients at zero for the senders of the given dealings, which must be at least `old_threshold`
+/// dealings from different senders.
+fn sender_coefficients(dealings: &[Dealing], old_threshold: u16) -> FastCryptoResult<Vec<Scalar>> {
+    if dealings.len() < old_threshold as usize || !dealings.iter().map(|d| d.sender).all_unique() {
+        return Err(InvalidInput);
+    }
+    lagrange_coefficients_at_zero(&dealings.iter().map(|d| to_scalar(d.sender)).collect_vec())
+}
+
+fn to_scalar(index: ShareIndex) -> Scalar {
+    Scalar::from(index as u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ibe;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_split_and_recover() {
+        let mut rng = thread_rng();
+        let (master_key, public_key) = ibe::generate_key_pair(&mut rng);
+        let (shares, commitment) = split_master_key(&mut rng, &master_key, 3, 5).unwrap();
+
+        assert_eq!(commitment.constant_term(), public_key);
+        for share in &shares {
+            commitment.verify_share(share).unwrap();
+        }
+        assert_eq!(recover_master_key(&shares[1..4]).unwrap(), master_key);
+        assert_ne!(recover_master_key(&shares[..2]).unwrap(), master_key);
+        assert!(split_master_key(&mut rng, &master_key, 6, 5).is_err());
+    }
+
+    #[test]
+    fn test_threshold_extract() {
+        let mut rng = thread_rng();
+        let (master_key, public_key) = ibe::generate_key_pair(&mut rng);
+        let (shares, _) = split_master_key(&mut rng, &master_key, 2, 3).unwrap();
+
+        let id = b"some id";
+        let partial_keys = shares[1..]
+            .iter()
+            .map(|s| (s.index, ibe::extract(&s.share, id)))
+            .collect_vec();
+        let usk = combine_user_secret_keys(&partial_keys).unwrap();
+        assert_eq!(usk, ibe::extract(&master_key, id));
+        ibe::verify_user_secret_key(&usk, id, &public_key).unwrap();
+    }
+
+    #[test]
+    fn test_resharing() {
+        let mut rng = thread_rng();
+        let (master_key, public_key) = ibe::generate_key_pair(&mut rng);
+
+        // Move from a 2-out-of-3 committee to a 3-out-of-5 committee with new indices.
+        let (old_shares, old_commitment) = split_master_key(&mut rng, &master_key, 2, 3).unwrap();
+        let new_threshold = 3;
+        let new_indices = [4, 5, 6, 7, 8];
+
+        // Only the first and last of the old committee participate.
+        let dealings = [&old_shares[0], &old_shares[2]]
+            .into_iter()
+            .map(|s| create_dealing(&mut rng, s, new_threshold, &new_indices).unwrap())
+            .collect_vec();
+
+        let new_shares = new_indices
+            .iter()
+            .map(|&recipient| {
+                for dealing in &dealings {
+                    verify_dealing(
+                        dealing,
+                        &old_commitment.evaluate(dealing.sender),
+                        new_threshold,
+                        recipient,
+                    )
+                    .unwrap();
+                }
+                complete_resharing(recipient, &dealings, 2).unwrap()
+            })
+            .collect_vec();
+
+        // The public key is unchanged and the new shares are consistent with the new commitment.
+        let new_commitment = combine_commitments(&dealings, 2, &public_key).unwrap();
+        assert_eq!(new_commitment.constant_term(), public_key);
+        for share in &new_shares {
+            new_commitment.verify_share(share).unwrap();
+        }
+
+        // Any three new shares recover the master key, but two do not.
+        assert_eq!(recover_master_key(&new_shares[2..]).unwrap(), master_key);
+        assert_ne!(recover_master_key(&new_shares[..2]).unwrap(), master_key);
+
+        // Old and new shares cannot be combined.
+        assert_ne!(
+            recover_master_key(&[old_shares[0], new_shares[0], new_shares[1]]).unwrap(),
+            master_key
+        );
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut rng = thread_rng();
+        let (master_key, public_key) = ibe::generate_key_pair(&mut rng);
+        let (old_shares, old_commitment) = split_master_key(&mut rng, &master_key, 2, 3).unwrap();
+        let indices = old_shares.iter().map(|s| s.index).collect_vec();
+
+        let dealings = old_shares
+            .iter()
+            .map(|s| create_dealing(&mut rng, s, 2, &indices).unwrap())
+            .collect_vec();
+        let new_shares = indices
+            .iter()
+            .map(|&i| complete_resharing(i, &dealings, 2).unwrap())
+            .collect_vec();
+
+        assert_eq!(
+            combine_commitments(&dealings, 2, &public_key)
+                .unwrap()
+                .constant_term(),
+            public_key
+        );
+        assert_eq!(recover_master_key(&new_shares[..2]).unwrap(), master_key);
+        for (old, new) in old_shares.iter().zip(&new_shares) {
+            assert_ne!(old.share, new.share);
+            assert!(old_commitment.verify_share(new).is_err());
+        }
+    }
+
+    #[test]
+    fn test_invalid_dealing() {
+        let mut rng = thread_rng();
+        let (master_key, _) = ibe::generate_key_pair(&mut rng);
+        let (old_shares, old_commitment) = split_master_key(&mut rng, &master_key, 2, 3).unwrap();
+
+        let mut dealing = create_dealing(&mut rng, &old_shares[0], 2, &[1, 2, 3]).unwrap();
+
+        // Wrong sender public key share
+        assert!(verify_dealing(&dealing, &old_commitment.evaluate(2), 2, 1).is_err());
+
+        // Wrong threshold
+        assert!(verify_dealing(&dealing, &old_commitment.evaluate(1), 3, 1).is_err());
+
+        // Modified share
+        dealing.shares[0].share = dealing.shares[0].share + Scalar::from(1u128);
+        assert!(verify_dealing(&dealing, &old_commitment.evaluate(1), 2, 1).is_err());
+        assert!(verify_dealing(&dealing, &old_commitment.evaluate(1), 2, 2).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_resharing() {
+        let mut rng = thread_rng();
+        let (master_key, public_key) = ibe::generate_key_pair(&mut rng);
+        let (old_shares, _) = split_master_key(&mut rng, &master_key, 2, 3).unwrap();
+        let new_indices = [1, 2, 3];
+        let dealings = old_shares
+            .iter()
+            .map(|s| create_dealing(&mut rng, s, 2, &new_indices).unwrap())
+            .collect_vec();
+
+        // Too few dealings
+        assert!(complete_resharing(1, &dealings[..1], 2).is_err());
+        assert!(combine_commitments(&dealings[..1], 2, &public_key).is_err());
+
+        // Duplicate sender
+        let duplicate = [dealings[0].clone(), dealings[0].clone()];
+        assert!(complete_resharing(1, &duplicate, 2).is_err());
+        assert!(combine_commitments(&duplicate, 2, &public_key).is_err());
+
+        // Wrong public key
+        let (_, other_public_key) = ibe::generate_key_pair(&mut rng);
+        assert!(combine_commitments(&dealings[1..], 2, &other_public_key).is_err());
+
+        // A dealing of another share with the index of an old committee member changes the public key
+        let forged = MasterKeyShare {
+            index: old_shares[0].index,
+            share: ibe::generate_key_pair(&mut rng).0,
+        };
+        let forged = [
+            create_dealing(&mut rng, &forged, 2, &new_indices).unwrap(),
+            dealings[1].clone(),
+        ];
+        assert!(combine_commitments(&forged, 2, &public_key).is_err());
+        assert!(combine_commitments(&dealings[1..], 2, &public_key).is_ok());
+    }
+}
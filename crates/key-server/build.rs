@@ -0,0 +1,11 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use a vendored protoc so building does not require it to be installed.
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/seal.proto"], &["proto"])?;
+    Ok(())
+}
@@ -0,0 +1,266 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Endpoints for operators of the key server. All requests must have the header `Authorization: Bearer <token>`
+//! where the token is set by the `ADMIN_TOKEN` environment variable.
+//!
+//! The endpoints are never served on the public port. They are served on the mutual TLS listener if there is one, see
+//! [crate::tls], and otherwise on a separate listener at `ADMIN_ADDRESS` which only accepts local clients by default.
+
+use crate::metrics::Metrics;
+use crate::namespace_policy::{Namespace, NamespacePolicy, NamespacePolicyStore};
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{from_fn_with_state, Next};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// The address of the admin listener, unless set by `ADMIN_ADDRESS`.
+const DEFAULT_ADMIN_ADDRESS: &str = "127.0.0.1:2026";
+
+#[derive(Clone)]
+pub(crate) struct AdminState {
+    pub token: Arc<String>,
+    pub namespace_policy: Arc<NamespacePolicyStore>,
+    pub metrics: Arc<Metrics>,
+}
+
+pub(crate) fn router(state: AdminState) -> Router {
+    Router::new()
+        .route(
+            "/v1/admin/namespace_policy",
+            get(handle_get_policy).put(handle_set_policy),
+        )
+        .route("/v1/admin/freeze", post(handle_freeze))
+        .route("/v1/admin/unfreeze", post(handle_unfreeze))
+        .layer(from_fn_with_state(state.clone(), authenticate))
+        .with_state(state)
+}
+
+/// Serve the admin endpoints on their own listener at `ADMIN_ADDRESS`, for deployments without mutual TLS.
+pub(crate) async fn serve(router: Router) -> anyhow::Result<()> {
+    let addr: SocketAddr = env::var("ADMIN_ADDRESS")
+        .unwrap_or_else(|_| DEFAULT_ADMIN_ADDRESS.to_string())
+        .parse()
+        .expect("Invalid ADMIN_ADDRESS");
+    info!("Serving admin endpoints on {}", addr);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+/// Middleware to check the admin token.
+async fn authenticate(
+    State(state): State<AdminState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match token {
+        Some(token) if constant_time_eq(token.as_bytes(), state.token.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        _ => {
+            warn!("Unauthorized admin request to {}", request.uri());
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn handle_get_policy(State(state): State<AdminState>) -> Json<NamespacePolicy> {
+    Json(state.namespace_policy.get())
+}
+
+async fn handle_set_policy(
+    State(state): State<AdminState>,
+    Json(policy): Json<NamespacePolicy>,
+) -> Result<Json<NamespacePolicy>, StatusCode> {
+    update(&state, |current| *current = policy)
+}
+
+async fn handle_freeze(
+    State(state): State<AdminState>,
+    Json(namespace): Json<Namespace>,
+) -> Result<Json<NamespacePolicy>, StatusCode> {
+    info!("Freezing namespace {:?}", namespace);
+    update(&state, |policy| {
+        policy.frozen.insert(namespace);
+    })
+}
+
+async fn handle_unfreeze(
+    State(state): State<AdminState>,
+    Json(namespace): Json<Namespace>,
+) -> Result<Json<NamespacePolicy>, StatusCode> {
+    info!("Unfreezing namespace {:?}", namespace);
+    update(&state, |policy| {
+        policy.frozen.remove(&namespace);
+    })
+}
+
+fn update(
+    state: &AdminState,
+    update: impl FnOnce(&mut NamespacePolicy),
+) -> Result<Json<NamespacePolicy>, StatusCode> {
+    let policy = state.namespace_policy.update(update).map_err(|e| {
+        warn!("Failed to update namespace policy: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    state.metrics.namespace_policy_updates.inc();
+    state.namespace_policy.update_metrics(&state.metrics);
+    Ok(Json(policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::InternalError;
+    use axum::body::{to_bytes, Body};
+    use prometheus::Registry;
+    use sui_sdk::types::base_types::ObjectID;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "secret";
+
+    fn sui(byte: u8) -> Namespace {
+        Namespace::Sui {
+            package_id: ObjectID::from_single_byte(byte),
+        }
+    }
+
+    fn state(namespace_policy: NamespacePolicyStore) -> AdminState {
+        AdminState {
+            token: Arc::new(TOKEN.to_string()),
+            namespace_policy: Arc::new(namespace_policy),
+            metrics: Arc::new(Metrics::new(&Registry::new())),
+        }
+    }
+
+    async fn send(
+        state: &AdminState,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Vec<u8>>,
+    ) -> (StatusCode, Option<NamespacePolicy>) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        if body.is_some() {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+        }
+        let request = request
+            .body(body.map_or_else(Body::empty, Body::from))
+            .unwrap();
+        let response = router(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).ok())
+    }
+
+    #[tokio::test]
+    async fn test_authentication() {
+        let state = state(NamespacePolicyStore::default());
+        let uri = "/v1/admin/namespace_policy";
+        let freeze = Some(serde_json::to_vec(&sui(1)).unwrap());
+        for token in [None, Some("wrong"), Some("secre"), Some("")] {
+            assert_eq!(
+                send(&state, "GET", uri, token, None).await.0,
+                StatusCode::UNAUTHORIZED
+            );
+            assert_eq!(
+                send(&state, "POST", "/v1/admin/freeze", token, freeze.clone())
+                    .await
+                    .0,
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        assert!(state.namespace_policy.check(&sui(1)).is_ok());
+
+        let (status, policy) = send(&state, "GET", uri, Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(policy, Some(NamespacePolicy::default()));
+    }
+
+    #[tokio::test]
+    async fn test_update_policy() {
+        let path = std::env::temp_dir().join(format!("policy-{}.json", rand::random::<u64>()));
+        let state = state(NamespacePolicyStore::open(&path).unwrap());
+
+        // Only serve the first two namespaces
+        let allowed = NamespacePolicy {
+            allowed: Some([sui(1), sui(2)].into()),
+            frozen: Default::default(),
+        };
+        let (status, policy) = send(
+            &state,
+            "PUT",
+            "/v1/admin/namespace_policy",
+            Some(TOKEN),
+            Some(serde_json::to_vec(&allowed).unwrap()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(policy.as_ref(), Some(&allowed));
+        assert!(state.namespace_policy.check(&sui(1)).is_ok());
+        assert_eq!(
+            state.namespace_policy.check(&sui(3)),
+            Err(InternalError::NamespaceNotAllowed)
+        );
+
+        // Freeze and unfreeze an allowed namespace
+        let namespace = Some(serde_json::to_vec(&sui(1)).unwrap());
+        let (status, _) = send(
+            &state,
+            "POST",
+            "/v1/admin/freeze",
+            Some(TOKEN),
+            namespace.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            state.namespace_policy.check(&sui(1)),
+            Err(InternalError::NamespaceFrozen)
+        );
+
+        // The update is persisted
+        let reopened = NamespacePolicyStore::open(&path).unwrap();
+        assert_eq!(reopened.get(), state.namespace_policy.get());
+        assert_eq!(reopened.check(&sui(1)), Err(InternalError::NamespaceFrozen));
+
+        let (status, policy) =
+            send(&state, "POST", "/v1/admin/unfreeze", Some(TOKEN), namespace).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(policy, Some(allowed));
+        assert!(state.namespace_policy.check(&sui(1)).is_ok());
+        assert!(NamespacePolicyStore::open(&path)
+            .unwrap()
+            .check(&sui(1))
+            .is_ok());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+        assert!(!constant_time_eq(b"", b"token"));
+    }
+}
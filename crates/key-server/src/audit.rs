@@ -0,0 +1,218 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::errors::InternalError;
+use crypto::audit::{read_log, verify_log, AuditEntry, AuditRecord, KeyRelease};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::traits::KeyPair;
+use parking_lot::Mutex;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Append-only, hash-chained log of all key releases. See [crypto::audit] for the format.
+pub(crate) struct AuditLog {
+    key_pair: Ed25519KeyPair,
+    state: Mutex<AuditLogState>,
+}
+
+struct AuditLogState {
+    file: File,
+    head: Option<AuditEntry>,
+    unsigned_entries: u64,
+    /// Set if a failed write could not be undone. No more entries are appended since they would follow a partial line.
+    broken: bool,
+}
+
+impl AuditLog {
+    /// Open the audit log at the given path, creating it if it does not exist.
+    /// An existing log is verified, including that its checkpoints are signed with the given key, before new entries
+    /// are appended to it.
+    pub fn open(path: &Path, key_pair: Ed25519KeyPair) -> anyhow::Result<Self> {
+        let (head, unsigned_entries) = if path.exists() {
+            let entries = read_log(BufReader::new(File::open(path)?))?;
+            let summary = verify_log(&entries, Some(key_pair.public()))?;
+            info!(
+                "Opened audit log with {} entries ({} unsigned)",
+                summary.entries, summary.unsigned_entries
+            );
+            (entries.last().cloned(), summary.unsigned_entries)
+        } else {
+            (None, 0)
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            key_pair,
+            state: Mutex::new(AuditLogState {
+                file,
+                head,
+                unsigned_entries,
+                broken: false,
+            }),
+        })
+    }
+
+    /// Append a key release to the log. This must succeed before the keys are returned to the user.
+    /// The write is synced to disk, so it runs on the blocking pool rather than on the async executor.
+    pub async fn record_release(
+        self: &Arc<Self>,
+        release: KeyRelease,
+    ) -> Result<(), InternalError> {
+        let audit_log = self.clone();
+        tokio::task::spawn_blocking(move || audit_log.append_release(release))
+            .await
+            .map_err(|_| InternalError::Failure)?
+    }
+
+    fn append_release(&self, release: KeyRelease) -> Result<(), InternalError> {
+        let mut state = self.state.lock();
+        let entry = AuditEntry::next(state.head.as_ref(), AuditRecord::KeyRelease(release))
+            .map_err(|_| InternalError::Failure)?;
+        state.append(entry).map_err(|e| {
+            warn!("Failed to write to audit log: {:?}", e);
+            InternalError::Failure
+        })?;
+        state.unsigned_entries += 1;
+        Ok(())
+    }
+
+    /// Append a signed checkpoint to the log if there are any entries since the last checkpoint.
+    pub fn checkpoint(&self) -> anyhow::Result<()> {
+        let mut state = self.state.lock();
+        let Some(head) = &state.head else {
+            return Ok(());
+        };
+        if state.unsigned_entries == 0 {
+            return Ok(());
+        }
+        let entry = AuditEntry::checkpoint(head, &self.key_pair)?;
+        state.append(entry)?;
+        state.unsigned_entries = 0;
+        Ok(())
+    }
+
+    /// Spawns a task that appends a signed checkpoint to the log once per `interval`.
+    pub fn spawn_checkpointer(self: &Arc<Self>, interval: Duration) {
+        let audit_log = self.clone();
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        tokio::task::spawn(async move {
+            loop {
+                interval.tick().await;
+                let checkpointed = {
+                    let audit_log = audit_log.clone();
+                    tokio::task::spawn_blocking(move || audit_log.checkpoint()).await
+                };
+                match checkpointed {
+                    Ok(Err(e)) => warn!("Failed to checkpoint audit log: {:?}", e),
+                    Err(e) => warn!("Failed to checkpoint audit log: {:?}", e),
+                    Ok(Ok(())) => {}
+                }
+            }
+        });
+    }
+}
+
+impl AuditLogState {
+    /// Append an entry to the file. If the write fails, the file is truncated to its previous length so that no partial
+    /// line is left behind, and if that fails too, the log is marked as broken.
+    fn append(&mut self, entry: AuditEntry) -> std::io::Result<()> {
+        if self.broken {
+            return Err(std::io::Error::other("audit log ends with a partial entry"));
+        }
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        let length = self.file.metadata()?.len();
+        let written = self
+            .file
+            .write_all(&line)
+            .and_then(|_| self.file.sync_data());
+        if let Err(e) = written {
+            if let Err(truncate_error) = self.file.set_len(length) {
+                warn!(
+                    "Failed to remove partial audit log entry: {:?}",
+                    truncate_error
+                );
+                self.broken = true;
+            }
+            return Err(e);
+        }
+        self.head = Some(entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::audit::PolicyEvidence;
+    use rand::thread_rng;
+
+    fn release(i: u64) -> KeyRelease {
+        KeyRelease {
+            timestamp_ms: i,
+            chain: "sui".to_string(),
+            namespace: "0x1".to_string(),
+            ids: vec![],
+            user: "0x2".to_string(),
+            session_key: "key".to_string(),
+            request_id: None,
+            evidence: PolicyEvidence::SuiCheckpoint { timestamp_ms: i },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", rand::random::<u64>()));
+        let key_pair = Ed25519KeyPair::generate(&mut thread_rng());
+        let public_key = key_pair.public().clone();
+
+        let log = Arc::new(AuditLog::open(&path, key_pair.copy()).unwrap());
+        log.record_release(release(0)).await.unwrap();
+        log.record_release(release(1)).await.unwrap();
+        log.checkpoint().unwrap();
+        log.checkpoint().unwrap(); // No-op
+        drop(log);
+
+        // A log signed by another key is not continued
+        let other_key_pair = Ed25519KeyPair::generate(&mut thread_rng());
+        assert!(AuditLog::open(&path, other_key_pair).is_err());
+
+        // Reopening continues the chain
+        let log = Arc::new(AuditLog::open(&path, key_pair).unwrap());
+        log.record_release(release(2)).await.unwrap();
+        drop(log);
+
+        let entries = read_log(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let summary = verify_log(&entries, Some(&public_key)).unwrap();
+        assert_eq!(summary.entries, 4);
+        assert_eq!(summary.key_releases, 3);
+        assert_eq!(summary.checkpoints, 1);
+        assert_eq!(summary.unsigned_entries, 1);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_broken_audit_log() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", rand::random::<u64>()));
+        let log =
+            Arc::new(AuditLog::open(&path, Ed25519KeyPair::generate(&mut thread_rng())).unwrap());
+        log.record_release(release(0)).await.unwrap();
+        let length = std::fs::metadata(&path).unwrap().len();
+
+        // Nothing is appended after a partial entry that could not be removed
+        log.state.lock().broken = true;
+        assert_eq!(
+            log.record_release(release(1)).await,
+            Err(InternalError::Failure)
+        );
+        assert!(log.checkpoint().is_err());
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), length);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,12 +1,32 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::Serialize;
+use std::cell::Cell;
+use std::future::Future;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, PartialEq)]
+tokio::task_local! {
+    /// The number of seconds until a rate limited request may be retried. The wait time is not part of the error, so
+    /// that it serializes like the other variants, and is only sent in the `Retry-After` header.
+    static RETRY_AFTER: Cell<Option<u64>>;
+}
+
+/// Run the handling of a request, during which [set_retry_after] records the wait time for the `Retry-After` header of
+/// a [InternalError::RateLimited] response.
+pub(crate) async fn with_retry_after<F: Future>(f: F) -> F::Output {
+    RETRY_AFTER.scope(Cell::new(None), f).await
+}
+
+/// Record the number of seconds until a rejected request may be retried. Ignored outside [with_retry_after].
+pub(crate) fn set_retry_after(seconds: u64) {
+    let _ = RETRY_AFTER.try_with(|retry_after| retry_after.set(Some(seconds)));
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
 pub enum InternalError {
     InvalidPTB(String),
     InvalidPackage,
@@ -18,10 +38,17 @@ pub enum InternalError {
     InvalidSDKVersion,
     DeprecatedSDKVersion,
     InvalidParameter,
+    NamespaceNotAllowed,
+    NamespaceFrozen,
+    BatchTooLarge,
+    /// Too many requests. The number of seconds to wait before retrying is sent in the `Retry-After` header.
+    RateLimited,
+    /// The key extraction pool is busy or did not extract the keys in time.
+    Overloaded,
     Failure, // Internal error, try again later
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     error: InternalError,
     message: String,
@@ -29,6 +56,51 @@ pub struct ErrorResponse {
 
 impl IntoResponse for InternalError {
     fn into_response(self) -> Response {
+        let retry_after = self.retry_after();
+        let (status, error_response) = self.into_parts();
+        let mut response = (status, Json(error_response)).into_response();
+        if let Some(seconds) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, seconds.into());
+        }
+        response
+    }
+}
+
+impl From<InternalError> for tonic::Status {
+    fn from(error: InternalError) -> Self {
+        let retry_after = error.retry_after();
+        let (status, error_response) = error.into_parts();
+        let code = match status {
+            StatusCode::BAD_REQUEST => tonic::Code::InvalidArgument,
+            StatusCode::FORBIDDEN => tonic::Code::PermissionDenied,
+            StatusCode::UPGRADE_REQUIRED => tonic::Code::FailedPrecondition,
+            StatusCode::TOO_MANY_REQUESTS => tonic::Code::ResourceExhausted,
+            StatusCode::SERVICE_UNAVAILABLE => tonic::Code::Unavailable,
+            _ => tonic::Code::Internal,
+        };
+        let mut status = tonic::Status::new(code, error_response.message);
+        if let Some(seconds) = retry_after {
+            status
+                .metadata_mut()
+                .insert("retry-after", seconds.to_string().parse().expect("valid"));
+        }
+        status
+    }
+}
+
+impl InternalError {
+    /// The number of seconds to wait before retrying a rate limited request, see [set_retry_after].
+    fn retry_after(&self) -> Option<u64> {
+        match self {
+            InternalError::RateLimited => RETRY_AFTER.try_with(Cell::get).ok().flatten(),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status and the body of the response for this error.
+    pub fn into_parts(self) -> (StatusCode, ErrorResponse) {
         let (status, message) = match self {
             InternalError::InvalidPTB(ref inner) => {
                 (StatusCode::FORBIDDEN, format!("Invalid PTB: {}", inner))
@@ -63,6 +135,26 @@ impl IntoResponse for InternalError {
                 StatusCode::FORBIDDEN,
                 "Invalid parameter. If the object was just created, try again later.".to_string(),
             ),
+            InternalError::NamespaceNotAllowed => (
+                StatusCode::FORBIDDEN,
+                "Package or contract is not served by this key server".to_string(),
+            ),
+            InternalError::NamespaceFrozen => (
+                StatusCode::FORBIDDEN,
+                "Package or contract has been frozen by the key server operator".to_string(),
+            ),
+            InternalError::BatchTooLarge => (
+                StatusCode::BAD_REQUEST,
+                "Too many requests in batch".to_string(),
+            ),
+            InternalError::RateLimited => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, please try again later".to_string(),
+            ),
+            InternalError::Overloaded => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Key server is overloaded, please try again later".to_string(),
+            ),
             InternalError::Failure => (
                 StatusCode::SERVICE_UNAVAILABLE,
                 "Internal server error, please try again later".to_string(),
@@ -73,12 +165,9 @@ impl IntoResponse for InternalError {
             error: self,
             message,
         };
-
-        (status, Json(error_response)).into_response()
+        (status, error_response)
     }
-}
 
-impl InternalError {
     pub fn as_str(&self) -> &'static str {
         match self {
             InternalError::InvalidPTB(_) => "InvalidPTB",
@@ -91,6 +180,11 @@ impl InternalError {
             InternalError::InvalidSDKVersion => "InvalidSDKVersion",
             InternalError::DeprecatedSDKVersion => "DeprecatedSDKVersion",
             InternalError::InvalidParameter => "InvalidParameter",
+            InternalError::NamespaceNotAllowed => "NamespaceNotAllowed",
+            InternalError::NamespaceFrozen => "NamespaceFrozen",
+            InternalError::BatchTooLarge => "BatchTooLarge",
+            InternalError::RateLimited => "RateLimited",
+            InternalError::Overloaded => "Overloaded",
             InternalError::Failure => "Failure",
         }
     }
@@ -103,3 +197,26 @@ macro_rules! return_err {
         return Err($err);
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limited_response() {
+        let response = with_retry_after(async {
+            set_retry_after(3);
+            InternalError::RateLimited.into_response()
+        })
+        .await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "3");
+
+        // The error serializes like the other variants without a payload
+        let (_, error_response) = InternalError::RateLimited.into_parts();
+        assert_eq!(
+            serde_json::to_value(&error_response).unwrap()["error"],
+            "RateLimited"
+        );
+    }
+}
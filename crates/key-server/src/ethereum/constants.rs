@@ -7,12 +7,29 @@ pub const SESSION_KEY_TTL_MAX: u16 = 30;
 /// Expected function selector for seal_approve
 pub const SEAL_APPROVE_SELECTOR: &[u8; 4] = &[0x12, 0x34, 0x56, 0x78]; // Replace with actual selector
 
+/// Network the policy transactions are simulated on if `ETHEREUM_CHAIN_ID` is not set (Base Sepolia testnet)
+pub const SIMULATION_NETWORK_ID: &str = "84532";
+
 /// Tenderly API endpoint
 pub const TENDERLY_API_ENDPOINT: &str = "https://api.tenderly.co/api/v1/account/may19/project/project/simulate-bundle";
 
 use std::env;
+use std::sync::OnceLock;
 use dotenv::dotenv;
 
+/// The chain the policies are evaluated on and the keys are released for, from `ETHEREUM_CHAIN_ID`. Defaults to
+/// [SIMULATION_NETWORK_ID]. Panics if the value is not a valid chain id.
+pub fn chain_id() -> u64 {
+    static CHAIN_ID: OnceLock<u64> = OnceLock::new();
+    *CHAIN_ID.get_or_init(|| {
+        dotenv().ok();
+        env::var("ETHEREUM_CHAIN_ID")
+            .unwrap_or_else(|_| SIMULATION_NETWORK_ID.to_string())
+            .parse()
+            .expect("Invalid ETHEREUM_CHAIN_ID")
+    })
+}
+
 pub fn get_tenderly_access_key() -> Result<String, String> {
     dotenv().ok();
     env::var("TENDERLY_ACCESS_KEY")
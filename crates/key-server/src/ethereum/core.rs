@@ -1,23 +1,34 @@
 use crate::errors::InternalError;
-use crate::ethereum::ptb::{parse_ptb, simulate_transaction_bundle};
+use crate::ethereum::constants::chain_id;
+use crate::ethereum::ptb::{parse_ptb, parse_transaction_input, simulate_transaction_bundle};
+use crate::types::AccessDecision;
 use ethers::types::{Address};
 use hex;
-use crypto::create_full_id;
+use crypto::{create_full_id, Namespace};
 use tracing::{debug};
 
+/// The output of a seal_approve call that approves access.
+const SEAL_APPROVE_TRUE: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+
 /// Checks if a user has permission to access resources based on the seal contract.
+/// Returns the full ids of the approved keys for encrypted objects of the given version, see [key_full_id], and the
+/// block number the simulation was done on, if reported.
 pub async fn check_seal_approve(
     contract_address: &Address,
     ptb: &str,
     cert_user: &Address,
+    version: u8,
     metrics: Option<&crate::metrics::Metrics>,
     req_id: Option<&str>,
-) -> Result<Vec<Vec<u8>>, InternalError> {
+) -> Result<(Vec<Vec<u8>>, Option<u64>), InternalError> {
     debug!(
         "Attempting to check seal approval for contract: {}, user: {}, eq_id: {:?}",
         contract_address, cert_user, req_id
     );
 
+    // Reject unsupported versions before simulating
+    key_full_id(contract_address, version, &[])?;
+
     // Parse PTB to get transactions
     let transactions = parse_ptb(ptb)?;
 
@@ -25,7 +36,7 @@ pub async fn check_seal_approve(
     let response = simulate_transaction_bundle(
         &transactions,
         &cert_user,
-        "84532", // Base Sepolia testnet
+        &chain_id().to_string(),
         None,
     )
     .await?;
@@ -56,7 +67,7 @@ pub async fn check_seal_approve(
 
         // output is successful
         if response["simulation_results"][i]["transaction"]["call_trace"][0]["output"]
-            == "0x0000000000000000000000000000000000000000000000000000000000000001"
+            == SEAL_APPROVE_TRUE
         {
             let input: &str = response["simulation_results"][i]["transaction"]["call_trace"][0]
                 ["input"]
@@ -64,17 +75,131 @@ pub async fn check_seal_approve(
                 .unwrap();
             let key_id_hex = input.strip_prefix("0x").unwrap()[8..72].to_string(); // Skip 4 bytes (8 hex chars) of function hash
             let key_id_bytes32 = hex::decode(&key_id_hex).unwrap();
-
-            let mut padded_contract = [0u8; 32];
-            padded_contract[12..].copy_from_slice(&contract_address.as_bytes());
-            let full_id = create_full_id(&padded_contract, &key_id_bytes32);
-
-            valid_key_ids.push(full_id);
+            valid_key_ids.push(key_full_id(contract_address, version, &key_id_bytes32)?);
         }
 
         println!("########################################################");
     }
 
     println!("### valid_key_ids: {:?}", valid_key_ids.len());
-    Ok(valid_key_ids)
+    let block_number = response["simulation_results"][0]["simulation"]["block_number"].as_u64();
+    Ok((valid_key_ids, block_number))
+}
+
+/// The full id of a key approved by the given contract, for encrypted objects of the given version. Version 0 objects
+/// use the zero-padded contract address as package id, and version 1 objects the EVM namespace of the contract on the
+/// configured chain. Other versions are rejected.
+pub fn key_full_id(
+    contract_address: &Address,
+    version: u8,
+    key_id: &[u8],
+) -> Result<Vec<u8>, InternalError> {
+    match version {
+        0 => {
+            let mut padded_contract = [0u8; 32];
+            padded_contract[12..].copy_from_slice(contract_address.as_bytes());
+            Ok(create_full_id(&padded_contract, key_id))
+        }
+        1 => Ok(Namespace::Evm {
+            chain_id: chain_id(),
+            address: contract_address.to_fixed_bytes(),
+        }
+        .full_id(key_id)),
+        _ => Err(InternalError::InvalidParameter),
+    }
+}
+
+/// Evaluates the seal contract for each transaction of the bundle without failing if access is denied.
+/// Returns one decision per transaction, with the full ids [check_seal_approve] returns for the given object version, and
+/// the block number the simulation was done on, if reported.
+pub async fn explain_seal_approve(
+    contract_address: &Address,
+    ptb: &str,
+    cert_user: &Address,
+    version: u8,
+    trace: bool,
+    req_id: Option<&str>,
+) -> Result<(Vec<AccessDecision>, Option<u64>), InternalError> {
+    debug!(
+        "Explaining seal approval for contract: {}, user: {}, req_id: {:?}",
+        contract_address, cert_user, req_id
+    );
+
+    // Reject unsupported versions before simulating
+    key_full_id(contract_address, version, &[])?;
+
+    let transactions = parse_ptb(ptb)?;
+    let response = simulate_transaction_bundle(
+        &transactions,
+        cert_user,
+        &chain_id().to_string(),
+        None,
+    )
+    .await?;
+    let results = response["simulation_results"]
+        .as_array()
+        .ok_or_else(|| InternalError::InvalidPTB("Invalid simulation response".to_string()))?;
+
+    let decisions = transactions
+        .iter()
+        .zip(results)
+        .map(|(tx, result)| {
+            if tx.input.len() < 36 {
+                return Err(InternalError::InvalidPTB("Transaction input too short".to_string()));
+            }
+            let (_, key_id) = parse_transaction_input(&tx.input)?;
+            let output = result["transaction"]["call_trace"][0]["output"].as_str();
+            let approved = result["simulation"]["status"] == true && output == Some(SEAL_APPROVE_TRUE);
+            let reason = (!approved).then(|| {
+                result["transaction"]["error_message"]
+                    .as_str()
+                    .unwrap_or("seal_approve did not return true")
+                    .to_string()
+            });
+            Ok(AccessDecision {
+                id: key_full_id(contract_address, version, key_id)?,
+                approved,
+                reason,
+                abort_code: None,
+                revert_data: if approved { None } else { output.map(String::from) },
+                gas_used: result["simulation"]["gas_used"].as_u64(),
+                trace: trace.then(|| result["transaction"]["call_trace"].clone()),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let block_number = results
+        .first()
+        .and_then(|result| result["simulation"]["block_number"].as_u64());
+    Ok((decisions, block_number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_full_id() {
+        let contract_address = Address::repeat_byte(1);
+        let key_id = [2u8; 32];
+
+        let mut padded_contract = [0u8; 32];
+        padded_contract[12..].copy_from_slice(contract_address.as_bytes());
+        assert_eq!(
+            key_full_id(&contract_address, 0, &key_id).unwrap(),
+            create_full_id(&padded_contract, &key_id)
+        );
+        assert_eq!(
+            key_full_id(&contract_address, 1, &key_id).unwrap(),
+            Namespace::Evm {
+                chain_id: chain_id(),
+                address: [1; 20],
+            }
+            .full_id(&key_id)
+        );
+        assert_eq!(
+            key_full_id(&contract_address, 2, &key_id),
+            Err(InternalError::InvalidParameter)
+        );
+    }
 }
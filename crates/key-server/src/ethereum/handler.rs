@@ -4,51 +4,63 @@ use axum::{
     http::HeaderMap,
 };
 use tracing::{debug, info, warn};
-use crate::errors::InternalError; 
+use crate::errors::InternalError;
+use crate::externals::current_epoch_time;
 use crate::{MyState, KeyId};
-use crypto::elgamal::encrypt;
-use crypto::ibe::extract;
-use rand::thread_rng;
+use crypto::audit::{KeyRelease, PolicyEvidence};
 
-use crate::ethereum::types::{Certificate, FetchKeyRequest, FetchKeyResponse, DecryptionKey};
+use crate::ethereum::types::{CheckAccessRequest, Certificate, FetchKeyRequest, FetchKeyResponse, DecryptionKey};
 use crate::ethereum::certificate::check_certificate;
 use crate::ethereum::request::verify_request_signature;
-use crate::ethereum::core::check_seal_approve;
+use crate::ethereum::constants::chain_id;
+use crate::ethereum::core::{check_seal_approve, explain_seal_approve};
 use crate::ethereum::ptb::{parse_ptb, get_first_contract_address_from_ptb};
 use crate::metrics::Metrics;
+use crate::namespace_policy::Namespace;
+use crate::rate_limit::request_limits;
 use crate::ALLOWED_STALENESS;
-use crate::types::{ElGamalPublicKey, ElgamalVerificationKey};
+use crate::types::{CheckAccessResponse, ElGamalPublicKey, ElgamalVerificationKey};
+use ethers::types::Address;
 use fastcrypto::ed25519::Ed25519Signature;
 use std::time::Instant;
 
-/// Create response with encrypted keys
-fn create_response(
+/// Create response with encrypted keys. The keys are extracted on the extraction pool of the server.
+async fn create_response(
     server: &crate::Server,
     key_ids: &[Vec<u8>],
     enc_key: &ElGamalPublicKey,
-) -> FetchKeyResponse {
-    let mut decryption_keys = Vec::new();
-    let mut rng = thread_rng();
-
-    for id in key_ids {
-        // Extract a key based on the ID
-        let derived_key = extract(&server.master_key, id);
-
-        // Encrypt the derived key with user's key
-        let encrypted_key = encrypt(&mut rng, &derived_key, enc_key);
-
-        println!("extract key for id: {:?}", id);
-        // Add to response
-        decryption_keys.push(DecryptionKey {
-            id: id.clone(),
-            encrypted_key,
-        });
-    }
+    metrics: &Metrics,
+) -> Result<FetchKeyResponse, InternalError> {
+    let start = Instant::now();
+    let decryption_keys = server
+        .extractor
+        .extract_and_encrypt(key_ids.to_vec(), enc_key.clone())
+        .await?
+        .into_iter()
+        .map(|(id, encrypted_key)| DecryptionKey { id, encrypted_key })
+        .collect();
+    metrics
+        .key_extraction_duration
+        .observe(start.elapsed().as_millis() as f64);
 
-    FetchKeyResponse { decryption_keys }
+    Ok(FetchKeyResponse {
+        decryption_keys,
+        evidence: None,
+    })
 }
 
 /// Main handler for the `/v1/fetch_key_ethereum` endpoint.
+#[utoipa::path(
+    post,
+    path = "/v1/fetch_key_ethereum",
+    request_body = ethereum::FetchKeyRequest,
+    params(crate::openapi::RequestHeaders),
+    responses(
+        (status = 200, description = "The encrypted keys", body = ethereum::FetchKeyResponse),
+        (status = "4XX", description = "The request was rejected", body = ErrorResponse),
+        (status = "5XX", description = "The key server failed to handle the request", body = ErrorResponse),
+    )
+)]
 pub async fn handle_fetch_key(
     State(app_state): State<MyState>,
     headers: HeaderMap,
@@ -58,7 +70,7 @@ pub async fn handle_fetch_key(
 
     // Extract request ID for logging
     let req_id = headers
-        .get("x-request-id")
+        .get(crate::REQUEST_ID_HEADER)
         .map(|v| v.to_str().unwrap_or_default());
 
     println!("handle_fetch_key req_id: {:?}", req_id);
@@ -80,6 +92,18 @@ pub async fn handle_fetch_key(
         }
     }
 
+    fetch_key(&app_state, payload, req_id).await.map(Json)
+}
+
+/// Checks a fetch key request and returns the encrypted keys.
+/// Used by both the `/v1/fetch_key_ethereum` and the `/v1/fetch_keys_batch` endpoints.
+pub async fn fetch_key(
+    app_state: &MyState,
+    payload: FetchKeyRequest,
+    req_id: Option<&str>,
+) -> Result<FetchKeyResponse, InternalError> {
+    let metrics = &app_state.metrics;
+
     debug!("Received /v1/fetch_key_ethereum request (req_id: {:?})", req_id);
 
     // Parse ethereum transactions from ptb bytes
@@ -94,19 +118,24 @@ pub async fn handle_fetch_key(
 
     println!("PTB (array of transactions) parsed successfully (req_id: {:?})", req_id);
 
+    let namespace = get_first_contract_address_from_ptb(&payload.ptb)
+        .ok()
+        .map(|address| format!("{:?}", address));
+
     // check request for its signature (also get contract address (to field))
     // then checkcertificate for its validity and signature (with contract address from request)
+    // then charge the rate limits
     // then check policy (seal_approve)
     // then extract key ids
-    let key_ids = check_request(
+    let (key_ids, block_number) = check_request(
         &payload.ptb,
         &payload.enc_key,
         &payload.enc_verification_key,
         &payload.request_signature,
         &payload.certificate,
-        Some(metrics),
+        payload.version,
+        app_state,
         req_id,
-        &app_state.server,
     )
     .await
     .map_err(|e| {
@@ -117,33 +146,117 @@ pub async fn handle_fetch_key(
 
     println!("Key IDs extracted count: {:?}", key_ids.len());
 
+    let evidence = PolicyEvidence::EthereumBlock {
+        network_id: chain_id().to_string(),
+        block_number,
+    };
+
     // Create response with keys
-    let response = create_response(&app_state.server, &key_ids, &payload.enc_key);
+    let mut response = create_response(&app_state.server, &key_ids, &payload.enc_key, metrics)
+        .await
+        .map_err(|e| {
+            metrics.observe_error(e.as_str());
+            e
+        })?;
+    response.evidence = Some(evidence.clone());
 
     println!(
         "Response created with {} keys",
         response.decryption_keys.len()
     );
+
+    app_state
+        .record_release(KeyRelease {
+            timestamp_ms: current_epoch_time(),
+            chain: "ethereum".to_string(),
+            namespace: namespace.unwrap_or_default(),
+            ids: key_ids.iter().map(hex::encode).collect(),
+            user: format!("{:?}", payload.certificate.user),
+            session_key: payload.certificate.session_vk.to_string(),
+            request_id: req_id.map(String::from),
+            evidence,
+        })
+        .await
+        .map_err(|e| {
+            metrics.observe_error(e.as_str());
+            e
+        })?;
     info!("Fetch key request successful (req_id: {:?})", req_id);
 
-    Ok(Json(response))
+    Ok(response)
 }
 
 /// This is the overall main function
-/// It checks the request, certificate, and policy
-/// It returns the key ids
+/// It checks the request and certificate, charges the rate limits of the user, and checks the policy
+/// It returns the key ids and the block number the policy was evaluated on
 async fn check_request(
     ptb: &str,
     enc_key: &ElGamalPublicKey,
     enc_verification_key: &ElgamalVerificationKey,
     request_signature: &Ed25519Signature,
     certificate: &Certificate,
-    metrics: Option<&Metrics>,
+    version: u8,
+    app_state: &MyState,
     req_id: Option<&str>,
-    _server: &crate::Server,
-) -> Result<Vec<Vec<u8>>, InternalError> {
-    let _start = Instant::now();
+) -> Result<(Vec<Vec<u8>>, Option<u64>), InternalError> {
+    let metrics = Some(&app_state.metrics);
+
+    let contract_address = validate_request(
+        ptb,
+        enc_key,
+        enc_verification_key,
+        request_signature,
+        certificate,
+        metrics,
+        req_id,
+        &app_state.server,
+    )
+    .await?;
+
+    // Only verified requests are charged, right before the policy is simulated
+    app_state.check_rate_limits(
+        &request_limits(
+            format!("{:?}", certificate.user),
+            certificate.session_vk.to_string(),
+            Some(format!("{:?}", contract_address)),
+        ),
+        1,
+    )?;
+
+    // Check policy by simulating the transaction
+    let (valid_key_ids, block_number) = check_seal_approve(
+        &contract_address,
+        ptb,
+        &certificate.user,
+        version,
+        metrics,
+        req_id,
+    ).await?;
+
+    debug!("Policy checked successfully (req_id: {:?})", req_id);
+    debug!("Key IDs count: {:?}", valid_key_ids.len());
+
+    // Report metrics if available
+    if let Some(m) = metrics {
+        m.requests_per_number_of_ids.observe(valid_key_ids.len() as f64);
+    }
+
+    Ok((valid_key_ids, block_number))
+}
 
+/// Checks the request signature, the namespace policy and the certificate
+/// It returns the contract address of the policy
+#[allow(clippy::too_many_arguments)]
+async fn validate_request(
+    ptb: &str,
+    enc_key: &ElGamalPublicKey,
+    enc_verification_key: &ElgamalVerificationKey,
+    request_signature: &Ed25519Signature,
+    certificate: &Certificate,
+    metrics: Option<&Metrics>,
+    req_id: Option<&str>,
+    server: &crate::Server,
+) -> Result<Address, InternalError> {
     // Verify request signature (signs over transaction + encryption keys)
     verify_request_signature(
         ptb,
@@ -170,27 +283,103 @@ async fn check_request(
 
     println!("first contract_address: {:?}", contract_address);
 
+    // Check that the operator serves this contract
+    server.namespace_policy.check(&Namespace::Ethereum {
+        chain_id: chain_id().to_string(),
+        contract: contract_address,
+    })?;
+
     // Check certificate validity
     check_certificate(certificate, &contract_address).await?;
 
     debug!("Certificate validity checked successfully (req_id: {:?})", req_id);
 
-    // Check policy by simulating the transaction
-    let valid_key_ids: Vec<Vec<u8>> = check_seal_approve(
-        &contract_address,
-        ptb,
-        &certificate.user,
-        metrics,
-        req_id,
-    ).await?;
+    Ok(contract_address)
+}
 
-    debug!("Policy checked successfully (req_id: {:?})", req_id);
-    debug!("Key IDs count: {:?}", valid_key_ids.len());
+/// Handler for the `/v1/check_access_ethereum` endpoint.
+/// Evaluates the policy for each transaction without releasing any keys.
+#[utoipa::path(
+    post,
+    path = "/v1/check_access_ethereum",
+    request_body = ethereum::CheckAccessRequest,
+    params(crate::openapi::RequestHeaders),
+    responses(
+        (status = 200, description = "The access decisions", body = CheckAccessResponse),
+        (status = "4XX", description = "The request was rejected", body = ErrorResponse),
+        (status = "5XX", description = "The key server failed to handle the request", body = ErrorResponse),
+    )
+)]
+pub async fn handle_check_access(
+    State(app_state): State<MyState>,
+    headers: HeaderMap,
+    Json(payload): Json<CheckAccessRequest>,
+) -> Result<Json<CheckAccessResponse>, InternalError> {
+    let req_id = headers
+        .get(crate::REQUEST_ID_HEADER)
+        .map(|v| v.to_str().unwrap_or_default());
 
-    // Report metrics if available
-    if let Some(m) = metrics {
-        m.requests_per_number_of_ids.observe(valid_key_ids.len() as f64);
+    app_state.metrics.check_access_requests.inc();
+
+    check_access(&app_state, payload, req_id).await.map(Json)
+}
+
+/// Evaluates the policy of a check access request, including all checks of fetch key requests.
+/// Used by both the `/v1/check_access_ethereum` endpoint and the gRPC service.
+pub async fn check_access(
+    app_state: &MyState,
+    payload: CheckAccessRequest,
+    req_id: Option<&str>,
+) -> Result<CheckAccessResponse, InternalError> {
+    let metrics = &app_state.metrics;
+    let CheckAccessRequest { request, trace } = payload;
+
+    let result = async {
+        app_state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
+        let contract_address = validate_request(
+            &request.ptb,
+            &request.enc_key,
+            &request.enc_verification_key,
+            &request.request_signature,
+            &request.certificate,
+            Some(metrics),
+            req_id,
+            &app_state.server,
+        )
+        .await?;
+        // Like for Sui, the policy is evaluated once per key id, so a token is taken for each transaction
+        let transactions = parse_ptb(&request.ptb)?;
+        app_state.check_rate_limits(
+            &request_limits(
+                format!("{:?}", request.certificate.user),
+                request.certificate.session_vk.to_string(),
+                Some(format!("{:?}", contract_address)),
+            ),
+            transactions.len() as u32,
+        )?;
+        explain_seal_approve(
+            &contract_address,
+            &request.ptb,
+            &request.certificate.user,
+            request.version,
+            trace && app_state.developer_mode,
+            req_id,
+        )
+        .await
     }
+    .await;
 
-    Ok(valid_key_ids)
-}
\ No newline at end of file
+    let (decisions, block_number) = result.map_err(|e| {
+        warn!("check_access failed: {:?} (req_id: {:?})", e, req_id);
+        metrics.observe_error(e.as_str());
+        e
+    })?;
+
+    Ok(CheckAccessResponse {
+        decisions,
+        evidence: PolicyEvidence::EthereumBlock {
+            network_id: chain_id().to_string(),
+            block_number,
+        },
+    })
+}
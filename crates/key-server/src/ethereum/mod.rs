@@ -6,5 +6,7 @@ pub mod request;
 pub mod constants;
 pub mod ptb;
 
+pub use types::{FetchKeyRequest, FetchKeyResponse};
+
 #[cfg(test)]
 pub mod tests;
\ No newline at end of file
@@ -1,6 +1,8 @@
 use crate::errors::InternalError;
 use crate::metrics::Metrics;
 use crate::MyState;
+use crate::rate_limit::RateLimiter;
+use std::collections::HashMap;
 use crate::Server;
 use crate::ethereum::handler::handle_fetch_key;
 use crate::ethereum::types::{Certificate, FetchKeyRequest};
@@ -127,6 +129,11 @@ async fn test_handle_fetch_key_ethereum() {
         server: server.clone(),
         latest_checkpoint_timestamp_receiver: checkpoint_receiver,
         reference_gas_price: gas_price_receiver,
+        audit_log: None,
+        signing_key: Arc::new(Ed25519KeyPair::generate(&mut thread_rng())),
+        rate_limiter: Arc::new(RateLimiter::new(HashMap::new(), 0)),
+        developer_mode: false,
+        time_lock: None,
     };
     
     // Generate test keys
@@ -151,7 +158,7 @@ async fn test_handle_fetch_key_ethereum() {
     let (tx_data, tx_hash) = create_test_transaction();
     
     // Create ElGamal keys for encryption
-    let (enc_key, enc_vk) = create_test_elgamal_keys(&server.master_key);
+    let (enc_key, enc_vk) = create_test_elgamal_keys(&master_key);
     
     // Create request data
     let request_data = format!("{}{}{}", 
@@ -168,11 +175,12 @@ async fn test_handle_fetch_key_ethereum() {
         enc_verification_key: enc_vk,
         request_signature,
         certificate: certificate.clone(),
+        version: 0,
     };
     
     // Create headers
     let mut headers = HeaderMap::new();
-    headers.insert("x-request-id", "test-request-id".parse().unwrap());
+    headers.insert(crate::REQUEST_ID_HEADER, "test-request-id".parse().unwrap());
     
     // Mock the ureq client
     let mut mock_client = MockUreqClient::new();
@@ -3,15 +3,20 @@ use fastcrypto::ed25519::{Ed25519PublicKey, Ed25519Signature};
 use ethers::types::{Address, Signature};
 use std::str::FromStr;
 use crate::types::{ElGamalPublicKey, ElgamalEncryption, ElgamalVerificationKey};
+use crypto::audit::PolicyEvidence;
+use utoipa::ToSchema;
 
 /// The "session" certificate for Ethereum, signed by the user's Ethereum key.
 /// It authorizes a session key to act on the user's behalf.
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+#[schema(as = ethereum::Certificate)]
 pub struct Certificate {
     /// User's Ethereum address.
     #[serde(deserialize_with = "deserialize_address")]
+    #[schema(value_type = String)]
     pub user: Address,
     /// The Ed25519 public key of the session.
+    #[schema(value_type = String, format = Byte)]
     pub session_vk: Ed25519PublicKey,
     /// Timestamp of certificate creation (seconds since epoch).
     pub creation_time: u64,
@@ -19,6 +24,7 @@ pub struct Certificate {
     pub ttl_min: u16,
     /// Ethereum signature over (user_eth_address, session_vk, creation_time, ttl_min).
     #[serde(deserialize_with = "deserialize_signature")]
+    #[schema(value_type = String)]
     pub signature: Signature,
 }
 
@@ -41,34 +47,62 @@ where
 }
 
 /// Request structure for fetching a key with Ethereum context.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = ethereum::FetchKeyRequest)]
 pub struct FetchKeyRequest {
     /// Ethereum transaction payload
     pub ptb: String,
     /// User's ElGamal public key for encrypting the IBE key.
+    #[schema(value_type = String, format = Byte)]
     pub enc_key: ElGamalPublicKey,
     /// Verification key for the ElGamal public key.
+    #[schema(value_type = String, format = Byte)]
     pub enc_verification_key: ElgamalVerificationKey,
     /// Ed25519 signature by `certificate.session_vk` over (tx, enc_key, enc_verification_key).
+    #[schema(value_type = String, format = Byte)]
     pub request_signature: Ed25519Signature,
     /// The Ethereum certificate authorizing this request.
+    #[schema(value_type = ethereum::Certificate)]
     pub certificate: Certificate,
+    /// The version of the encrypted objects the keys are for. Version 0 objects use the zero-padded contract address
+    /// as package id, and version 1 objects the EVM namespace of the contract. Defaults to 0.
+    #[serde(default)]
+    pub version: u8,
+}
+
+/// Request structure for the check_access_ethereum endpoint.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = ethereum::CheckAccessRequest)]
+pub struct CheckAccessRequest {
+    #[serde(flatten)]
+    #[schema(value_type = ethereum::FetchKeyRequest)]
+    pub request: FetchKeyRequest,
+    /// Return the full call traces. Ignored unless the key server runs in developer mode.
+    #[serde(default)]
+    pub trace: bool,
 }
 
 // Key ID for Ethereum is a vector of bytes
 type KeyId = Vec<u8>;
 
 /// Structure for an encrypted decryption key
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = ethereum::DecryptionKey)]
 pub struct DecryptionKey {
     pub id: KeyId,
+    #[schema(value_type = Vec<String>)]
     pub encrypted_key: ElgamalEncryption,
 }
 
 /// Response structure for the fetch_key_eth endpoint.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = ethereum::FetchKeyResponse)]
 pub struct FetchKeyResponse {
+    #[schema(value_type = Vec<ethereum::DecryptionKey>)]
     pub decryption_keys: Vec<DecryptionKey>,
+    /// The evidence the policy was evaluated against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<PolicyEvidence>,
 }
 
 #[derive(Serialize, Deserialize)]
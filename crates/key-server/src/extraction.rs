@@ -0,0 +1,200 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Extraction of user secret keys off the async runtime.
+//!
+//! Extracting a key hashes the id to G1 and multiplies it with the master key, and the key is then encrypted with
+//! ElGamal under the key of the user. This is too slow to run on the async executor for requests with many ids, so it
+//! runs on a dedicated, bounded pool of threads, and the ids of a request are handled in parallel. The hashes of
+//! recently requested ids are cached since popular ids, e.g., time-lock ids, are requested by many users.
+//!
+//! The number of jobs on the pool is bounded and each job has a deadline, so an overloaded key server rejects requests
+//! instead of queueing them indefinitely, and clients can turn to other key servers.
+
+use crate::cache::Cache;
+use crate::errors::InternalError;
+use crate::types::{ElGamalPublicKey, ElgamalEncryption, IbeMasterKey};
+use crate::KeyId;
+use crypto::elgamal::encrypt;
+use crypto::ibe;
+use fastcrypto::groups::bls12381::G1Element;
+use rand::thread_rng;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::available_parallelism;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+/// The number of hashed ids to cache.
+pub(crate) const HASHED_ID_CACHE_SIZE: usize = 10_000;
+/// Hashes never change, so they are only evicted when the cache is full or after a long time.
+pub(crate) const HASHED_ID_CACHE_TTL: u64 = 60 * 60 * 1000; // 1 hour
+/// The maximum number of jobs queued or running on the extraction pool. Further jobs are rejected.
+pub(crate) const MAX_QUEUED_JOBS: usize = 1000;
+/// The time to wait for a job on the extraction pool, including the time it is queued.
+pub(crate) const EXTRACTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) struct KeyExtractor {
+    master_key: IbeMasterKey,
+    pool: ThreadPool,
+    hashed_ids: Cache<KeyId, G1Element>,
+    /// The number of jobs queued or running on the pool.
+    queued_jobs: AtomicUsize,
+    max_queued_jobs: usize,
+    timeout: Duration,
+}
+
+impl KeyExtractor {
+    /// Create an extractor with a pool of the given number of threads. Panics if the pool cannot be created.
+    pub fn new(master_key: IbeMasterKey, threads: usize) -> Self {
+        Self {
+            master_key,
+            pool: ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(|i| format!("key-extraction-{}", i))
+                .build()
+                .expect("Failed to create key extraction pool"),
+            hashed_ids: Cache::new(HASHED_ID_CACHE_TTL, HASHED_ID_CACHE_SIZE),
+            queued_jobs: AtomicUsize::new(0),
+            max_queued_jobs: MAX_QUEUED_JOBS,
+            timeout: EXTRACTION_TIMEOUT,
+        }
+    }
+
+    /// The number of extraction threads from `EXTRACTION_THREADS`, or the number of CPUs if it is not set.
+    /// Panics if the value is invalid.
+    pub fn threads_from_env() -> usize {
+        match env::var("EXTRACTION_THREADS") {
+            Ok(threads) => threads.parse().expect("Invalid EXTRACTION_THREADS"),
+            Err(_) => available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Extract the user secret key for an id.
+    pub fn extract(&self, id: &KeyId) -> ibe::UserSecretKey {
+        let hashed_id = self.hashed_ids.get(id).unwrap_or_else(|| {
+            let hashed_id = ibe::hash_id(id);
+            self.hashed_ids.insert(id.clone(), hashed_id);
+            hashed_id
+        });
+        ibe::extract_from_hashed_id(&self.master_key, &hashed_id)
+    }
+
+    /// Extract the user secret keys for the given ids on the extraction pool. The keys are returned in the order of
+    /// the ids.
+    pub async fn extract_batch(
+        self: &Arc<Self>,
+        ids: Vec<KeyId>,
+    ) -> Result<Vec<ibe::UserSecretKey>, InternalError> {
+        self.run(move |extractor| ids.par_iter().map(|id| extractor.extract(id)).collect())
+            .await
+    }
+
+    /// Extract the user secret keys for the given ids and encrypt them under the given ElGamal key. The work is done on
+    /// the extraction pool, and the keys are returned in the order of the ids.
+    pub async fn extract_and_encrypt(
+        self: &Arc<Self>,
+        ids: Vec<KeyId>,
+        enc_key: ElGamalPublicKey,
+    ) -> Result<Vec<(KeyId, ElgamalEncryption)>, InternalError> {
+        self.run(move |extractor| {
+            ids.into_par_iter()
+                .map(|id| {
+                    let key = extractor.extract(&id);
+                    let encrypted_key = encrypt(&mut thread_rng(), &key, &enc_key);
+                    (id, encrypted_key)
+                })
+                .collect()
+        })
+        .await
+    }
+
+    /// Run a job on the extraction pool. Fails with [InternalError::Overloaded] if too many jobs are queued or if the
+    /// job does not finish in time. A job that times out still runs to completion and counts towards the queue.
+    async fn run<T: Send + 'static>(
+        self: &Arc<Self>,
+        job: impl FnOnce(&Self) -> T + Send + 'static,
+    ) -> Result<T, InternalError> {
+        if self.queued_jobs.fetch_add(1, Ordering::Relaxed) >= self.max_queued_jobs {
+            self.queued_jobs.fetch_sub(1, Ordering::Relaxed);
+            return Err(InternalError::Overloaded);
+        }
+        let extractor = self.clone();
+        let (sender, receiver) = oneshot::channel();
+        self.pool.spawn(move || {
+            let result = job(&extractor);
+            extractor.queued_jobs.fetch_sub(1, Ordering::Relaxed);
+            // The request may have been dropped in the meantime
+            let _ = sender.send(result);
+        });
+        timeout(self.timeout, receiver)
+            .await
+            .map_err(|_| InternalError::Overloaded)?
+            .map_err(|_| InternalError::Failure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::elgamal;
+
+    #[tokio::test]
+    async fn test_extract_and_encrypt() {
+        let (master_key, public_key) = ibe::generate_key_pair(&mut thread_rng());
+        let extractor = Arc::new(KeyExtractor::new(master_key, 2));
+        let (sk, pk, _) = elgamal::genkey::<_, ibe::PublicKey, _>(&mut thread_rng());
+
+        let ids = (0..10u8).map(|i| vec![i; 3]).collect::<Vec<_>>();
+        let keys = extractor
+            .extract_and_encrypt(ids.clone(), pk.clone())
+            .await
+            .unwrap();
+        assert_eq!(keys.len(), ids.len());
+        for ((id, encrypted_key), expected_id) in keys.iter().zip(&ids) {
+            assert_eq!(id, expected_id);
+            let key = elgamal::decrypt(&sk, encrypted_key);
+            assert_eq!(key, ibe::extract(&master_key, id));
+            assert!(ibe::verify_user_secret_key(&key, id, &public_key).is_ok());
+        }
+
+        // Cached hashes give the same keys
+        assert_eq!(
+            extractor.extract(&ids[0]),
+            ibe::extract(&master_key, &ids[0])
+        );
+        assert_eq!(
+            extractor.extract_batch(ids.clone()).await.unwrap(),
+            ids.iter()
+                .map(|id| ibe::extract(&master_key, id))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_overloaded() {
+        let (master_key, _) = ibe::generate_key_pair(&mut thread_rng());
+        let mut extractor = KeyExtractor::new(master_key, 1);
+        extractor.max_queued_jobs = 1;
+        extractor.timeout = Duration::from_millis(100);
+        let extractor = Arc::new(extractor);
+
+        // A slow job times out and occupies the queue until it finishes
+        let slow_job = |_: &KeyExtractor| std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(
+            extractor.run(slow_job).await,
+            Err(InternalError::Overloaded)
+        );
+        assert_eq!(
+            extractor.extract_batch(vec![vec![1]]).await,
+            Err(InternalError::Overloaded)
+        );
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(extractor.extract_batch(vec![vec![1]]).await.is_ok());
+    }
+}
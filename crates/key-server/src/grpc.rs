@@ -0,0 +1,603 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! gRPC API of the key server, see `proto/seal.proto`. The requests are evaluated exactly as the requests of the JSON
+//! HTTP API, but keys, signatures and group elements are sent in their binary encodings.
+//!
+//! Like for the HTTP API, clients must send their SDK version in the `client-sdk-version` metadata, and all responses,
+//! including errors, are signed with the server's signing key. The signature and its timestamp are returned in the
+//! `x-keyserver-signature` and `x-keyserver-timestamp` metadata, except for batches where each result carries its own
+//! signature. If TLS is configured for the HTTP API, the gRPC API is served over TLS with the same certificate.
+
+use crate::errors::InternalError::InvalidSDKVersion;
+use crate::errors::{with_retry_after, InternalError};
+use crate::ethereum;
+use crate::externals::current_epoch_time;
+use crate::rate_limit::LimitKind;
+use crate::signed_message::signed_response;
+use crate::tls::TlsConfig;
+use crate::types::{AccessDecision, CheckAccessResponse};
+use crate::{
+    Certificate, CheckAccessRequest, DecryptionKey, FetchKeyRequest, FetchKeyResponse, MyState,
+    ALLOWED_STALENESS, MAX_BATCH_CONCURRENCY, MAX_BATCH_SIZE, PACKAGE_VERSION,
+};
+use crypto::audit::PolicyEvidence;
+use ethers::types::{Address, Signature};
+use fastcrypto::encoding::{Base64, Encoding};
+use fastcrypto::traits::{KeyPair, Signer, ToFromBytes};
+use futures::stream::{self, BoxStream, StreamExt};
+use prost::Message;
+use proto::key_server_server::{KeyServer, KeyServerServer};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use sui_sdk::types::base_types::SuiAddress;
+use sui_sdk::types::signature::GenericSignature;
+use tap::TapFallible;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Request, Response, Status};
+use tracing::{info, warn};
+
+pub(crate) mod proto {
+    tonic::include_proto!("seal.v1");
+}
+
+/// Serve the gRPC API on the given port, over TLS if configured.
+pub(crate) async fn serve(
+    state: MyState,
+    port: u16,
+    tls_config: Option<TlsConfig>,
+) -> anyhow::Result<()> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    info!("Serving gRPC on {} (TLS: {})", addr, tls_config.is_some());
+    let mut builder = tonic::transport::Server::builder();
+    if let Some(tls_config) = tls_config {
+        builder = builder.tls_config(tls_config.grpc_tls_config()?)?;
+    }
+    builder
+        .add_service(KeyServerServer::new(KeyServerService { state }))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+struct KeyServerService {
+    state: MyState,
+}
+
+impl KeyServerService {
+    /// Limit the number of requests per client IP, check the SDK version and return the request id, if given.
+    /// See [crate::rate_limit::RateLimiter::client_ip] for how the IP is found.
+    fn check_client<T>(&self, request: &Request<T>) -> Result<Option<String>, Status> {
+        let forwarded_for = request
+            .metadata()
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok());
+        let peer = request.remote_addr().map(|addr| addr.ip());
+        if let Some(ip) = self.state.rate_limiter.client_ip(forwarded_for, peer) {
+            self.state
+                .check_rate_limits(&[(LimitKind::Ip, ip)], 1)
+                .tap_err(|e| self.state.metrics.observe_error(e.as_str()))?;
+        }
+        let req_id = request
+            .metadata()
+            .get("request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let version = request.metadata().get("client-sdk-version");
+        info!("gRPC request id: {:?}, SDK version: {:?}", req_id, version);
+        version
+            .ok_or(InvalidSDKVersion)
+            .and_then(|v| v.to_str().map_err(|_| InvalidSDKVersion))
+            .and_then(|v| self.state.validate_sdk_version(v))
+            .tap_err(|e| {
+                warn!("Invalid SDK version: {:?}", e);
+                self.state.metrics.observe_error(e.as_str());
+            })?;
+        Ok(req_id)
+    }
+
+    /// Sign the outcome of a request, see the `sign_response` middleware of the HTTP API. The gRPC status code is
+    /// signed instead of the HTTP status, and the message of the status is signed for errors.
+    fn sign<R: Message>(
+        &self,
+        request_body: &[u8],
+        result: Result<R, Status>,
+    ) -> Result<Response<R>, Status> {
+        match result {
+            Ok(response) => {
+                let signature = sign(
+                    &self.state,
+                    request_body,
+                    Code::Ok,
+                    &response.encode_to_vec(),
+                );
+                let mut response = Response::new(response);
+                insert_signature(response.metadata_mut(), signature);
+                Ok(response)
+            }
+            Err(status) => Err(self.sign_status(request_body, status)),
+        }
+    }
+
+    fn sign_status(&self, request_body: &[u8], status: Status) -> Status {
+        let signature = sign(
+            &self.state,
+            request_body,
+            status.code(),
+            status.message().as_bytes(),
+        );
+        let mut metadata = status.metadata().clone();
+        insert_signature(&mut metadata, signature);
+        Status::with_metadata(status.code(), status.message(), metadata)
+    }
+}
+
+/// The signature of the server on a response and the timestamp it was created at. See [signed_response].
+fn sign(state: &MyState, request_body: &[u8], code: Code, response_body: &[u8]) -> (Vec<u8>, u64) {
+    let timestamp = current_epoch_time();
+    let signature = state.signing_key.sign(&signed_response(
+        &state.server.key_server_object_id,
+        request_body,
+        code as u16,
+        response_body,
+        timestamp,
+    ));
+    (signature.as_bytes().to_vec(), timestamp)
+}
+
+fn insert_signature(metadata: &mut MetadataMap, (signature, timestamp): (Vec<u8>, u64)) {
+    metadata.insert(
+        "x-keyserver-signature",
+        MetadataValue::try_from(Base64::encode(signature))
+            .expect("Base64 is a valid metadata value"),
+    );
+    metadata.insert("x-keyserver-timestamp", MetadataValue::from(timestamp));
+}
+
+#[tonic::async_trait]
+impl KeyServer for KeyServerService {
+    async fn get_service(
+        &self,
+        request: Request<proto::GetServiceRequest>,
+    ) -> Result<Response<proto::GetServiceResponse>, Status> {
+        let request_body = request.get_ref().encode_to_vec();
+        let result: Result<proto::GetServiceResponse, Status> = with_retry_after(async {
+            self.check_client(&request)?;
+            self.state.metrics.service_requests.inc();
+            Ok(proto::GetServiceResponse {
+                service_id: self.state.server.key_server_object_id.into_bytes().to_vec(),
+                pop: to_bcs(&self.state.server.key_server_object_id_sig),
+                version: PACKAGE_VERSION.to_string(),
+                signing_key: self.state.signing_key.public().as_bytes().to_vec(),
+            })
+        })
+        .await;
+        self.sign(&request_body, result)
+    }
+
+    async fn fetch_key(
+        &self,
+        request: Request<proto::FetchKeyRequest>,
+    ) -> Result<Response<proto::FetchKeyResponse>, Status> {
+        let request_body = request.get_ref().encode_to_vec();
+        let result: Result<proto::FetchKeyResponse, Status> = with_retry_after(async {
+            let req_id = self.check_client(&request)?;
+            self.state.metrics.requests.inc();
+            self.state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
+            let response = crate::fetch_key(
+                &self.state,
+                request.into_inner().try_into()?,
+                req_id.as_deref(),
+            )
+            .await?;
+            Ok(response.into())
+        })
+        .await;
+        self.sign(&request_body, result)
+    }
+
+    async fn fetch_key_ethereum(
+        &self,
+        request: Request<proto::FetchKeyEthereumRequest>,
+    ) -> Result<Response<proto::FetchKeyResponse>, Status> {
+        let request_body = request.get_ref().encode_to_vec();
+        let result: Result<proto::FetchKeyResponse, Status> = with_retry_after(async {
+            let req_id = self.check_client(&request)?;
+            self.state.metrics.requests.inc();
+            self.state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
+            let response = ethereum::handler::fetch_key(
+                &self.state,
+                request.into_inner().try_into()?,
+                req_id.as_deref(),
+            )
+            .await?;
+            Ok(response.into())
+        })
+        .await;
+        self.sign(&request_body, result)
+    }
+
+    async fn check_access(
+        &self,
+        request: Request<proto::CheckAccessRequest>,
+    ) -> Result<Response<proto::CheckAccessResponse>, Status> {
+        let request_body = request.get_ref().encode_to_vec();
+        let result: Result<proto::CheckAccessResponse, Status> = with_retry_after(async {
+            let req_id = self.check_client(&request)?;
+            self.state.metrics.check_access_requests.inc();
+            self.state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
+            let proto::CheckAccessRequest { request, trace } = request.into_inner();
+            let payload = CheckAccessRequest {
+                request: request.ok_or_else(|| missing("request"))?.try_into()?,
+                trace,
+            };
+            let response = crate::check_access(&self.state, payload, req_id.as_deref()).await?;
+            Ok(response.into())
+        })
+        .await;
+        self.sign(&request_body, result)
+    }
+
+    async fn check_access_ethereum(
+        &self,
+        request: Request<proto::CheckAccessEthereumRequest>,
+    ) -> Result<Response<proto::CheckAccessResponse>, Status> {
+        let request_body = request.get_ref().encode_to_vec();
+        let result: Result<proto::CheckAccessResponse, Status> = with_retry_after(async {
+            let req_id = self.check_client(&request)?;
+            self.state.metrics.check_access_requests.inc();
+            let proto::CheckAccessEthereumRequest { request, trace } = request.into_inner();
+            let payload = ethereum::types::CheckAccessRequest {
+                request: request.ok_or_else(|| missing("request"))?.try_into()?,
+                trace,
+            };
+            let response =
+                ethereum::handler::check_access(&self.state, payload, req_id.as_deref()).await?;
+            Ok(response.into())
+        })
+        .await;
+        self.sign(&request_body, result)
+    }
+
+    type FetchKeysBatchStream = BoxStream<'static, Result<proto::BatchResult, Status>>;
+
+    async fn fetch_keys_batch(
+        &self,
+        request: Request<proto::FetchKeysBatchRequest>,
+    ) -> Result<Response<Self::FetchKeysBatchStream>, Status> {
+        let request_body: Arc<[u8]> = request.get_ref().encode_to_vec().into();
+        let checked: Result<_, Status> = with_retry_after(async {
+            let req_id = self.check_client(&request)?;
+            self.state.metrics.batch_requests.inc();
+            if request.get_ref().requests.len() > MAX_BATCH_SIZE {
+                self.state
+                    .metrics
+                    .observe_error(InternalError::BatchTooLarge.as_str());
+                return Err(InternalError::BatchTooLarge.into());
+            }
+            self.state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
+            Ok(req_id)
+        })
+        .await;
+        let req_id = match checked {
+            Ok(req_id) => req_id,
+            Err(status) => return Err(self.sign_status(&request_body, status)),
+        };
+        let requests = request.into_inner().requests;
+
+        // Each result is signed separately since they are streamed as soon as they are ready.
+        let state = self.state.clone();
+        let results = stream::iter(requests.into_iter().enumerate())
+            .map(move |(index, entry)| {
+                let state = state.clone();
+                let req_id = req_id.clone();
+                let request_body = request_body.clone();
+                async move {
+                    state.metrics.requests.inc();
+                    let (code, result) = match fetch_batch_entry(&state, entry, req_id.as_deref())
+                        .await
+                    {
+                        Ok(response) => (Code::Ok, proto::batch_result::Result::Response(response)),
+                        Err(status) => (
+                            status.code(),
+                            proto::batch_result::Result::Error(proto::Error {
+                                code: status.code() as i32,
+                                message: status.message().to_string(),
+                            }),
+                        ),
+                    };
+                    let mut result = proto::BatchResult {
+                        index: index as u32,
+                        result: Some(result),
+                        signature: vec![],
+                        timestamp: 0,
+                    };
+                    (result.signature, result.timestamp) =
+                        sign(&state, &request_body, code, &result.encode_to_vec());
+                    Ok(result)
+                }
+            })
+            .buffer_unordered(MAX_BATCH_CONCURRENCY);
+        Ok(Response::new(results.boxed()))
+    }
+}
+
+async fn fetch_batch_entry(
+    state: &MyState,
+    entry: proto::BatchRequest,
+    req_id: Option<&str>,
+) -> Result<proto::FetchKeyResponse, Status> {
+    match entry.request.ok_or_else(|| missing("request"))? {
+        proto::batch_request::Request::Sui(request) => {
+            Ok(crate::fetch_key(state, request.try_into()?, req_id)
+                .await?
+                .into())
+        }
+        proto::batch_request::Request::Ethereum(request) => {
+            Ok(
+                ethereum::handler::fetch_key(state, request.try_into()?, req_id)
+                    .await?
+                    .into(),
+            )
+        }
+    }
+}
+
+fn missing(field: &str) -> Status {
+    Status::invalid_argument(format!("Missing {}", field))
+}
+
+fn invalid(field: &str) -> Status {
+    Status::invalid_argument(format!("Invalid {}", field))
+}
+
+fn from_bcs<T: DeserializeOwned>(bytes: &[u8], field: &str) -> Result<T, Status> {
+    bcs::from_bytes(bytes).map_err(|_| invalid(field))
+}
+
+fn from_bytes<T: ToFromBytes>(bytes: &[u8], field: &str) -> Result<T, Status> {
+    T::from_bytes(bytes).map_err(|_| invalid(field))
+}
+
+fn to_bcs<T: Serialize>(value: &T) -> Vec<u8> {
+    bcs::to_bytes(value).expect("serialization cannot fail")
+}
+
+impl TryFrom<proto::FetchKeyRequest> for FetchKeyRequest {
+    type Error = Status;
+
+    fn try_from(request: proto::FetchKeyRequest) -> Result<Self, Self::Error> {
+        let certificate = request.certificate.ok_or_else(|| missing("certificate"))?;
+        Ok(FetchKeyRequest {
+            ptb: Base64::encode(&request.ptb),
+            enc_key: from_bcs(&request.enc_key, "enc_key")?,
+            enc_verification_key: from_bcs(&request.enc_verification_key, "enc_verification_key")?,
+            request_signature: from_bytes(&request.request_signature, "request_signature")?,
+            certificate: Certificate {
+                user: SuiAddress::from_bytes(&certificate.user)
+                    .map_err(|_| invalid("certificate.user"))?,
+                session_vk: from_bytes(&certificate.session_vk, "certificate.session_vk")?,
+                creation_time: certificate.creation_time,
+                ttl_min: certificate
+                    .ttl_min
+                    .try_into()
+                    .map_err(|_| invalid("certificate.ttl_min"))?,
+                signature: from_bytes::<GenericSignature>(
+                    &certificate.signature,
+                    "certificate.signature",
+                )?,
+            },
+        })
+    }
+}
+
+impl TryFrom<proto::FetchKeyEthereumRequest> for ethereum::types::FetchKeyRequest {
+    type Error = Status;
+
+    fn try_from(request: proto::FetchKeyEthereumRequest) -> Result<Self, Self::Error> {
+        let certificate = request.certificate.ok_or_else(|| missing("certificate"))?;
+        if certificate.user.len() != Address::len_bytes() {
+            return Err(invalid("certificate.user"));
+        }
+        Ok(ethereum::types::FetchKeyRequest {
+            ptb: Base64::encode(&request.ptb),
+            enc_key: from_bcs(&request.enc_key, "enc_key")?,
+            enc_verification_key: from_bcs(&request.enc_verification_key, "enc_verification_key")?,
+            request_signature: from_bytes(&request.request_signature, "request_signature")?,
+            certificate: ethereum::types::Certificate {
+                user: Address::from_slice(&certificate.user),
+                session_vk: from_bytes(&certificate.session_vk, "certificate.session_vk")?,
+                creation_time: certificate.creation_time,
+                ttl_min: certificate
+                    .ttl_min
+                    .try_into()
+                    .map_err(|_| invalid("certificate.ttl_min"))?,
+                signature: Signature::try_from(certificate.signature.as_slice())
+                    .map_err(|_| invalid("certificate.signature"))?,
+            },
+            version: request.version.try_into().map_err(|_| invalid("version"))?,
+        })
+    }
+}
+
+impl From<PolicyEvidence> for proto::PolicyEvidence {
+    fn from(evidence: PolicyEvidence) -> Self {
+        let evidence = match evidence {
+            PolicyEvidence::SuiCheckpoint { timestamp_ms } => {
+                proto::policy_evidence::Evidence::SuiCheckpoint(proto::SuiCheckpoint {
+                    timestamp_ms,
+                })
+            }
+            PolicyEvidence::EthereumBlock {
+                network_id,
+                block_number,
+            } => proto::policy_evidence::Evidence::EthereumBlock(proto::EthereumBlock {
+                network_id,
+                block_number,
+            }),
+        };
+        proto::PolicyEvidence {
+            evidence: Some(evidence),
+        }
+    }
+}
+
+impl From<DecryptionKey> for proto::DecryptionKey {
+    fn from(key: DecryptionKey) -> Self {
+        proto::DecryptionKey {
+            encrypted_key: to_bcs(&key.encrypted_key),
+            id: key.id,
+        }
+    }
+}
+
+impl From<FetchKeyResponse> for proto::FetchKeyResponse {
+    fn from(response: FetchKeyResponse) -> Self {
+        proto::FetchKeyResponse {
+            decryption_keys: response
+                .decryption_keys
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            evidence: response.evidence.map(Into::into),
+        }
+    }
+}
+
+impl From<ethereum::types::FetchKeyResponse> for proto::FetchKeyResponse {
+    fn from(response: ethereum::types::FetchKeyResponse) -> Self {
+        proto::FetchKeyResponse {
+            decryption_keys: response
+                .decryption_keys
+                .into_iter()
+                .map(|key| proto::DecryptionKey {
+                    encrypted_key: to_bcs(&key.encrypted_key),
+                    id: key.id,
+                })
+                .collect(),
+            evidence: response.evidence.map(Into::into),
+        }
+    }
+}
+
+impl From<AccessDecision> for proto::AccessDecision {
+    fn from(decision: AccessDecision) -> Self {
+        proto::AccessDecision {
+            id: decision.id,
+            approved: decision.approved,
+            reason: decision.reason,
+            abort_code: decision.abort_code,
+            revert_data: decision.revert_data,
+            gas_used: decision.gas_used,
+            trace: decision.trace.map(|trace| trace.to_string()),
+        }
+    }
+}
+
+impl From<CheckAccessResponse> for proto::CheckAccessResponse {
+    fn from(response: CheckAccessResponse) -> Self {
+        proto::CheckAccessResponse {
+            decisions: response.decisions.into_iter().map(Into::into).collect(),
+            evidence: Some(response.evidence.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::set_retry_after;
+    use crate::types::{ElGamalPublicKey, ElgamalVerificationKey};
+    use crypto::elgamal;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::Signer;
+    use rand::thread_rng;
+    use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+    use sui_sdk::types::crypto::Signature as SuiSignature;
+    use tonic::Code;
+
+    #[test]
+    fn test_fetch_key_request_from_proto() {
+        let kp = Ed25519KeyPair::generate(&mut thread_rng());
+        let (_, pk, vk): (_, ElGamalPublicKey, ElgamalVerificationKey) =
+            elgamal::genkey(&mut thread_rng());
+        let msg_with_intent = IntentMessage::new(
+            Intent::personal_message(),
+            PersonalMessage {
+                message: b"certificate".to_vec(),
+            },
+        );
+        let cert_sig = GenericSignature::Signature(SuiSignature::new_secure(&msg_with_intent, &kp));
+        let request_signature = kp.sign(b"request");
+        let user: SuiAddress = kp.public().into();
+
+        let mut request = proto::FetchKeyRequest {
+            ptb: vec![1, 2, 3],
+            enc_key: to_bcs(&pk),
+            enc_verification_key: to_bcs(&vk),
+            request_signature: request_signature.as_bytes().to_vec(),
+            certificate: Some(proto::SuiCertificate {
+                user: user.to_vec(),
+                session_vk: kp.public().as_bytes().to_vec(),
+                creation_time: 1,
+                ttl_min: 10,
+                signature: cert_sig.as_bytes().to_vec(),
+            }),
+        };
+        let converted = FetchKeyRequest::try_from(request.clone()).unwrap();
+        assert_eq!(converted.ptb, Base64::encode([1, 2, 3]));
+        assert_eq!(to_bcs(&converted.enc_key), request.enc_key);
+        assert_eq!(
+            to_bcs(&converted.enc_verification_key),
+            request.enc_verification_key
+        );
+        assert_eq!(converted.request_signature, request_signature);
+        assert_eq!(converted.certificate.user, user);
+        assert_eq!(converted.certificate.ttl_min, 10);
+
+        request.certificate.as_mut().unwrap().ttl_min = u32::MAX;
+        assert_eq!(
+            FetchKeyRequest::try_from(request.clone())
+                .err()
+                .unwrap()
+                .code(),
+            Code::InvalidArgument
+        );
+
+        request.enc_key = vec![0; 10];
+        assert_eq!(
+            FetchKeyRequest::try_from(request.clone())
+                .err()
+                .unwrap()
+                .code(),
+            Code::InvalidArgument
+        );
+
+        request.certificate = None;
+        assert_eq!(
+            FetchKeyRequest::try_from(request).err().unwrap().message(),
+            "Missing certificate"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_from_error() {
+        let status = with_retry_after(async {
+            set_retry_after(3);
+            Status::from(InternalError::RateLimited)
+        })
+        .await;
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status.metadata().get("retry-after").unwrap(), "3");
+        assert_eq!(
+            Status::from(InternalError::NoAccess).code(),
+            Code::PermissionDenied
+        );
+        assert_eq!(
+            Status::from(InternalError::Failure).code(),
+            Code::Unavailable
+        );
+    }
+}
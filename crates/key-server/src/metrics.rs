@@ -3,7 +3,8 @@
 
 use prometheus::{
     register_histogram_with_registry, register_int_counter_vec_with_registry,
-    register_int_counter_with_registry, Histogram, IntCounter, IntCounterVec, Registry,
+    register_int_counter_with_registry, register_int_gauge_with_registry, Histogram, IntCounter,
+    IntCounterVec, IntGauge, Registry,
 };
 use std::time::Instant;
 
@@ -15,9 +16,21 @@ pub(crate) struct Metrics {
     /// Total number of service requests received
     pub service_requests: IntCounter,
 
+    /// Total number of fetch_keys_batch requests received
+    pub batch_requests: IntCounter,
+
+    /// Total number of check_access requests received
+    pub check_access_requests: IntCounter,
+
+    /// Total number of released_keys requests received
+    pub released_keys_requests: IntCounter,
+
     /// Total number of internal errors by type
     errors: IntCounterVec,
 
+    /// Total number of throttled requests by the limit that was exceeded
+    pub throttled_requests: IntCounterVec,
+
     /// Delay of timestamp of the latest checkpoint
     pub checkpoint_timestamp_delay: Histogram,
 
@@ -36,8 +49,20 @@ pub(crate) struct Metrics {
     /// Duration of fetch_pkg_ids
     pub fetch_pkg_ids_duration: Histogram,
 
+    /// Duration of extracting and encrypting the keys of a request, including waiting for the extraction pool
+    pub key_extraction_duration: Histogram,
+
     /// Total number of requests per number of ids
     pub requests_per_number_of_ids: Histogram,
+
+    /// Number of namespaces in the allow list, or -1 if all namespaces are allowed
+    pub allowed_namespaces: IntGauge,
+
+    /// Number of frozen namespaces
+    pub frozen_namespaces: IntGauge,
+
+    /// Total number of updates to the namespace policy
+    pub namespace_policy_updates: IntCounter,
 }
 
 impl Metrics {
@@ -49,6 +74,24 @@ impl Metrics {
                 registry
             )
             .unwrap(),
+            batch_requests: register_int_counter_with_registry!(
+                "batch_requests",
+                "Total number of fetch_keys_batch requests received",
+                registry
+            )
+            .unwrap(),
+            check_access_requests: register_int_counter_with_registry!(
+                "check_access_requests",
+                "Total number of check_access requests received",
+                registry
+            )
+            .unwrap(),
+            released_keys_requests: register_int_counter_with_registry!(
+                "released_keys_requests",
+                "Total number of released_keys requests received",
+                registry
+            )
+            .unwrap(),
             errors: register_int_counter_vec_with_registry!(
                 "internal_errors",
                 "Total number of internal errors by type",
@@ -56,6 +99,13 @@ impl Metrics {
                 registry
             )
             .unwrap(),
+            throttled_requests: register_int_counter_vec_with_registry!(
+                "throttled_requests",
+                "Total number of throttled requests by the limit that was exceeded",
+                &["limit"],
+                registry
+            )
+            .unwrap(),
             service_requests: register_int_counter_with_registry!(
                 "service_requests",
                 "Total number of service requests received",
@@ -90,6 +140,13 @@ impl Metrics {
                 registry
             )
             .unwrap(),
+            key_extraction_duration: register_histogram_with_registry!(
+                "key_extraction_duration",
+                "Duration of extracting and encrypting the keys of a request",
+                default_fast_call_duration_buckets(),
+                registry
+            )
+            .unwrap(),
             check_policy_duration: register_histogram_with_registry!(
                 "check_policy_duration",
                 "Duration of check_policy",
@@ -111,6 +168,24 @@ impl Metrics {
                 registry
             )
             .unwrap(),
+            allowed_namespaces: register_int_gauge_with_registry!(
+                "allowed_namespaces",
+                "Number of namespaces in the allow list, or -1 if all namespaces are allowed",
+                registry
+            )
+            .unwrap(),
+            frozen_namespaces: register_int_gauge_with_registry!(
+                "frozen_namespaces",
+                "Number of frozen namespaces",
+                registry
+            )
+            .unwrap(),
+            namespace_policy_updates: register_int_counter_with_registry!(
+                "namespace_policy_updates",
+                "Total number of updates to the namespace policy",
+                registry
+            )
+            .unwrap(),
         }
     }
 
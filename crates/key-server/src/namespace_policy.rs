@@ -0,0 +1,161 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::errors::InternalError;
+use crate::metrics::Metrics;
+use ethers::types::Address;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sui_sdk::types::base_types::ObjectID;
+
+/// A namespace of key ids which is served by the key server.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "chain", rename_all = "snake_case")]
+pub(crate) enum Namespace {
+    /// A Sui package, identified by the id of its first version.
+    Sui { package_id: ObjectID },
+    /// An EVM contract on the given chain.
+    Ethereum { chain_id: String, contract: Address },
+}
+
+/// The namespaces an operator has chosen to serve.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct NamespacePolicy {
+    /// If set, only these namespaces are served.
+    #[serde(default)]
+    pub allowed: Option<BTreeSet<Namespace>>,
+    /// Namespaces that are not served, e.g., during an incident. Takes precedence over `allowed`.
+    #[serde(default)]
+    pub frozen: BTreeSet<Namespace>,
+}
+
+impl NamespacePolicy {
+    pub fn check(&self, namespace: &Namespace) -> Result<(), InternalError> {
+        if self.frozen.contains(namespace) {
+            return Err(InternalError::NamespaceFrozen);
+        }
+        if self
+            .allowed
+            .as_ref()
+            .is_some_and(|allowed| !allowed.contains(namespace))
+        {
+            return Err(InternalError::NamespaceNotAllowed);
+        }
+        Ok(())
+    }
+}
+
+/// A [NamespacePolicy] which can be edited at runtime. If a path is given, updates are persisted to this file.
+#[derive(Default)]
+pub(crate) struct NamespacePolicyStore {
+    path: Option<PathBuf>,
+    policy: RwLock<NamespacePolicy>,
+}
+
+impl NamespacePolicyStore {
+    /// Open the policy stored at the given path. If the file does not exist, all namespaces are served until the
+    /// policy is updated.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let policy = if path.exists() {
+            serde_json::from_slice(&fs::read(path)?)?
+        } else {
+            NamespacePolicy::default()
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            policy: RwLock::new(policy),
+        })
+    }
+
+    pub fn check(&self, namespace: &Namespace) -> Result<(), InternalError> {
+        self.policy.read().check(namespace)
+    }
+
+    pub fn get(&self) -> NamespacePolicy {
+        self.policy.read().clone()
+    }
+
+    /// Apply an update to the policy. The update is persisted before it takes effect.
+    pub fn update(
+        &self,
+        update: impl FnOnce(&mut NamespacePolicy),
+    ) -> anyhow::Result<NamespacePolicy> {
+        let mut policy = self.policy.write();
+        let mut updated = policy.clone();
+        update(&mut updated);
+        if let Some(path) = &self.path {
+            // Write to a temporary file first so a crash never leaves a partially written policy.
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_vec_pretty(&updated)?)?;
+            fs::rename(&tmp, path)?;
+        }
+        *policy = updated.clone();
+        Ok(updated)
+    }
+
+    pub fn update_metrics(&self, metrics: &Metrics) {
+        let policy = self.policy.read();
+        metrics.allowed_namespaces.set(
+            policy
+                .allowed
+                .as_ref()
+                .map_or(-1, |allowed| allowed.len() as i64),
+        );
+        metrics.frozen_namespaces.set(policy.frozen.len() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sui(byte: u8) -> Namespace {
+        Namespace::Sui {
+            package_id: ObjectID::from_single_byte(byte),
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        let evm = Namespace::Ethereum {
+            chain_id: "84532".to_string(),
+            contract: Address::from_low_u64_be(1),
+        };
+        let mut policy = NamespacePolicy::default();
+        assert!(policy.check(&sui(1)).is_ok());
+        assert!(policy.check(&evm).is_ok());
+
+        policy.frozen.insert(sui(1));
+        assert_eq!(policy.check(&sui(1)), Err(InternalError::NamespaceFrozen));
+        assert!(policy.check(&sui(2)).is_ok());
+
+        policy.allowed = Some(BTreeSet::from([sui(1), evm.clone()]));
+        assert_eq!(policy.check(&sui(1)), Err(InternalError::NamespaceFrozen));
+        assert_eq!(
+            policy.check(&sui(2)),
+            Err(InternalError::NamespaceNotAllowed)
+        );
+        assert!(policy.check(&evm).is_ok());
+    }
+
+    #[test]
+    fn test_store_persists_updates() {
+        let path = std::env::temp_dir().join(format!("policy-{}.json", rand::random::<u64>()));
+        let store = NamespacePolicyStore::open(&path).unwrap();
+        store
+            .update(|policy| {
+                policy.frozen.insert(sui(1));
+            })
+            .unwrap();
+        assert_eq!(store.check(&sui(1)), Err(InternalError::NamespaceFrozen));
+
+        let reopened = NamespacePolicyStore::open(&path).unwrap();
+        assert_eq!(reopened.get(), store.get());
+        assert_eq!(reopened.check(&sui(1)), Err(InternalError::NamespaceFrozen));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
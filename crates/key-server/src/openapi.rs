@@ -0,0 +1,135 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! OpenAPI document of the key server API, generated from the request and response types of the handlers. Served at
+//! `/v1/openapi.json` so clients can be generated from it.
+
+use crate::errors::{ErrorResponse, InternalError};
+use crate::ethereum;
+use crate::time_lock::{self, ReleasedKey, ReleasedKeysResponse};
+use crate::types::{AccessDecision, CheckAccessResponse};
+use crate::{
+    BatchRequestEntry, BatchResponseEntry, Certificate, CheckAccessRequest, DecryptionKey,
+    FetchKeyRequest, FetchKeyResponse, FetchKeysBatchRequest, FetchKeysBatchResponse,
+    GetServiceResponse,
+};
+use axum::Json;
+use crypto::audit::PolicyEvidence;
+use utoipa::{IntoParams, OpenApi};
+
+/// Headers of the requests to the key server API.
+#[derive(IntoParams)]
+#[into_params(parameter_in = Header)]
+#[allow(dead_code)]
+pub(crate) struct RequestHeaders {
+    /// The version of the client SDK. Requests from unsupported versions are rejected.
+    #[param(rename = "Client-Sdk-Version")]
+    client_sdk_version: String,
+    /// An id of the request used in the logs of the key server.
+    #[param(rename = "Request-Id")]
+    request_id: Option<String>,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Seal key server",
+        description = "All responses are signed by the key server, see the `X-KeyServer-Signature` and \
+            `X-KeyServer-Timestamp` headers."
+    ),
+    paths(
+        crate::handle_get_service,
+        crate::handle_fetch_key,
+        crate::handle_fetch_keys_batch,
+        crate::handle_check_access,
+        ethereum::handler::handle_fetch_key,
+        ethereum::handler::handle_check_access,
+        time_lock::handle_released_keys,
+    ),
+    components(schemas(
+        Certificate,
+        FetchKeyRequest,
+        DecryptionKey,
+        FetchKeyResponse,
+        FetchKeysBatchRequest,
+        FetchKeysBatchResponse,
+        BatchRequestEntry,
+        BatchResponseEntry,
+        CheckAccessRequest,
+        CheckAccessResponse,
+        AccessDecision,
+        PolicyEvidence,
+        GetServiceResponse,
+        ReleasedKey,
+        ReleasedKeysResponse,
+        ErrorResponse,
+        InternalError,
+        ethereum::types::Certificate,
+        ethereum::types::FetchKeyRequest,
+        ethereum::types::DecryptionKey,
+        ethereum::types::FetchKeyResponse,
+        ethereum::types::CheckAccessRequest,
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+pub(crate) async fn handle_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn collect_refs<'a>(value: &'a Value, refs: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => map.iter().for_each(|(key, value)| match value {
+                Value::String(reference) if key == "$ref" => refs.push(reference),
+                _ => collect_refs(value, refs),
+            }),
+            Value::Array(values) => values.iter().for_each(|value| collect_refs(value, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_openapi_document() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+        for path in [
+            "/v1/service",
+            "/v1/fetch_key",
+            "/v1/fetch_keys_batch",
+            "/v1/check_access",
+            "/v1/fetch_key_ethereum",
+            "/v1/check_access_ethereum",
+            "/v1/released_keys/{namespace}",
+        ] {
+            assert!(doc["paths"].get(path).is_some(), "Missing path {}", path);
+        }
+
+        // The Sui and Ethereum types must not shadow each other.
+        let schemas = doc["components"]["schemas"].as_object().unwrap();
+        for schema in [
+            "FetchKeyRequest",
+            "ethereum.FetchKeyRequest",
+            "Certificate",
+            "ethereum.Certificate",
+            "InternalError",
+            "PolicyEvidence",
+        ] {
+            assert!(schemas.contains_key(schema), "Missing schema {}", schema);
+        }
+
+        let mut refs = Vec::new();
+        collect_refs(&doc, &mut refs);
+        assert!(!refs.is_empty());
+        for reference in refs {
+            let name = reference
+                .strip_prefix("#/components/schemas/")
+                .unwrap_or_else(|| panic!("Unexpected reference {}", reference));
+            assert!(schemas.contains_key(name), "Unresolved reference {}", name);
+        }
+    }
+}
@@ -0,0 +1,400 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::externals::current_epoch_time;
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::num::NonZero;
+use std::str::FromStr;
+
+/// The maximum number of buckets kept in memory. The least recently used buckets are dropped first, which only
+/// resets the limit for clients that have not made requests recently.
+pub(crate) const RATE_LIMITER_SIZE: usize = 100_000;
+
+/// The properties of a request that are rate limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum LimitKind {
+    /// The IP of the client, see [RateLimiter::client_ip].
+    Ip,
+    /// The user from the certificate.
+    User,
+    /// The session key from the certificate.
+    SessionKey,
+    /// The package id (Sui) or contract address (EVM) of the policy.
+    Namespace,
+}
+
+impl LimitKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitKind::Ip => "ip",
+            LimitKind::User => "user",
+            LimitKind::SessionKey => "session_key",
+            LimitKind::Namespace => "namespace",
+        }
+    }
+
+    fn env_var(&self) -> &'static str {
+        match self {
+            LimitKind::Ip => "RATE_LIMIT_IP",
+            LimitKind::User => "RATE_LIMIT_USER",
+            LimitKind::SessionKey => "RATE_LIMIT_SESSION_KEY",
+            LimitKind::Namespace => "RATE_LIMIT_NAMESPACE",
+        }
+    }
+}
+
+/// A limit of `requests` per `period_ms`, allowing bursts of up to `requests` requests.
+/// Parsed from strings of the form `<requests>/<seconds>`, e.g., `100/60`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Limit {
+    requests: u32,
+    period_ms: u64,
+}
+
+impl Limit {
+    pub fn new(requests: u32, period_ms: u64) -> Self {
+        assert!(
+            requests > 0 && period_ms > 0,
+            "Requests and period must be greater than 0"
+        );
+        Self {
+            requests,
+            period_ms,
+        }
+    }
+
+    fn refill_per_ms(&self) -> f64 {
+        self.requests as f64 / self.period_ms as f64
+    }
+}
+
+impl FromStr for Limit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid rate limit '{}', expected <requests>/<seconds>", s);
+        let (requests, seconds) = s.split_once('/').ok_or_else(invalid)?;
+        let requests: u32 = requests.trim().parse().map_err(|_| invalid())?;
+        let seconds: u64 = seconds.trim().parse().map_err(|_| invalid())?;
+        if requests == 0 || seconds == 0 {
+            return Err(invalid());
+        }
+        Ok(Self::new(requests, seconds * 1000))
+    }
+}
+
+/// The rate limited properties of a fetch key request, except the client IP which is limited by a middleware.
+pub(crate) fn request_limits(
+    user: String,
+    session_key: String,
+    namespace: Option<String>,
+) -> Vec<(LimitKind, String)> {
+    let mut limited = vec![
+        (LimitKind::User, user),
+        (LimitKind::SessionKey, session_key),
+    ];
+    limited.extend(namespace.map(|ns| (LimitKind::Namespace, ns)));
+    limited
+}
+
+/// Why a request was rejected by the [RateLimiter].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum LimitExceeded {
+    /// The bucket of the given kind has too few tokens. Enough tokens are available after the given number of seconds.
+    Wait(LimitKind, u64),
+    /// The request needs more tokens than the bucket of the given kind can hold, so it is never accepted.
+    TooManyTokens(LimitKind),
+}
+
+impl LimitExceeded {
+    pub fn kind(&self) -> LimitKind {
+        match self {
+            LimitExceeded::Wait(kind, _) | LimitExceeded::TooManyTokens(kind) => *kind,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &Limit, now: u64) {
+        let elapsed = now.saturating_sub(self.updated);
+        self.tokens =
+            (self.tokens + elapsed as f64 * limit.refill_per_ms()).min(limit.requests as f64);
+        self.updated = now;
+    }
+}
+
+/// Token bucket rate limiter with one bucket per limited value, e.g., per user.
+pub(crate) struct RateLimiter {
+    limits: HashMap<LimitKind, Limit>,
+    /// The number of proxies in front of the key server that append the address of their peer to `X-Forwarded-For`.
+    trusted_proxy_hops: usize,
+    buckets: Mutex<LruCache<(LimitKind, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: HashMap<LimitKind, Limit>, trusted_proxy_hops: usize) -> Self {
+        Self {
+            limits,
+            trusted_proxy_hops,
+            buckets: Mutex::new(LruCache::new(
+                NonZero::new(RATE_LIMITER_SIZE).expect("fixed value"),
+            )),
+        }
+    }
+
+    /// Read the limits from the environment variables `RATE_LIMIT_IP`, `RATE_LIMIT_USER`, `RATE_LIMIT_SESSION_KEY`
+    /// and `RATE_LIMIT_NAMESPACE`. Properties without a limit set are not limited.
+    /// The number of trusted proxies is read from `TRUSTED_PROXY_HOPS` and defaults to 0.
+    pub fn from_env() -> Self {
+        let limits = [
+            LimitKind::Ip,
+            LimitKind::User,
+            LimitKind::SessionKey,
+            LimitKind::Namespace,
+        ]
+        .into_iter()
+        .filter_map(|kind| {
+            env::var(kind.env_var()).ok().map(|limit| {
+                (
+                    kind,
+                    limit
+                        .parse()
+                        .unwrap_or_else(|e| panic!("{}: {}", kind.env_var(), e)),
+                )
+            })
+        })
+        .collect();
+        let trusted_proxy_hops = env::var("TRUSTED_PROXY_HOPS")
+            .map(|hops| hops.parse().expect("Invalid TRUSTED_PROXY_HOPS"))
+            .unwrap_or(0);
+        Self::new(limits, trusted_proxy_hops)
+    }
+
+    /// The IP of the client of a request, given the `X-Forwarded-For` header and the address of the peer.
+    ///
+    /// Each trusted proxy appends the address of its peer to `X-Forwarded-For`, so the client is the entry
+    /// `trusted_proxy_hops` from the right. Entries further left are set by the client and cannot be trusted. Without
+    /// trusted proxies, or if the header has fewer entries than expected, the address of the peer is used.
+    pub fn client_ip(&self, forwarded_for: Option<&str>, peer: Option<IpAddr>) -> Option<String> {
+        if self.trusted_proxy_hops > 0 {
+            let entries = forwarded_for
+                .map(|v| v.split(',').map(str::trim).collect::<Vec<_>>())
+                .unwrap_or_default();
+            if let Some(ip) = entries
+                .len()
+                .checked_sub(self.trusted_proxy_hops)
+                .map(|i| entries[i])
+            {
+                return Some(ip.to_string());
+            }
+        }
+        peer.map(|ip| ip.to_string())
+    }
+
+    /// Take the given number of tokens from the bucket of each of the given values, e.g., one per policy evaluation of
+    /// a request. Tokens are only taken if all buckets have enough, so a rejected request does not count against any
+    /// limit. Otherwise, the kind of the first bucket without enough tokens and the number of seconds until they are
+    /// available are returned as the error. Requests for more tokens than a bucket can hold are rejected right away.
+    pub fn check(&self, limited: &[(LimitKind, String)], tokens: u32) -> Result<(), LimitExceeded> {
+        self.check_at(limited, tokens, current_epoch_time())
+    }
+
+    fn check_at(
+        &self,
+        limited: &[(LimitKind, String)],
+        tokens: u32,
+        now: u64,
+    ) -> Result<(), LimitExceeded> {
+        let limited = limited
+            .iter()
+            .filter_map(|(kind, value)| self.limits.get(kind).map(|limit| (kind, value, limit)))
+            .collect::<Vec<_>>();
+        if let Some((kind, _, _)) = limited.iter().find(|(_, _, limit)| tokens > limit.requests) {
+            return Err(LimitExceeded::TooManyTokens(**kind));
+        }
+
+        let tokens = tokens as f64;
+        let mut buckets = self.buckets.lock();
+        for (kind, value, limit) in &limited {
+            let bucket = buckets.get_or_insert_mut((**kind, value.to_string()), || Bucket {
+                tokens: limit.requests as f64,
+                updated: now,
+            });
+            bucket.refill(limit, now);
+            if bucket.tokens < tokens {
+                let wait_ms = (tokens - bucket.tokens) / limit.refill_per_ms();
+                return Err(LimitExceeded::Wait(
+                    **kind,
+                    (wait_ms / 1000.0).ceil() as u64,
+                ));
+            }
+        }
+        for (kind, value, _) in limited {
+            if let Some(bucket) = buckets.get_mut(&(*kind, value.to_string())) {
+                bucket.tokens -= tokens;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!("100/60".parse(), Ok(Limit::new(100, 60_000)));
+        assert!("100".parse::<Limit>().is_err());
+        assert!("0/60".parse::<Limit>().is_err());
+        assert!("10/0".parse::<Limit>().is_err());
+        assert!("a/60".parse::<Limit>().is_err());
+    }
+
+    fn user(value: &str) -> Vec<(LimitKind, String)> {
+        vec![(LimitKind::User, value.to_string())]
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter =
+            RateLimiter::new(HashMap::from([(LimitKind::User, Limit::new(2, 10_000))]), 0);
+
+        // Burst of two requests is allowed
+        assert!(limiter.check_at(&user("alice"), 1, 0).is_ok());
+        assert!(limiter.check_at(&user("alice"), 1, 0).is_ok());
+        assert!(limiter.check_at(&user("alice"), 1, 0).is_err());
+
+        // Other users and unlimited kinds are not affected
+        assert!(limiter.check_at(&user("bob"), 1, 0).is_ok());
+        for _ in 0..10 {
+            assert!(limiter
+                .check_at(&[(LimitKind::Ip, "127.0.0.1".to_string())], 1, 0)
+                .is_ok());
+        }
+
+        // One token is refilled every 5 seconds
+        assert_eq!(
+            limiter.check_at(&user("alice"), 1, 2_500),
+            Err(LimitExceeded::Wait(LimitKind::User, 3))
+        );
+        assert!(limiter.check_at(&user("alice"), 1, 6_000).is_ok());
+        assert!(limiter.check_at(&user("alice"), 1, 6_000).is_err());
+
+        // The bucket never holds more than the burst size
+        assert!(limiter.check_at(&user("alice"), 1, 100_000).is_ok());
+        assert!(limiter.check_at(&user("alice"), 1, 100_000).is_ok());
+        assert!(limiter.check_at(&user("alice"), 1, 100_000).is_err());
+    }
+
+    #[test]
+    fn test_rejected_requests_take_no_tokens() {
+        let limiter = RateLimiter::new(
+            HashMap::from([
+                (LimitKind::User, Limit::new(1, 10_000)),
+                (LimitKind::SessionKey, Limit::new(2, 10_000)),
+            ]),
+            0,
+        );
+        let limited = |user: &str| {
+            vec![
+                (LimitKind::SessionKey, "key".to_string()),
+                (LimitKind::User, user.to_string()),
+            ]
+        };
+        assert!(limiter.check_at(&limited("alice"), 1, 0).is_ok());
+
+        // The user bucket is empty, so the session key bucket is not charged
+        for _ in 0..5 {
+            assert_eq!(
+                limiter.check_at(&limited("alice"), 1, 0),
+                Err(LimitExceeded::Wait(LimitKind::User, 10))
+            );
+        }
+        assert!(limiter.check_at(&limited("bob"), 1, 0).is_ok());
+        assert_eq!(
+            limiter.check_at(&limited("carol"), 1, 0),
+            Err(LimitExceeded::Wait(LimitKind::SessionKey, 5))
+        );
+    }
+
+    #[test]
+    fn test_charge_multiple_tokens() {
+        let limiter =
+            RateLimiter::new(HashMap::from([(LimitKind::User, Limit::new(5, 10_000))]), 0);
+        assert!(limiter.check_at(&user("alice"), 3, 0).is_ok());
+        assert_eq!(
+            limiter.check_at(&user("alice"), 3, 0),
+            Err(LimitExceeded::Wait(LimitKind::User, 2))
+        );
+        assert!(limiter.check_at(&user("alice"), 2, 0).is_ok());
+        assert!(limiter.check_at(&user("alice"), 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_more_tokens_than_limit() {
+        let limiter =
+            RateLimiter::new(HashMap::from([(LimitKind::User, Limit::new(5, 10_000))]), 0);
+
+        // The bucket can never hold six tokens, so waiting does not help
+        assert_eq!(
+            limiter.check_at(&user("alice"), 6, 0),
+            Err(LimitExceeded::TooManyTokens(LimitKind::User))
+        );
+        assert_eq!(
+            limiter.check_at(&user("alice"), 6, 1_000_000),
+            Err(LimitExceeded::TooManyTokens(LimitKind::User))
+        );
+
+        // No tokens were taken, and unlimited kinds accept any number of tokens
+        assert!(limiter.check_at(&user("alice"), 5, 1_000_000).is_ok());
+        assert!(limiter
+            .check_at(&[(LimitKind::Ip, "127.0.0.1".to_string())], 100, 0)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let peer = Some(IpAddr::from([10, 0, 0, 2]));
+
+        // Without trusted proxies, the header is ignored
+        let limiter = RateLimiter::new(HashMap::new(), 0);
+        assert_eq!(
+            limiter.client_ip(Some("1.1.1.1"), peer),
+            Some("10.0.0.2".to_string())
+        );
+        assert_eq!(limiter.client_ip(Some("1.1.1.1"), None), None);
+
+        // With two proxies, the client is the second entry from the right
+        let limiter = RateLimiter::new(HashMap::from([(LimitKind::Ip, Limit::new(1, 10_000))]), 2);
+        assert_eq!(
+            limiter.client_ip(Some("1.1.1.1, 10.0.0.1"), peer),
+            Some("1.1.1.1".to_string())
+        );
+        assert_eq!(
+            limiter.client_ip(Some("10.0.0.1"), peer),
+            Some("10.0.0.2".to_string())
+        );
+
+        // Spoofed entries on the left do not give the client a fresh bucket
+        let ip = limiter.client_ip(Some("1.1.1.1, 10.0.0.1"), peer).unwrap();
+        assert!(limiter.check_at(&[(LimitKind::Ip, ip)], 1, 0).is_ok());
+        for spoofed in [
+            "2.2.2.2, 1.1.1.1, 10.0.0.1",
+            "3.3.3.3,4.4.4.4, 1.1.1.1, 10.0.0.1",
+        ] {
+            let ip = limiter.client_ip(Some(spoofed), peer).unwrap();
+            assert_eq!(ip, "1.1.1.1");
+            assert!(limiter.check_at(&[(LimitKind::Ip, ip)], 1, 0).is_err());
+        }
+    }
+}
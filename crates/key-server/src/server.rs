@@ -1,44 +1,57 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
+use crate::admin::AdminState;
+use crate::audit::AuditLog;
+use crate::errors::{set_retry_after, with_retry_after, ErrorResponse};
 use crate::errors::InternalError::{DeprecatedSDKVersion, InvalidSDKVersion};
 use crate::externals::{current_epoch_time, duration_since, get_reference_gas_price};
+use crate::extraction::KeyExtractor;
 use crate::metrics::{call_with_duration, observation_callback, status_callback, Metrics};
-use crate::signed_message::{signed_message, signed_request};
+use crate::namespace_policy::{Namespace, NamespacePolicyStore};
+use crate::rate_limit::{request_limits, LimitExceeded, LimitKind, RateLimiter};
+use crate::signed_message::{signed_message, signed_request, signed_response};
+use crate::time_lock::TimeLockFeed;
+use crate::tls::{TlsConfig, DEFAULT_PORT};
 use crate::types::MasterKeyPOP;
 use anyhow::Result;
-use axum::extract::Request;
-use axum::http::{HeaderMap, HeaderValue};
+use axum::body::{to_bytes, Body};
+use axum::extract::{ConnectInfo, Request};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::middleware::{from_fn_with_state, map_response, Next};
-use axum::response::Response;
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{extract::State, Json};
 use core::time::Duration;
-use crypto::elgamal::encrypt;
+use crypto::audit::{KeyRelease, PolicyEvidence};
 use crypto::ibe;
 use crypto::ibe::create_proof_of_possession;
 use errors::InternalError;
 use externals::get_latest_checkpoint_timestamp;
-use fastcrypto::ed25519::{Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature};
 use fastcrypto::encoding::{Base64, Encoding};
 use fastcrypto::serde_helpers::ToFromByteArray;
-use fastcrypto::traits::VerifyingKey;
+use fastcrypto::traits::{KeyPair, Signer, ToFromBytes, VerifyingKey};
+use futures::stream::{self, StreamExt};
 use jsonrpsee::core::ClientError;
 use jsonrpsee::types::error::{INVALID_PARAMS_CODE, METHOD_NOT_FOUND_CODE};
 use mysten_service::get_mysten_service;
 use mysten_service::metrics::start_basic_prometheus_server;
 use mysten_service::package_name;
 use mysten_service::package_version;
-use mysten_service::serve;
 use rand::thread_rng;
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::env;
 use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 use sui_sdk::error::{Error, SuiRpcResult};
-use sui_sdk::rpc_types::SuiTransactionBlockEffectsAPI;
+use sui_sdk::rpc_types::{
+    DryRunTransactionBlockResponse, SuiExecutionStatus, SuiTransactionBlockEffectsAPI,
+};
 use sui_sdk::types::base_types::{ObjectID, SuiAddress};
 use sui_sdk::types::signature::GenericSignature;
 use sui_sdk::types::transaction::{ProgrammableTransaction, TransactionKind};
@@ -48,20 +61,33 @@ use tap::tap::TapFallible;
 use tokio::sync::watch::{channel, Receiver};
 use tower_http::cors::{Any, CorsLayer};
 use tracing::{debug, info, warn};
-use types::{ElGamalPublicKey, ElgamalEncryption, ElgamalVerificationKey, IbeMasterKey, Network};
+use types::{
+    AccessDecision, CheckAccessResponse, ElGamalPublicKey, ElgamalEncryption,
+    ElgamalVerificationKey, IbeMasterKey, Network,
+};
+use utoipa::ToSchema;
 use valid_ptb::ValidPtb;
 
+mod admin;
+mod audit;
 mod cache;
 mod errors;
 mod externals;
+mod extraction;
+mod grpc;
 mod signed_message;
 mod types;
 mod valid_ptb;
 mod ethereum;
 
 mod metrics;
+mod namespace_policy;
+mod openapi;
+mod rate_limit;
 #[cfg(test)]
 pub mod tests;
+mod time_lock;
+mod tls;
 
 /// The allowed staleness of the full node.
 /// When setting this duration, note a timestamp on Sui may be a bit late compared to
@@ -74,6 +100,21 @@ const CHECKPOINT_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
 /// The interval at which the reference gas price is updated.
 const RGP_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
 
+/// The interval at which a signed checkpoint is appended to the audit log.
+const AUDIT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The maximum number of requests in a batch.
+const MAX_BATCH_SIZE: usize = 32;
+
+/// The maximum number of requests in a batch that are evaluated concurrently.
+const MAX_BATCH_CONCURRENCY: usize = 8;
+
+/// The maximum size of a request body which is buffered to sign the response.
+const MAX_REQUEST_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// The maximum size of a response body which is buffered to sign it, e.g., with the traces of a check access request.
+const MAX_RESPONSE_BODY_SIZE: usize = 16 * 1024 * 1024;
+
 /// The maximum time to live for a session key.
 const SESSION_KEY_TTL_MAX: u16 = 30;
 
@@ -82,29 +123,44 @@ const GAS_BUDGET: u64 = 500_000_000;
 
 const PACKAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// The header with the id of a request, used for logging.
+const REQUEST_ID_HEADER: &str = "Request-Id";
+
 /// The minimum version of the SDK that is required to use this service.
 const SDK_VERSION_REQUIREMENT: &str = ">=0.3.5";
 
 // The "session" certificate, signed by the user
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
 struct Certificate {
+    #[schema(value_type = String)]
     pub user: SuiAddress,
+    /// Base64 encoding of the Ed25519 public key of the session.
+    #[schema(value_type = String, format = Byte)]
     pub session_vk: Ed25519PublicKey,
     pub creation_time: u64,
     pub ttl_min: u16,
+    /// Base64 encoding of the user's signature on the personal message, see `signed_message`.
+    #[schema(value_type = String, format = Byte)]
     pub signature: GenericSignature,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 struct FetchKeyRequest {
     // Next fields must be signed to prevent others from sending requests on behalf of the user and
     // being able to fetch the key
+    /// Base64 encoding of the BCS encoded PTB calling `seal_approve*` functions.
     ptb: String, // must adhere specific structure, see ValidPtb
     // We don't want to rely on https only for restricting the response to this user, since in the
     // case of multiple services, one service can do a replay attack to get the key from other
     // services.
+    /// Base64 encoding of the ElGamal public key (a G1 element) the keys are encrypted with.
+    #[schema(value_type = String, format = Byte)]
     enc_key: ElGamalPublicKey,
+    /// Base64 encoding of the ElGamal verification key (a G2 element).
+    #[schema(value_type = String, format = Byte)]
     enc_verification_key: ElgamalVerificationKey,
+    /// Base64 encoding of the session key's signature on the request, see `signed_request`.
+    #[schema(value_type = String, format = Byte)]
     request_signature: Ed25519Signature,
 
     certificate: Certificate,
@@ -114,25 +170,32 @@ type KeyId = Vec<u8>;
 
 type Timestamp = u64;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 struct DecryptionKey {
     id: KeyId,
+    /// Base64 encodings of the two G1 elements of the ElGamal encryption of the key.
+    #[schema(value_type = Vec<String>)]
     encrypted_key: ElgamalEncryption,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 struct FetchKeyResponse {
     decryption_keys: Vec<DecryptionKey>,
+    /// The evidence the policy was evaluated against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    evidence: Option<PolicyEvidence>,
 }
 
 #[derive(Clone)]
 struct Server {
     sui_client: SuiClient,
     network: Network,
-    master_key: IbeMasterKey,
     key_server_object_id: ObjectID,
     key_server_object_id_sig: MasterKeyPOP,
     sdk_version_requirement: VersionReq,
+    namespace_policy: Arc<NamespacePolicyStore>,
+    /// Extracts keys with the master key.
+    extractor: Arc<KeyExtractor>,
 }
 
 impl Server {
@@ -162,10 +225,14 @@ impl Server {
         Server {
             sui_client,
             network,
-            master_key,
             key_server_object_id,
             key_server_object_id_sig,
             sdk_version_requirement,
+            namespace_policy: Arc::new(NamespacePolicyStore::default()),
+            extractor: Arc::new(KeyExtractor::new(
+                master_key,
+                KeyExtractor::threads_from_env(),
+            )),
         }
     }
 
@@ -239,12 +306,68 @@ impl Server {
             req_id
         );
         // Evaluate the `seal_approve*` function
+        let dry_run_res = self
+            .dry_run(sender, vptb.ptb().clone(), gas_price, req_id)
+            .await?;
+        if dry_run_res.effects.status().is_err() {
+            debug!("Dry run execution asserted (req_id: {:?})", req_id);
+            // TODO: Should we return a different error per status, e.g., InsufficientGas?
+            return Err(InternalError::NoAccess);
+        }
+
+        // all good!
+        Ok(())
+    }
+
+    /// Evaluate the `seal_approve*` call for each id separately, so the outcome is known for each id.
+    async fn explain_policy(
+        &self,
+        sender: SuiAddress,
+        vptb: &ValidPtb,
+        first_pkg_id: &ObjectID,
+        gas_price: u64,
+        trace: bool,
+        req_id: Option<&str>,
+    ) -> Result<Vec<AccessDecision>, InternalError> {
+        let mut decisions = vec![];
+        for (command, id) in vptb.ptb().commands.iter().zip(vptb.full_ids(first_pkg_id)) {
+            let ptb = ProgrammableTransaction {
+                inputs: vptb.ptb().inputs.clone(),
+                commands: vec![command.clone()],
+            };
+            let dry_run_res = self.dry_run(sender, ptb, gas_price, req_id).await?;
+            let reason = match dry_run_res.effects.status() {
+                SuiExecutionStatus::Success => None,
+                SuiExecutionStatus::Failure { error } => Some(error.clone()),
+            };
+            decisions.push(AccessDecision {
+                id,
+                approved: reason.is_none(),
+                abort_code: reason.as_deref().and_then(parse_abort_code),
+                reason,
+                revert_data: None,
+                gas_used: Some(dry_run_res.effects.gas_cost_summary().gas_used()),
+                trace: trace
+                    .then(|| serde_json::to_value(&dry_run_res).ok())
+                    .flatten(),
+            });
+        }
+        Ok(decisions)
+    }
+
+    async fn dry_run(
+        &self,
+        sender: SuiAddress,
+        ptb: ProgrammableTransaction,
+        gas_price: u64,
+        req_id: Option<&str>,
+    ) -> Result<DryRunTransactionBlockResponse, InternalError> {
         let tx_data = self
             .sui_client
             .transaction_builder()
             .tx_data_for_dry_run(
                 sender,
-                TransactionKind::ProgrammableTransaction(vptb.ptb().clone()),
+                TransactionKind::ProgrammableTransaction(ptb),
                 GAS_BUDGET,
                 gas_price,
                 None,
@@ -277,16 +400,12 @@ impl Server {
                 InternalError::Failure
             })?;
         debug!("Dry run response: {:?} (req_id: {:?})", dry_run_res, req_id);
-        if dry_run_res.effects.status().is_err() {
-            debug!("Dry run execution asserted (req_id: {:?})", req_id);
-            // TODO: Should we return a different error per status, e.g., InsufficientGas?
-            return Err(InternalError::NoAccess);
-        }
-
-        // all good!
-        Ok(())
+        Ok(dry_run_res)
     }
 
+    /// Check a request and evaluate its policy. The handlers run the two steps separately, so requests can be rate
+    /// limited in between, see [fetch_key].
+    #[cfg(test)]
     #[allow(clippy::too_many_arguments)]
     async fn check_request(
         &self,
@@ -299,6 +418,130 @@ impl Server {
         metrics: Option<&Metrics>,
         req_id: Option<&str>,
     ) -> Result<Vec<KeyId>, InternalError> {
+        let (valid_ptb, first_pkg_id) = self
+            .validate_request(
+                ptb_str,
+                enc_key,
+                enc_verification_key,
+                request_signature,
+                certificate,
+                metrics,
+                req_id,
+            )
+            .await?;
+        self.approve_request(
+            certificate,
+            &valid_ptb,
+            &first_pkg_id,
+            gas_price,
+            metrics,
+            req_id,
+        )
+        .await
+    }
+
+    /// Evaluate the policy of a request that passed [Server::validate_request]. Returns the full ids of the request.
+    async fn approve_request(
+        &self,
+        certificate: &Certificate,
+        valid_ptb: &ValidPtb,
+        first_pkg_id: &ObjectID,
+        gas_price: u64,
+        metrics: Option<&Metrics>,
+        req_id: Option<&str>,
+    ) -> Result<Vec<KeyId>, InternalError> {
+        call_with_duration(metrics.map(|m| &m.check_policy_duration), || async {
+            self.check_policy(certificate.user, valid_ptb, gas_price, req_id)
+                .await
+        })
+        .await?;
+
+        info!(
+            "Valid request: {}",
+            json!({ "user": certificate.user, "package_id": valid_ptb.pkg_id(), "req_id": req_id })
+        );
+
+        // return the full id with the first package id as prefix
+        Ok(valid_ptb.full_ids(first_pkg_id))
+    }
+
+    /// Like [Server::check_request], but evaluates the policy for each id separately and returns the outcomes
+    /// instead of failing if access is denied.
+    #[cfg(test)]
+    #[allow(clippy::too_many_arguments)]
+    async fn check_access(
+        &self,
+        ptb_str: &str,
+        enc_key: &ElGamalPublicKey,
+        enc_verification_key: &ElgamalVerificationKey,
+        request_signature: &Ed25519Signature,
+        certificate: &Certificate,
+        gas_price: u64,
+        trace: bool,
+        metrics: Option<&Metrics>,
+        req_id: Option<&str>,
+    ) -> Result<Vec<AccessDecision>, InternalError> {
+        let (valid_ptb, first_pkg_id) = self
+            .validate_request(
+                ptb_str,
+                enc_key,
+                enc_verification_key,
+                request_signature,
+                certificate,
+                metrics,
+                req_id,
+            )
+            .await?;
+        self.explain_request(
+            certificate,
+            &valid_ptb,
+            &first_pkg_id,
+            gas_price,
+            trace,
+            metrics,
+            req_id,
+        )
+        .await
+    }
+
+    /// Like [Server::approve_request], but returns the outcome for each id.
+    #[allow(clippy::too_many_arguments)]
+    async fn explain_request(
+        &self,
+        certificate: &Certificate,
+        valid_ptb: &ValidPtb,
+        first_pkg_id: &ObjectID,
+        gas_price: u64,
+        trace: bool,
+        metrics: Option<&Metrics>,
+        req_id: Option<&str>,
+    ) -> Result<Vec<AccessDecision>, InternalError> {
+        call_with_duration(metrics.map(|m| &m.check_policy_duration), || async {
+            self.explain_policy(
+                certificate.user,
+                valid_ptb,
+                first_pkg_id,
+                gas_price,
+                trace,
+                req_id,
+            )
+            .await
+        })
+        .await
+    }
+
+    /// Check everything in a request except the policy. Returns the parsed PTB and the first version of the package.
+    #[allow(clippy::too_many_arguments)]
+    async fn validate_request(
+        &self,
+        ptb_str: &str,
+        enc_key: &ElGamalPublicKey,
+        enc_verification_key: &ElgamalVerificationKey,
+        request_signature: &Ed25519Signature,
+        certificate: &Certificate,
+        metrics: Option<&Metrics>,
+        req_id: Option<&str>,
+    ) -> Result<(ValidPtb, ObjectID), InternalError> {
         debug!(
             "Checking request for ptb_str: {:?}, cert {:?} (req_id: {:?})",
             ptb_str, certificate, req_id
@@ -307,7 +550,7 @@ impl Server {
             .map_err(|_| InternalError::InvalidPTB("Invalid Base64".to_string()))?;
         let ptb: ProgrammableTransaction = bcs::from_bytes(&ptb_b64)
             .map_err(|_| InternalError::InvalidPTB("Invalid BCS".to_string()))?;
-        let valid_ptb = ValidPtb::try_from(ptb.clone())?;
+        let valid_ptb = ValidPtb::try_from(ptb)?;
 
         // Report the number of id's in the request to the metrics.
         if let Some(m) = metrics {
@@ -332,10 +575,14 @@ impl Server {
             return Err(InternalError::OldPackageVersion);
         }
 
+        self.namespace_policy.check(&Namespace::Sui {
+            package_id: first_pkg_id,
+        })?;
+
         // Check all conditions
         self.check_signature(
             &first_pkg_id,
-            &ptb,
+            valid_ptb.ptb(),
             enc_key,
             enc_verification_key,
             request_signature,
@@ -344,37 +591,34 @@ impl Server {
         )
         .await?;
 
-        call_with_duration(metrics.map(|m| &m.check_policy_duration), || async {
-            self.check_policy(certificate.user, &valid_ptb, gas_price, req_id)
-                .await
-        })
-        .await?;
-
-        info!(
-            "Valid request: {}",
-            json!({ "user": certificate.user, "package_id": valid_ptb.pkg_id(), "req_id": req_id })
-        );
-
-        // return the full id with the first package id as prefix
-        Ok(valid_ptb.full_ids(&first_pkg_id))
+        Ok((valid_ptb, first_pkg_id))
     }
 
-    fn create_response(&self, ids: &[KeyId], enc_key: &ElGamalPublicKey) -> FetchKeyResponse {
+    /// Extract the requested keys and encrypt them under the user's ElGamal key. The keys are extracted on the
+    /// extraction pool, see [KeyExtractor].
+    async fn create_response(
+        &self,
+        ids: &[KeyId],
+        enc_key: &ElGamalPublicKey,
+        metrics: Option<&Metrics>,
+    ) -> Result<FetchKeyResponse, InternalError> {
         debug!("Checking response for ids: {:?}", ids);
-        let decryption_keys = ids
-            .iter()
-            .map(|id| {
-                // Requested key
-                let key = ibe::extract(&self.master_key, id);
-                // ElGamal encryption of key under the user's public key
-                let encrypted_key = encrypt(&mut thread_rng(), &key, enc_key);
-                DecryptionKey {
-                    id: id.to_owned(),
-                    encrypted_key,
-                }
-            })
+        let start = Instant::now();
+        let decryption_keys = self
+            .extractor
+            .extract_and_encrypt(ids.to_vec(), enc_key.clone())
+            .await?
+            .into_iter()
+            .map(|(id, encrypted_key)| DecryptionKey { id, encrypted_key })
             .collect();
-        FetchKeyResponse { decryption_keys }
+        if let Some(m) = metrics {
+            m.key_extraction_duration
+                .observe(start.elapsed().as_millis() as f64);
+        }
+        Ok(FetchKeyResponse {
+            decryption_keys,
+            evidence: None,
+        })
     }
 
     /// Helper function to spawn a thread that periodically fetches a value and sends it to a [Receiver].
@@ -486,42 +730,321 @@ impl Server {
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/fetch_key",
+    request_body = FetchKeyRequest,
+    params(openapi::RequestHeaders),
+    responses(
+        (status = 200, description = "The encrypted keys", body = FetchKeyResponse),
+        (status = "4XX", description = "The request was rejected", body = ErrorResponse),
+        (status = "5XX", description = "The key server failed to handle the request", body = ErrorResponse),
+    )
+)]
 async fn handle_fetch_key(
     State(app_state): State<MyState>,
     headers: HeaderMap,
     Json(payload): Json<FetchKeyRequest>,
 ) -> Result<Json<FetchKeyResponse>, InternalError> {
     let req_id = headers
-        .get("Request-Id")
+        .get(REQUEST_ID_HEADER)
         .map(|v| v.to_str().unwrap_or_default());
 
     app_state.metrics.requests.inc();
     app_state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
 
-    app_state
+    fetch_key(&app_state, payload, req_id).await.map(Json)
+}
+
+/// Check a fetch key request and return the encrypted keys.
+/// Used by both the `/v1/fetch_key` and the `/v1/fetch_keys_batch` endpoints.
+async fn fetch_key(
+    app_state: &MyState,
+    payload: FetchKeyRequest,
+    req_id: Option<&str>,
+) -> Result<FetchKeyResponse, InternalError> {
+    let (valid_ptb, first_pkg_id) = app_state
         .server
-        .check_request(
+        .validate_request(
             &payload.ptb,
             &payload.enc_key,
             &payload.enc_verification_key,
             &payload.request_signature,
             &payload.certificate,
+            Some(&app_state.metrics),
+            req_id,
+        )
+        .await
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?;
+
+    // Only charge the buckets of the user and session key once the certificate is verified, right before the policy is
+    // evaluated.
+    app_state
+        .check_rate_limits(
+            &request_limits(
+                payload.certificate.user.to_string(),
+                payload.certificate.session_vk.to_string(),
+                Some(valid_ptb.pkg_id().to_string()),
+            ),
+            1,
+        )
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?;
+
+    let full_ids = app_state
+        .server
+        .approve_request(
+            &payload.certificate,
+            &valid_ptb,
+            &first_pkg_id,
             app_state.reference_gas_price(),
             Some(&app_state.metrics),
             req_id,
         )
         .await
-        .map(|full_id| Json(app_state.server.create_response(&full_id, &payload.enc_key)))
-        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?;
+
+    let evidence = PolicyEvidence::SuiCheckpoint {
+        timestamp_ms: *app_state.latest_checkpoint_timestamp_receiver.borrow(),
+    };
+    let mut response = app_state
+        .server
+        .create_response(&full_ids, &payload.enc_key, Some(&app_state.metrics))
+        .await
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?;
+    response.evidence = Some(evidence.clone());
+    app_state
+        .record_release(KeyRelease {
+            timestamp_ms: current_epoch_time(),
+            chain: "sui".to_string(),
+            namespace: valid_ptb.pkg_id().to_string(),
+            ids: full_ids.iter().map(hex::encode).collect(),
+            user: payload.certificate.user.to_string(),
+            session_key: payload.certificate.session_vk.to_string(),
+            request_id: req_id.map(String::from),
+            evidence,
+        })
+        .await
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?;
+    Ok(response)
 }
 
-#[derive(Serialize, Deserialize)]
+/// A single request in a batch.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(tag = "chain", rename_all = "snake_case")]
+enum BatchRequestEntry {
+    Sui(FetchKeyRequest),
+    Ethereum(ethereum::FetchKeyRequest),
+}
+
+/// The result of a single request in a batch.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum BatchResponseEntry {
+    Sui(FetchKeyResponse),
+    Ethereum(ethereum::FetchKeyResponse),
+    Error {
+        status: u16,
+        #[serde(flatten)]
+        error: ErrorResponse,
+    },
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+struct FetchKeysBatchRequest {
+    requests: Vec<BatchRequestEntry>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct FetchKeysBatchResponse {
+    /// The results in the same order as the requests.
+    results: Vec<BatchResponseEntry>,
+}
+
+/// Evaluate multiple independent fetch key requests concurrently. A failing request does not fail the batch.
+#[utoipa::path(
+    post,
+    path = "/v1/fetch_keys_batch",
+    request_body = FetchKeysBatchRequest,
+    params(openapi::RequestHeaders),
+    responses(
+        (status = 200, description = "The results of the requests", body = FetchKeysBatchResponse),
+        (status = "4XX", description = "The request was rejected", body = ErrorResponse),
+        (status = "5XX", description = "The key server failed to handle the request", body = ErrorResponse),
+    )
+)]
+async fn handle_fetch_keys_batch(
+    State(app_state): State<MyState>,
+    headers: HeaderMap,
+    Json(payload): Json<FetchKeysBatchRequest>,
+) -> Result<Json<FetchKeysBatchResponse>, InternalError> {
+    let req_id = headers
+        .get(REQUEST_ID_HEADER)
+        .map(|v| v.to_str().unwrap_or_default());
+
+    app_state.metrics.batch_requests.inc();
+    if payload.requests.len() > MAX_BATCH_SIZE {
+        app_state
+            .metrics
+            .observe_error(InternalError::BatchTooLarge.as_str());
+        return Err(InternalError::BatchTooLarge);
+    }
+    app_state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
+
+    let results = stream::iter(payload.requests)
+        .map(|entry| {
+            app_state.metrics.requests.inc();
+            let app_state = &app_state;
+            async move {
+                let result = match entry {
+                    BatchRequestEntry::Sui(request) => fetch_key(app_state, request, req_id)
+                        .await
+                        .map(BatchResponseEntry::Sui),
+                    BatchRequestEntry::Ethereum(request) => {
+                        ethereum::handler::fetch_key(app_state, request, req_id)
+                            .await
+                            .map(BatchResponseEntry::Ethereum)
+                    }
+                };
+                result.unwrap_or_else(|e| {
+                    let (status, error) = e.into_parts();
+                    BatchResponseEntry::Error {
+                        status: status.as_u16(),
+                        error,
+                    }
+                })
+            }
+        })
+        .buffered(MAX_BATCH_CONCURRENCY)
+        .collect()
+        .await;
+    Ok(Json(FetchKeysBatchResponse { results }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+struct CheckAccessRequest {
+    #[serde(flatten)]
+    request: FetchKeyRequest,
+    /// Return the full dry run results. Ignored unless the key server runs in developer mode.
+    #[serde(default)]
+    trace: bool,
+}
+
+/// Evaluate the policy for a fetch key request without releasing any keys.
+#[utoipa::path(
+    post,
+    path = "/v1/check_access",
+    request_body = CheckAccessRequest,
+    params(openapi::RequestHeaders),
+    responses(
+        (status = 200, description = "The access decisions", body = CheckAccessResponse),
+        (status = "4XX", description = "The request was rejected", body = ErrorResponse),
+        (status = "5XX", description = "The key server failed to handle the request", body = ErrorResponse),
+    )
+)]
+async fn handle_check_access(
+    State(app_state): State<MyState>,
+    headers: HeaderMap,
+    Json(payload): Json<CheckAccessRequest>,
+) -> Result<Json<CheckAccessResponse>, InternalError> {
+    let req_id = headers
+        .get(REQUEST_ID_HEADER)
+        .map(|v| v.to_str().unwrap_or_default());
+
+    app_state.metrics.check_access_requests.inc();
+    app_state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
+
+    check_access(&app_state, payload, req_id).await.map(Json)
+}
+
+/// Evaluate the policy of a check access request.
+/// Used by both the `/v1/check_access` endpoint and the gRPC service.
+async fn check_access(
+    app_state: &MyState,
+    payload: CheckAccessRequest,
+    req_id: Option<&str>,
+) -> Result<CheckAccessResponse, InternalError> {
+    let CheckAccessRequest { request, trace } = payload;
+    let evidence = PolicyEvidence::SuiCheckpoint {
+        timestamp_ms: *app_state.latest_checkpoint_timestamp_receiver.borrow(),
+    };
+    let (valid_ptb, first_pkg_id) = app_state
+        .server
+        .validate_request(
+            &request.ptb,
+            &request.enc_key,
+            &request.enc_verification_key,
+            &request.request_signature,
+            &request.certificate,
+            Some(&app_state.metrics),
+            req_id,
+        )
+        .await
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?;
+
+    // Like for fetch key requests, only verified requests are charged. The policy is evaluated once per id, so a token
+    // is taken for each.
+    app_state
+        .check_rate_limits(
+            &request_limits(
+                request.certificate.user.to_string(),
+                request.certificate.session_vk.to_string(),
+                Some(valid_ptb.pkg_id().to_string()),
+            ),
+            valid_ptb.ptb().commands.len() as u32,
+        )
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?;
+
+    let decisions = app_state
+        .server
+        .explain_request(
+            &request.certificate,
+            &valid_ptb,
+            &first_pkg_id,
+            app_state.reference_gas_price(),
+            trace && app_state.developer_mode,
+            Some(&app_state.metrics),
+            req_id,
+        )
+        .await
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?;
+    Ok(CheckAccessResponse {
+        decisions,
+        evidence,
+    })
+}
+
+/// Get the abort code from the error of a failed Move execution, e.g.,
+/// `MoveAbort(MoveLocation { .. }, 1) in command 0`.
+fn parse_abort_code(error: &str) -> Option<u64> {
+    let abort = &error[error.find("MoveAbort(")?..];
+    let start = abort.rfind("}, ")? + 3;
+    let end = start + abort[start..].find(')')?;
+    abort[start..end].trim().parse().ok()
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 struct GetServiceResponse {
+    #[schema(value_type = String)]
     service_id: ObjectID,
+    /// Base64 encoding of the proof of possession of the master key.
+    #[schema(value_type = String, format = Byte)]
     pop: MasterKeyPOP,
     version: String,
+    /// The key used to sign all responses from this key server.
+    #[schema(value_type = String, format = Byte)]
+    signing_key: Ed25519PublicKey,
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/service",
+    params(openapi::RequestHeaders),
+    responses(
+        (status = 200, description = "The key server object and its proof of possession", body = GetServiceResponse),
+        (status = "4XX", description = "The request was rejected", body = ErrorResponse),
+        (status = "5XX", description = "The key server failed to handle the request", body = ErrorResponse),
+    )
+)]
 async fn handle_get_service(
     State(app_state): State<MyState>,
 ) -> Result<Json<GetServiceResponse>, InternalError> {
@@ -530,6 +1053,7 @@ async fn handle_get_service(
         service_id: app_state.server.key_server_object_id,
         pop: app_state.server.key_server_object_id_sig,
         version: PACKAGE_VERSION.to_string(),
+        signing_key: app_state.signing_key.public().clone(),
     }))
 }
 
@@ -539,6 +1063,13 @@ pub struct MyState {
     server: Arc<Server>,
     latest_checkpoint_timestamp_receiver: Receiver<Timestamp>,
     reference_gas_price: Receiver<u64>,
+    audit_log: Option<Arc<AuditLog>>,
+    signing_key: Arc<Ed25519KeyPair>,
+    rate_limiter: Arc<RateLimiter>,
+    /// If set, clients may request full simulation traces from /v1/check_access.
+    developer_mode: bool,
+    /// If set, keys of time-lock namespaces are released publicly once they are due.
+    time_lock: Option<Arc<TimeLockFeed>>,
 }
 
 impl MyState {
@@ -558,6 +1089,42 @@ impl MyState {
         *self.reference_gas_price.borrow()
     }
 
+    /// Record a key release in the audit log, if enabled.
+    async fn record_release(&self, release: KeyRelease) -> Result<(), InternalError> {
+        match &self.audit_log {
+            Some(audit_log) => audit_log.record_release(release).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Take the given number of tokens from the rate limit bucket of each of the given properties of a request, or none
+    /// if any of the buckets has too few. Requests needing more tokens than a bucket holds are rejected as too large,
+    /// since retrying them would never succeed.
+    fn check_rate_limits(
+        &self,
+        limited: &[(LimitKind, String)],
+        tokens: u32,
+    ) -> Result<(), InternalError> {
+        self.rate_limiter
+            .check(limited, tokens)
+            .map_err(|exceeded| {
+                let kind = exceeded.kind();
+                let value = limited.iter().find(|(k, _)| *k == kind).map(|(_, v)| v);
+                warn!("Rate limit exceeded for {} {:?}", kind.as_str(), value);
+                self.metrics
+                    .throttled_requests
+                    .with_label_values(&[kind.as_str()])
+                    .inc();
+                match exceeded {
+                    LimitExceeded::Wait(_, retry_after) => {
+                        set_retry_after(retry_after);
+                        InternalError::RateLimited
+                    }
+                    LimitExceeded::TooManyTokens(_) => InternalError::BatchTooLarge,
+                }
+            })
+    }
+
     fn validate_sdk_version(&self, version_string: &str) -> Result<(), InternalError> {
         let version = Version::parse(version_string).map_err(|_| InvalidSDKVersion)?;
         if !self.server.sdk_version_requirement.matches(&version) {
@@ -580,7 +1147,7 @@ async fn handle_request_headers(
         "Request id: {:?}, SDK version: {:?}, SDK type: {:?}, Target API version: {:?}",
         request
             .headers()
-            .get("Request-Id")
+            .get(REQUEST_ID_HEADER)
             .map(|v| v.to_str().unwrap_or_default()),
         version,
         request.headers().get("Client-Sdk-Type"),
@@ -598,6 +1165,95 @@ async fn handle_request_headers(
     Ok(next.run(request).await)
 }
 
+/// Middleware to sign all responses, including errors, with the server's signing key.
+/// The signature and the timestamp it was created at are returned in the `X-KeyServer-Signature` and
+/// `X-KeyServer-Timestamp` headers. See [signed_response] for the signed message.
+///
+/// Responses to requests with a body larger than [MAX_REQUEST_BODY_SIZE] are signed with an empty request body since
+/// the request is not buffered.
+async fn sign_response(state: State<MyState>, request: Request, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let Ok(request_body) = to_bytes(body, MAX_REQUEST_BODY_SIZE).await else {
+        return add_signature(&state, &[], StatusCode::PAYLOAD_TOO_LARGE.into_response()).await;
+    };
+    let response = next
+        .run(Request::from_parts(parts, Body::from(request_body.clone())))
+        .await;
+    add_signature(&state, &request_body, response).await
+}
+
+/// Sign a response to the given request body, see [sign_response]. If the response body is larger than
+/// [MAX_RESPONSE_BODY_SIZE], a signed [InternalError::Failure] is returned instead.
+async fn add_signature(state: &MyState, request_body: &[u8], response: Response) -> Response {
+    let (parts, body) = response.into_parts();
+    let (mut parts, response_body) = match to_bytes(body, MAX_RESPONSE_BODY_SIZE).await {
+        Ok(response_body) => (parts, response_body),
+        Err(_) => {
+            warn!("Failed to read response body");
+            let (parts, body) = InternalError::Failure.into_response().into_parts();
+            let response_body = to_bytes(body, MAX_RESPONSE_BODY_SIZE)
+                .await
+                .expect("error responses are small");
+            (parts, response_body)
+        }
+    };
+    let timestamp = current_epoch_time();
+    let signature = state.signing_key.sign(&signed_response(
+        &state.server.key_server_object_id,
+        request_body,
+        parts.status.as_u16(),
+        &response_body,
+        timestamp,
+    ));
+    parts.headers.insert(
+        "X-KeyServer-Signature",
+        HeaderValue::from_str(&Base64::encode(signature.as_bytes()))
+            .expect("Base64 is a valid header value"),
+    );
+    parts
+        .headers
+        .insert("X-KeyServer-Timestamp", HeaderValue::from(timestamp));
+    Response::from_parts(parts, Body::from(response_body))
+}
+
+/// Middleware to limit the number of requests per client IP. See [RateLimiter::client_ip] for how the IP is found.
+/// The request is handled within [with_retry_after], so rate limited responses of the handlers get a `Retry-After`
+/// header too.
+async fn limit_client_ip(state: State<MyState>, request: Request, next: Next) -> Response {
+    with_retry_after(async {
+        let forwarded_for = request
+            .headers()
+            .get("X-Forwarded-For")
+            .and_then(|v| v.to_str().ok());
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        if let Some(ip) = state.rate_limiter.client_ip(forwarded_for, peer) {
+            if let Err(e) = state.check_rate_limits(&[(LimitKind::Ip, ip)], 1) {
+                state.metrics.observe_error(e.as_str());
+                // Converted within the scope to keep the `Retry-After` header
+                return e.into_response();
+            }
+        }
+        next.run(request).await
+    })
+    .await
+}
+
+/// Serve the app over plain HTTP, e.g., behind a TLS terminating proxy. The address of the peer is passed on to the
+/// handlers for [limit_client_ip].
+async fn serve(app: axum::Router) -> Result<()> {
+    let listener =
+        tokio::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], DEFAULT_PORT))).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
+
 /// Middleware to add headers to all responses.
 async fn add_response_headers(mut response: Response) -> Response {
     response.headers_mut().insert(
@@ -626,7 +1282,7 @@ async fn main() -> Result<()> {
 
     info!("Starting server, version {}", PACKAGE_VERSION);
 
-    let s = Server::new(
+    let mut s = Server::new(
         IbeMasterKey::from_byte_array(
             &Base64::decode(&master_key)
                 .expect("MASTER_KEY should be base64 encoded")
@@ -638,6 +1294,15 @@ async fn main() -> Result<()> {
         ObjectID::from_hex_literal(&object_id).expect("Invalid KEY_SERVER_OBJECT_ID"),
     )
     .await;
+
+    // Operators may restrict the packages and contracts served. If no path is given, all are served.
+    if let Ok(path) = env::var("NAMESPACE_POLICY_PATH") {
+        s.namespace_policy = Arc::new(
+            NamespacePolicyStore::open(&PathBuf::from(path))
+                .expect("Failed to open namespace policy"),
+        );
+    }
+    s.namespace_policy.update_metrics(&metrics);
     let server = Arc::new(s);
 
     // Spawn tasks that update the state of the server.
@@ -648,12 +1313,72 @@ async fn main() -> Result<()> {
         .spawn_reference_gas_price_updater(RGP_UPDATE_INTERVAL, Some(&metrics))
         .await;
 
+    // The audit log is optional, but if enabled, all key releases must be written to it.
+    let audit_log = env::var("AUDIT_LOG_PATH").ok().map(|path| {
+        let signing_key = env::var("AUDIT_SIGNING_KEY")
+            .expect("AUDIT_SIGNING_KEY must be set if AUDIT_LOG_PATH is set");
+        let key_pair = Ed25519KeyPair::from(
+            Ed25519PrivateKey::from_bytes(
+                &Base64::decode(&signing_key).expect("AUDIT_SIGNING_KEY should be base64 encoded"),
+            )
+            .expect("Invalid AUDIT_SIGNING_KEY"),
+        );
+        let audit_log = Arc::new(
+            AuditLog::open(&PathBuf::from(path), key_pair).expect("Failed to open audit log"),
+        );
+        audit_log.spawn_checkpointer(AUDIT_CHECKPOINT_INTERVAL);
+        audit_log
+    });
+
+    let developer_mode = env::var("DEVELOPER_MODE").is_ok_and(|v| v == "true");
+
+    // Responses are signed with a dedicated key which is advertised in /v1/service. In developer mode, an ephemeral
+    // key may be used instead, so signatures are only valid for the lifetime of the process.
+    let signing_key = match env::var("SERVER_SIGNING_KEY") {
+        Ok(signing_key) => Ed25519KeyPair::from(
+            Ed25519PrivateKey::from_bytes(
+                &Base64::decode(&signing_key).expect("SERVER_SIGNING_KEY should be base64 encoded"),
+            )
+            .expect("Invalid SERVER_SIGNING_KEY"),
+        ),
+        Err(_) if developer_mode => {
+            warn!("SERVER_SIGNING_KEY is not set, using an ephemeral key to sign responses");
+            Ed25519KeyPair::generate(&mut thread_rng())
+        }
+        Err(_) => panic!("SERVER_SIGNING_KEY must be set outside of developer mode"),
+    };
+    info!("Signing responses with key {}", signing_key.public());
+
+    // The admin endpoints are only enabled if a token is set.
+    let admin_state = env::var("ADMIN_TOKEN").ok().map(|token| AdminState {
+        token: Arc::new(token),
+        namespace_policy: server.namespace_policy.clone(),
+        metrics: metrics.clone(),
+    });
+
     let state = MyState {
         metrics,
         server,
         latest_checkpoint_timestamp_receiver,
         reference_gas_price,
+        audit_log,
+        signing_key: Arc::new(signing_key),
+        rate_limiter: Arc::new(RateLimiter::from_env()),
+        developer_mode,
+        time_lock: TimeLockFeed::from_env().map(Arc::new),
     };
+    TimeLockFeed::spawn_releaser(state.clone());
+
+    let tls_config = TlsConfig::from_env();
+
+    // The gRPC API is only enabled if a port is set.
+    let grpc = env::var("GRPC_PORT").ok().map(|port| {
+        grpc::serve(
+            state.clone(),
+            port.parse().expect("Invalid GRPC_PORT"),
+            tls_config.clone(),
+        )
+    });
 
     let cors = CorsLayer::new()
         .allow_methods(Any)
@@ -667,11 +1392,59 @@ async fn main() -> Result<()> {
                 .route("/v1/fetch_key", post(handle_fetch_key))
                 .route("/v1/service", get(handle_get_service))
                 .route("/v1/fetch_key_ethereum", post(ethereum::handler::handle_fetch_key))
+                .route("/v1/fetch_keys_batch", post(handle_fetch_keys_batch))
+                .route("/v1/check_access", post(handle_check_access))
+                .route(
+                    "/v1/check_access_ethereum",
+                    post(ethereum::handler::handle_check_access),
+                )
                 .layer(from_fn_with_state(state.clone(), handle_request_headers))
+                .layer(from_fn_with_state(state.clone(), limit_client_ip))
+                .layer(map_response(add_response_headers))
+                .layer(from_fn_with_state(state.clone(), sign_response))
+                .with_state(state.clone()),
+        )
+        // Released keys and the API document are public, so requests don't need to identify the SDK.
+        .merge(
+            axum::Router::new()
+                .route(
+                    "/v1/released_keys/:namespace",
+                    get(time_lock::handle_released_keys),
+                )
+                .route("/v1/openapi.json", get(openapi::handle_openapi))
+                .layer(from_fn_with_state(state.clone(), limit_client_ip))
                 .layer(map_response(add_response_headers))
+                .layer(from_fn_with_state(state.clone(), sign_response))
                 .with_state(state),
         )
         .layer(cors);
+    let admin = admin_state.map(admin::router);
 
-    serve(app).await
+    // The admin endpoints are never served on the public port, but on the mutual TLS listener if there is one and
+    // otherwise on their own listener.
+    let (mtls_admin, admin) = match &tls_config {
+        Some(tls_config) if tls_config.has_mtls() => (admin, None),
+        _ => (None, admin),
+    };
+
+    // Serve over HTTPS if a certificate is configured, otherwise rely on a proxy in front of the server for TLS.
+    let http = async {
+        match tls_config {
+            Some(tls_config) => tls_config.serve(app, mtls_admin).await,
+            None => serve(app).await,
+        }
+    };
+    let admin = async {
+        match admin {
+            Some(admin) => admin::serve(admin).await,
+            None => Ok(()),
+        }
+    };
+    let grpc = async {
+        match grpc {
+            Some(grpc) => grpc.await,
+            None => Ok(()),
+        }
+    };
+    tokio::try_join!(http, admin, grpc).map(|_| ())
 }
@@ -3,6 +3,7 @@
 use crate::types::{ElGamalPublicKey, ElgamalVerificationKey};
 use chrono::{DateTime, Utc};
 use fastcrypto::ed25519::Ed25519PublicKey;
+use fastcrypto::hash::{HashFunction, Sha3_256};
 use serde::{Deserialize, Serialize};
 use sui_types::base_types::ObjectID;
 use sui_types::transaction::ProgrammableTransaction;
@@ -47,12 +48,47 @@ pub fn signed_request(
     bcs::to_bytes(&req).expect("should serialize")
 }
 
+/// The domain separation tag for response signatures.
+const DST_RESPONSE: &[u8] = b"SUI-SEAL-KEY-SERVER-RESPONSE-00";
+
+#[derive(Serialize, Deserialize)]
+struct ResponseFormat {
+    dst: Vec<u8>,
+    key_server_object_id: ObjectID,
+    request_hash: [u8; 32],
+    status: u16,
+    response_hash: [u8; 32],
+    timestamp: u64,
+}
+
+/// The message signed by a key server for every response, including errors.
+/// The signature binds the response body (e.g., the ids, the encrypted keys and the policy evidence, or the error)
+/// to the request body it was given and the time the response was created.
+pub fn signed_response(
+    key_server_object_id: &ObjectID,
+    request_body: &[u8],
+    status: u16,
+    response_body: &[u8],
+    timestamp: u64,
+) -> Vec<u8> {
+    let res = ResponseFormat {
+        dst: DST_RESPONSE.to_vec(),
+        key_server_object_id: *key_server_object_id,
+        request_hash: Sha3_256::digest(request_body).digest,
+        status,
+        response_hash: Sha3_256::digest(response_body).digest,
+        timestamp,
+    };
+    bcs::to_bytes(&res).expect("should serialize")
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::signed_message::{signed_message, signed_request};
+    use crate::signed_message::{signed_message, signed_request, signed_response};
     use crypto::elgamal::genkey;
-    use fastcrypto::ed25519::Ed25519KeyPair;
-    use fastcrypto::traits::KeyPair;
+    use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey, Ed25519Signature};
+    use fastcrypto::error::FastCryptoResult;
+    use fastcrypto::traits::{KeyPair, Signer, VerifyingKey};
     use rand::rngs::StdRng;
     use rand::SeedableRng;
     use std::str::FromStr;
@@ -61,6 +97,28 @@ mod tests {
     use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
     use sui_types::Identifier;
 
+    /// Verify the signature on a response from a key server with the given signing key.
+    fn verify_response_signature(
+        signing_key: &Ed25519PublicKey,
+        key_server_object_id: &ObjectID,
+        request_body: &[u8],
+        status: u16,
+        response_body: &[u8],
+        timestamp: u64,
+        signature: &Ed25519Signature,
+    ) -> FastCryptoResult<()> {
+        signing_key.verify(
+            &signed_response(
+                key_server_object_id,
+                request_body,
+                status,
+                response_body,
+                timestamp,
+            ),
+            signature,
+        )
+    }
+
     #[test]
     fn test_signed_message_regression() {
         let pkg_id =
@@ -98,4 +156,59 @@ mod tests {
         let result = signed_request(&ptb, &eg_keys.1, &eg_keys.2);
         assert_eq!(hex::encode(result), expected_output);
     }
+
+    #[test]
+    fn test_signed_response() {
+        let (_, kp): (_, Ed25519KeyPair) = deterministic_random_account_key();
+        let object_id = ObjectID::from_single_byte(1);
+        let request = br#"{"ptb":"AAA="}"#;
+        let response = br#"{"decryption_keys":[]}"#;
+        let timestamp = 1622548800;
+
+        let signature = kp.sign(&signed_response(
+            &object_id, request, 200, response, timestamp,
+        ));
+        assert!(verify_response_signature(
+            kp.public(),
+            &object_id,
+            request,
+            200,
+            response,
+            timestamp,
+            &signature
+        )
+        .is_ok());
+
+        // Any change to the response or the request is detected
+        assert!(verify_response_signature(
+            kp.public(),
+            &object_id,
+            request,
+            403,
+            response,
+            timestamp,
+            &signature
+        )
+        .is_err());
+        assert!(verify_response_signature(
+            kp.public(),
+            &object_id,
+            br#"{"ptb":"AAB="}"#,
+            200,
+            response,
+            timestamp,
+            &signature
+        )
+        .is_err());
+        assert!(verify_response_signature(
+            kp.public(),
+            &object_id,
+            request,
+            200,
+            response,
+            timestamp + 1,
+            &signature
+        )
+        .is_err());
+    }
 }
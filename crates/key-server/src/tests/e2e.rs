@@ -4,7 +4,9 @@
 use crate::tests::externals::get_key;
 use crate::tests::whitelist::{add_user_to_whitelist, create_whitelist, whitelist_create_ptb};
 use crate::tests::SealTestCluster;
-use crypto::{seal_decrypt, seal_encrypt, EncryptionInput, IBEPublicKeys, IBEUserSecretKeys};
+use crypto::{
+    seal_decrypt, seal_encrypt, EncryptionInput, IBEPublicKeys, IBEUserSecretKeys, ObjectID,
+};
 use tracing_test::traced_test;
 
 #[traced_test]
@@ -76,9 +78,9 @@ async fn test_e2e() {
 
     // Encrypt a message
     let message = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.";
-    let services = services.to_vec();
+    let services: Vec<ObjectID> = services.into_iter().map(Into::into).collect();
     let encryption = seal_encrypt(
-        examples_package_id,
+        examples_package_id.into(),
         whitelist.to_vec(),
         services.clone(),
         &pks,
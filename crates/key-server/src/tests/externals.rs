@@ -63,7 +63,7 @@ pub(crate) async fn get_key(
 ) -> FastCryptoResult<G1Element> {
     let (sk, pk, vk) = elgamal::genkey(&mut thread_rng());
     let (cert, req_sig) = sign(pkg_id, &ptb, &pk, &vk, kp, current_epoch_time(), 1);
-    server
+    let ids = server
         .check_request(
             &ptb_to_base64(&ptb),
             &pk,
@@ -75,11 +75,13 @@ pub(crate) async fn get_key(
             None,
         )
         .await
-        .map(|ids| {
-            elgamal::decrypt(
-                &sk,
-                &server.create_response(&ids, &pk).decryption_keys[0].encrypted_key,
-            )
-        })
-        .map_err(|_| fastcrypto::error::FastCryptoError::GeneralOpaqueError)
+        .map_err(|_| fastcrypto::error::FastCryptoError::GeneralOpaqueError)?;
+    let response = server
+        .create_response(&ids, &pk, None)
+        .await
+        .map_err(|_| fastcrypto::error::FastCryptoError::GeneralOpaqueError)?;
+    Ok(elgamal::decrypt(
+        &sk,
+        &response.decryption_keys[0].encrypted_key,
+    ))
 }
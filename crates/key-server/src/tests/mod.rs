@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::externals::{add_latest, add_package};
+use crate::extraction::KeyExtractor;
 use crate::types::Network;
 use crate::Server;
 use crypto::ibe;
@@ -14,6 +15,7 @@ use semver::VersionReq;
 use serde_json::json;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 use sui_move_build::BuildConfig;
 use sui_sdk::json::SuiJsonValue;
 use sui_sdk::rpc_types::{ObjectChange, SuiData, SuiObjectDataOptions};
@@ -25,6 +27,7 @@ use test_cluster::{TestCluster, TestClusterBuilder};
 mod e2e;
 mod externals;
 mod pd;
+mod seal_client;
 mod tle;
 mod whitelist;
 
@@ -62,10 +65,11 @@ impl SealTestCluster {
                 server: Server {
                     sui_client: cluster.sui_client().clone(),
                     network: Network::TestCluster,
-                    master_key,
                     key_server_object_id: ObjectID::ZERO,
                     key_server_object_id_sig: G1Element::generator(),
                     sdk_version_requirement: VersionReq::STAR,
+                    namespace_policy: Default::default(),
+                    extractor: Arc::new(KeyExtractor::new(master_key, 2)),
                 },
                 public_key,
             })
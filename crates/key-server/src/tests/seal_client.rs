@@ -0,0 +1,89 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::tests::whitelist::{add_user_to_whitelist, create_whitelist, whitelist_create_ptb};
+use crate::tests::SealTestCluster;
+use crate::FetchKeyRequest;
+use crypto::{seal_encrypt, EncryptionInput, IBEPublicKeys};
+use fastcrypto::traits::KeyPair;
+use rand::thread_rng;
+use seal_client::{decrypt, FetchKeyResponse, FetchedKeys, SessionKey, SignedRequest};
+use sui_types::base_types::ObjectID;
+use sui_types::crypto::SuiKeyPair;
+use tracing_test::traced_test;
+
+/// Requests signed by the Rust client are accepted by the key servers, and their responses can be decrypted.
+#[traced_test]
+#[tokio::test]
+async fn test_seal_client() {
+    let mut tc = SealTestCluster::new(2, 1).await;
+    let (package_id, _) = tc.publish("patterns").await;
+
+    let (whitelist, cap) = create_whitelist(tc.get_mut(), package_id).await;
+    let user_address = tc.users[0].address;
+    add_user_to_whitelist(tc.get_mut(), package_id, whitelist, cap, user_address).await;
+
+    // We know the version at this point
+    let initial_shared_version = 3;
+    let ptb = whitelist_create_ptb(package_id, whitelist, initial_shared_version);
+
+    let session_key = SessionKey::new(&mut thread_rng(), 1);
+    let certificate = session_key.sui_certificate(
+        &package_id,
+        &SuiKeyPair::Ed25519(tc.users[0].keypair.copy()),
+    );
+    let request = SignedRequest::sui(&session_key, certificate, &ptb);
+
+    // The request and responses are sent as JSON
+    let payload: FetchKeyRequest =
+        serde_json::from_value(serde_json::to_value(&request.body).unwrap()).unwrap();
+
+    let services = [ObjectID::random(), ObjectID::random()];
+    let mut keys = FetchedKeys::default();
+    for (service, key_server) in services.iter().zip(&tc.servers) {
+        let full_ids = key_server
+            .server
+            .check_request(
+                &payload.ptb,
+                &payload.enc_key,
+                &payload.enc_verification_key,
+                &payload.request_signature,
+                &payload.certificate,
+                1000,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let response = key_server
+            .server
+            .create_response(&full_ids, &payload.enc_key, None)
+            .await
+            .unwrap();
+        let response: FetchKeyResponse =
+            serde_json::from_value(serde_json::to_value(&response).unwrap()).unwrap();
+        for (full_id, key) in request
+            .decrypt_response(response, &key_server.public_key)
+            .unwrap()
+        {
+            keys.insert(*service, full_id, key);
+        }
+    }
+
+    let pks =
+        IBEPublicKeys::BonehFranklinBLS12381(tc.servers.iter().map(|s| s.public_key).collect());
+    let message = b"Hello from the Rust client";
+    let (encryption, _) = seal_encrypt(
+        package_id.into(),
+        whitelist.to_vec(),
+        services.map(Into::into).to_vec(),
+        &pks,
+        2,
+        EncryptionInput::Aes256Gcm {
+            data: message.to_vec(),
+            aad: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(decrypt(&encryption, &keys, Some(&pks)).unwrap(), message);
+}
@@ -6,6 +6,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tracing_test::traced_test;
 
 use crate::externals::get_latest_checkpoint_timestamp;
+use crate::parse_abort_code;
 use crate::tests::SealTestCluster;
 
 #[tokio::test]
@@ -74,3 +75,10 @@ async fn test_rgp_updater() {
 
     receiver.changed().await.expect("Failed to get latest rgp");
 }
+
+#[test]
+fn test_parse_abort_code() {
+    let error = "MoveAbort(MoveLocation { module: ModuleId { address: 0x1, name: Identifier(\"whitelist\") }, function: 1, instruction: 12, function_name: Some(\"seal_approve\") }, 77) in command 0";
+    assert_eq!(parse_abort_code(error), Some(77));
+    assert_eq!(parse_abort_code("InsufficientGas"), None);
+}
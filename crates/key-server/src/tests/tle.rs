@@ -3,11 +3,13 @@
 
 use crate::tests::externals::{ptb_to_base64, sign};
 use crate::tests::SealTestCluster;
-use crate::{current_epoch_time, InternalError};
-use crypto::elgamal;
+use crate::time_lock::{TimeLockFeed, MAX_RELEASED_KEYS};
+use crate::{current_epoch_time, get_latest_checkpoint_timestamp, InternalError};
+use crypto::{create_full_id, elgamal, ibe};
 use fastcrypto::ed25519::Ed25519KeyPair;
 use fastcrypto::traits::KeyPair;
 use rand::thread_rng;
+use std::sync::Mutex;
 use sui_types::{
     base_types::ObjectID,
     programmable_transaction_builder::ProgrammableTransactionBuilder,
@@ -266,6 +268,55 @@ async fn test_tle_signed_request() {
     assert_eq!(result.err(), Some(InternalError::InvalidSessionSignature));
 }
 
+#[traced_test]
+#[tokio::test]
+async fn test_tle_released_keys() {
+    let mut tc = SealTestCluster::new(1, 1).await;
+    let (package_id, _) = tc.publish("patterns").await;
+    let checkpoint_timestamp = get_latest_checkpoint_timestamp(tc.server().sui_client.clone())
+        .await
+        .unwrap();
+
+    let feed = TimeLockFeed::new(vec![format!("{}::tle:1000", package_id).parse().unwrap()]);
+    let releases = Mutex::new(Vec::new());
+    let record = |release| {
+        releases.lock().unwrap().push(release);
+        async { Ok::<_, InternalError>(()) }
+    };
+    feed.release(tc.server(), checkpoint_timestamp, 1000, record)
+        .await;
+
+    let keys = feed.keys(&package_id, None).unwrap();
+    assert_eq!(keys.len(), MAX_RELEASED_KEYS);
+    assert!(keys.last().unwrap().time <= checkpoint_timestamp);
+    for key in &keys {
+        assert_eq!(
+            key.id,
+            create_full_id(&package_id.into_bytes(), &get_tle_id(key.time))
+        );
+        assert!(ibe::verify_user_secret_key(&key.key, &key.id, &tc.servers[0].public_key).is_ok());
+    }
+    let released = releases.lock().unwrap().clone();
+    assert_eq!(released.len(), 1);
+    assert_eq!(released[0].ids.len(), MAX_RELEASED_KEYS);
+
+    // Nothing is released twice, and clients can poll for the keys after the last one they have seen.
+    feed.release(tc.server(), checkpoint_timestamp, 1000, record)
+        .await;
+    assert_eq!(releases.lock().unwrap().len(), 1);
+    let after = keys[MAX_RELEASED_KEYS - 3].time;
+    assert_eq!(feed.keys(&package_id, Some(after)).unwrap().len(), 2);
+
+    // Keys are not released if the module is not a time-lock policy.
+    let feed = TimeLockFeed::new(vec![format!("{}::whitelist:1000", package_id)
+        .parse()
+        .unwrap()]);
+    feed.release(tc.server(), checkpoint_timestamp, 1000, record)
+        .await;
+    assert_eq!(feed.keys(&package_id, None).unwrap().len(), 0);
+    assert_eq!(releases.lock().unwrap().len(), 1);
+}
+
 fn get_tle_id(time: u64) -> Vec<u8> {
     bcs::to_bytes(&time).unwrap()
 }
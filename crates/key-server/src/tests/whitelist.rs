@@ -1,8 +1,11 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use super::externals::get_key;
+use super::externals::{get_key, ptb_to_base64, sign};
+use crate::externals::current_epoch_time;
 use crate::tests::SealTestCluster;
+use crypto::elgamal;
+use rand::thread_rng;
 use serde_json::json;
 use sui_sdk::{json::SuiJsonValue, rpc_types::ObjectChange};
 use sui_types::{
@@ -120,6 +123,52 @@ async fn test_whitelist_with_upgrade() {
     .is_err());
 }
 
+#[traced_test]
+#[tokio::test]
+async fn test_whitelist_check_access() {
+    let mut tc = SealTestCluster::new(1, 2).await;
+
+    let (package_id, _) = tc.publish("patterns").await;
+    let (whitelist, cap) = create_whitelist(tc.get_mut(), package_id).await;
+    let user_address = tc.users[0].address;
+    add_user_to_whitelist(tc.get_mut(), package_id, whitelist, cap, user_address).await;
+    let ptb = whitelist_create_ptb(package_id, whitelist, 3);
+
+    // The first user is on the whitelist, the second is not
+    for (user, approved) in [(0, true), (1, false)] {
+        let (_, pk, vk) = elgamal::genkey(&mut thread_rng());
+        let (cert, req_sig) = sign(
+            &package_id,
+            &ptb,
+            &pk,
+            &vk,
+            &tc.users[user].keypair,
+            current_epoch_time(),
+            1,
+        );
+        let decisions = tc
+            .server()
+            .check_access(
+                &ptb_to_base64(&ptb),
+                &pk,
+                &vk,
+                &req_sig,
+                &cert,
+                1000,
+                false,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].approved, approved);
+        assert_eq!(decisions[0].abort_code.is_some(), !approved);
+        assert!(decisions[0].gas_used.is_some());
+        assert!(decisions[0].trace.is_none());
+    }
+}
+
 // TODO: fix next test (as the router was modified)
 // #[traced_test]
 // #[tokio::test]
@@ -0,0 +1,424 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Scheduled public release of time-lock keys.
+//!
+//! Time-lock policies like `patterns::tle` approve the identity `[pkg id][bcs(T)]` for anyone once the chain time
+//! passes `T`, so the key is public from then on. Instead of every client fetching such keys with a session, the key
+//! server releases them for the configured namespaces as soon as the latest checkpoint passes `T` and serves them on
+//! `/v1/released_keys/{namespace}`. Like all responses, the feed is signed by the key server, and each key can also be
+//! verified against the public key of the key server.
+
+use crate::errors::{ErrorResponse, InternalError};
+use crate::externals::{current_epoch_time, fetch_first_and_last_pkg_id};
+use crate::namespace_policy::Namespace;
+use crate::{KeyId, MyState, Server, Timestamp};
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use crypto::audit::{KeyRelease, PolicyEvidence};
+use crypto::{create_full_id, ibe};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::future::Future;
+use std::str::FromStr;
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
+use sui_types::transaction::{ObjectArg, ProgrammableTransaction};
+use sui_types::{Identifier, SUI_CLOCK_OBJECT_ID};
+use tracing::{debug, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+/// The number of released keys kept per namespace. Older keys must be fetched from the key server as usual.
+pub(crate) const MAX_RELEASED_KEYS: usize = 1000;
+
+/// A namespace with a time-lock policy. Keys are released for the times that are multiples of the interval, so
+/// encryptors should pick one of those times.
+///
+/// Parsed from strings of the form `<package id>::<module>:<interval ms>`, e.g., `0x2b8d..::tle:1000`. The package id
+/// must be the first version of the package.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TimeLockSchedule {
+    package_id: ObjectID,
+    /// The module with the `seal_approve(id, clock)` function.
+    module: Identifier,
+    interval_ms: u64,
+}
+
+impl FromStr for TimeLockSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid time-lock schedule '{}', expected <package id>::<module>:<interval ms>",
+                s
+            )
+        };
+        let (package_id, rest) = s.trim().split_once("::").ok_or_else(invalid)?;
+        let (module, interval_ms) = rest.split_once(':').ok_or_else(invalid)?;
+        let interval_ms: u64 = interval_ms.parse().map_err(|_| invalid())?;
+        if interval_ms == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            package_id: ObjectID::from_hex_literal(package_id).map_err(|_| invalid())?,
+            module: Identifier::new(module).map_err(|_| invalid())?,
+            interval_ms,
+        })
+    }
+}
+
+impl TimeLockSchedule {
+    /// The times for which keys are due at the given chain time if keys were last released for `last`, oldest first.
+    /// At most [MAX_RELEASED_KEYS] times are returned.
+    fn due_times(&self, last: Option<Timestamp>, now: Timestamp) -> Vec<Timestamp> {
+        let latest = now - now % self.interval_ms;
+        let oldest = latest.saturating_sub((MAX_RELEASED_KEYS as u64 - 1) * self.interval_ms);
+        let first = match last {
+            Some(last) => (last + self.interval_ms).max(oldest),
+            None => oldest,
+        };
+        std::iter::successors(Some(first), |t| t.checked_add(self.interval_ms))
+            .take_while(|t| *t <= latest)
+            .collect()
+    }
+
+    /// The transaction calling `seal_approve` for the given time on the given version of the package.
+    fn seal_approve_ptb(&self, package_id: ObjectID, time: Timestamp) -> ProgrammableTransaction {
+        let mut builder = ProgrammableTransactionBuilder::new();
+        let id = builder.pure(time_lock_id(time)).expect("valid bcs");
+        let clock = builder
+            .obj(ObjectArg::SharedObject {
+                id: SUI_CLOCK_OBJECT_ID,
+                initial_shared_version: 1.into(),
+                mutable: false,
+            })
+            .expect("valid object");
+        builder.programmable_move_call(
+            package_id,
+            self.module.clone(),
+            Identifier::new("seal_approve").expect("valid identifier"),
+            vec![],
+            vec![id, clock],
+        );
+        builder.finish()
+    }
+}
+
+/// The inner id of a time-lock identity.
+fn time_lock_id(time: Timestamp) -> Vec<u8> {
+    bcs::to_bytes(&time).expect("valid bcs")
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct ReleasedKey {
+    /// The time of the time-lock. The key was released once the chain time passed it.
+    pub time: Timestamp,
+    /// The full id of the key.
+    pub id: KeyId,
+    /// Base64 encoding of the key (a G1 element).
+    #[schema(value_type = String, format = Byte)]
+    pub key: ibe::UserSecretKey,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct ReleasedKeysResponse {
+    /// The released keys, oldest first.
+    keys: Vec<ReleasedKey>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub(crate) struct ReleasedKeysQuery {
+    /// Only return keys for times after this one.
+    after: Option<Timestamp>,
+}
+
+#[derive(Default)]
+struct ReleasedKeys {
+    keys: VecDeque<ReleasedKey>,
+    last_time: Option<Timestamp>,
+}
+
+/// The keys released for the configured time-lock namespaces.
+pub(crate) struct TimeLockFeed {
+    schedules: Vec<TimeLockSchedule>,
+    released: RwLock<HashMap<ObjectID, ReleasedKeys>>,
+}
+
+impl TimeLockFeed {
+    pub fn new(schedules: Vec<TimeLockSchedule>) -> Self {
+        Self {
+            schedules,
+            released: Default::default(),
+        }
+    }
+
+    /// Create a feed from the comma separated schedules in `TIME_LOCK_RELEASES`. Returns `None` if it is not set.
+    /// Panics if a schedule is invalid.
+    pub fn from_env() -> Option<Self> {
+        let schedules = env::var("TIME_LOCK_RELEASES").ok()?;
+        Some(Self::new(
+            schedules
+                .split(',')
+                .map(|s| s.parse().unwrap_or_else(|e: String| panic!("{}", e)))
+                .collect(),
+        ))
+    }
+
+    /// The released keys for a namespace with times after `after`, or `None` if the namespace is not configured.
+    pub fn keys(
+        &self,
+        package_id: &ObjectID,
+        after: Option<Timestamp>,
+    ) -> Option<Vec<ReleasedKey>> {
+        self.schedules
+            .iter()
+            .any(|s| s.package_id == *package_id)
+            .then(|| {
+                self.released
+                    .read()
+                    .get(package_id)
+                    .map(|released| {
+                        released
+                            .keys
+                            .iter()
+                            .filter(|k| after.is_none_or(|after| k.time > after))
+                            .cloned()
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+    }
+
+    /// Release the keys of all namespaces that are due at the given checkpoint timestamp.
+    ///
+    /// Before releasing any keys for a namespace, `seal_approve` of the latest version of the package is evaluated
+    /// for the latest due time. Since the policy is a time-lock, this implies that the earlier times are approved too.
+    /// Each release is passed to `record` before the keys are published, and nothing is published if that fails.
+    pub async fn release<F: Future<Output = Result<(), InternalError>>>(
+        &self,
+        server: &Server,
+        checkpoint_timestamp: Timestamp,
+        gas_price: u64,
+        record: impl Fn(KeyRelease) -> F,
+    ) {
+        for schedule in &self.schedules {
+            let last = self
+                .released
+                .read()
+                .get(&schedule.package_id)
+                .and_then(|r| r.last_time);
+            let times = schedule.due_times(last, checkpoint_timestamp);
+            let Some(latest) = times.last() else {
+                continue;
+            };
+            if let Err(e) = self
+                .check_policy(server, schedule, *latest, gas_price)
+                .await
+            {
+                warn!(
+                    "Not releasing time-lock keys for {} at {}: {:?}",
+                    schedule.package_id, latest, e
+                );
+                continue;
+            }
+
+            let ids = times
+                .iter()
+                .map(|time| create_full_id(&schedule.package_id.into_bytes(), &time_lock_id(*time)))
+                .collect::<Vec<_>>();
+            let extracted = match server.extractor.extract_batch(ids.clone()).await {
+                Ok(extracted) => extracted,
+                Err(e) => {
+                    warn!(
+                        "Failed to extract time-lock keys for {} at {}: {:?}",
+                        schedule.package_id, latest, e
+                    );
+                    continue;
+                }
+            };
+            let keys = times
+                .iter()
+                .zip(ids)
+                .zip(extracted)
+                .map(|((time, id), key)| ReleasedKey {
+                    time: *time,
+                    id,
+                    key,
+                })
+                .collect::<Vec<_>>();
+            let release = KeyRelease {
+                timestamp_ms: current_epoch_time(),
+                chain: "sui".to_string(),
+                namespace: schedule.package_id.to_string(),
+                ids: keys.iter().map(|k| hex::encode(&k.id)).collect(),
+                user: String::new(),
+                session_key: String::new(),
+                request_id: None,
+                evidence: PolicyEvidence::SuiCheckpoint {
+                    timestamp_ms: checkpoint_timestamp,
+                },
+            };
+            if record(release).await.is_err() {
+                continue;
+            }
+
+            let mut released = self.released.write();
+            let released = released.entry(schedule.package_id).or_default();
+            released.keys.extend(keys);
+            let excess = released.keys.len().saturating_sub(MAX_RELEASED_KEYS);
+            released.keys.drain(..excess);
+            released.last_time = Some(*latest);
+            debug!(
+                "Released {} time-lock keys for {} up to {}",
+                times.len(),
+                schedule.package_id,
+                latest
+            );
+        }
+    }
+
+    async fn check_policy(
+        &self,
+        server: &Server,
+        schedule: &TimeLockSchedule,
+        time: Timestamp,
+        gas_price: u64,
+    ) -> Result<(), InternalError> {
+        let (first, last) =
+            fetch_first_and_last_pkg_id(&schedule.package_id, &server.network).await?;
+        if first != schedule.package_id {
+            return Err(InternalError::InvalidPackage);
+        }
+        server
+            .namespace_policy
+            .check(&Namespace::Sui { package_id: first })?;
+        let dry_run_res = server
+            .dry_run(
+                SuiAddress::ZERO,
+                schedule.seal_approve_ptb(last, time),
+                gas_price,
+                None,
+            )
+            .await?;
+        if dry_run_res.effects.status().is_err() {
+            return Err(InternalError::NoAccess);
+        }
+        Ok(())
+    }
+
+    /// Spawns a thread that releases the keys that are due whenever the latest checkpoint timestamp is updated.
+    pub fn spawn_releaser(state: MyState) {
+        let Some(feed) = state.time_lock.clone() else {
+            return;
+        };
+        info!(
+            "Releasing time-lock keys for {} namespaces",
+            feed.schedules.len()
+        );
+        let mut receiver = state.latest_checkpoint_timestamp_receiver.clone();
+        tokio::task::spawn(async move {
+            loop {
+                let checkpoint_timestamp = *receiver.borrow_and_update();
+                feed.release(
+                    &state.server,
+                    checkpoint_timestamp,
+                    state.reference_gas_price(),
+                    |release| state.record_release(release),
+                )
+                .await;
+                if receiver.changed().await.is_err() {
+                    warn!(
+                        "Checkpoint timestamp updater stopped, no more time-lock keys are released"
+                    );
+                    return;
+                }
+            }
+        });
+    }
+}
+
+/// Get the keys released for a time-lock namespace. No certificate is needed since the keys are public.
+#[utoipa::path(
+    get,
+    path = "/v1/released_keys/{namespace}",
+    params(
+        ("namespace" = String, Path, description = "The package id of the time-lock policy"),
+        ReleasedKeysQuery,
+    ),
+    responses(
+        (status = 200, description = "The released keys", body = ReleasedKeysResponse),
+        (status = "4XX", description = "The namespace is not released by this key server", body = ErrorResponse),
+        (status = "5XX", description = "The key server failed to handle the request", body = ErrorResponse),
+    )
+)]
+pub(crate) async fn handle_released_keys(
+    State(app_state): State<MyState>,
+    Path(namespace): Path<String>,
+    Query(query): Query<ReleasedKeysQuery>,
+) -> Result<Json<ReleasedKeysResponse>, InternalError> {
+    app_state.metrics.released_keys_requests.inc();
+    let package_id =
+        ObjectID::from_hex_literal(&namespace).map_err(|_| InternalError::InvalidPackage)?;
+    app_state
+        .time_lock
+        .as_ref()
+        .and_then(|feed| feed.keys(&package_id, query.after))
+        .map(|keys| Json(ReleasedKeysResponse { keys }))
+        .ok_or(InternalError::InvalidPackage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(interval_ms: u64) -> TimeLockSchedule {
+        TimeLockSchedule {
+            package_id: ObjectID::random(),
+            module: Identifier::new("tle").unwrap(),
+            interval_ms,
+        }
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let parsed: TimeLockSchedule = "0x2::tle:1000".parse().unwrap();
+        assert_eq!(parsed.package_id, ObjectID::from_single_byte(2));
+        assert_eq!(parsed.module, Identifier::new("tle").unwrap());
+        assert_eq!(parsed.interval_ms, 1000);
+
+        for invalid in [
+            "0x2::tle",
+            "0x2:tle:1000",
+            "0x2::tle:0",
+            "0x2::1tle:1000",
+            "x::tle:1",
+        ] {
+            assert!(invalid.parse::<TimeLockSchedule>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_due_times() {
+        let schedule = schedule(1000);
+        assert_eq!(schedule.due_times(Some(1000), 3500), vec![2000, 3000]);
+        assert_eq!(schedule.due_times(Some(3000), 3500), vec![]);
+        assert_eq!(schedule.due_times(None, 2000), vec![0, 1000, 2000]);
+
+        // Only the latest keys are released after a long pause
+        let times = schedule.due_times(Some(0), 10_000_000);
+        assert_eq!(times.len(), MAX_RELEASED_KEYS);
+        assert_eq!(times.last(), Some(&10_000_000));
+        assert_eq!(times[0], 10_000_000 - 999_000);
+    }
+
+    #[test]
+    fn test_unknown_namespace() {
+        let feed = TimeLockFeed::new(vec![schedule(1000)]);
+        assert_eq!(feed.keys(&ObjectID::random(), None).map(|k| k.len()), None);
+        let package_id = feed.schedules[0].package_id;
+        assert_eq!(feed.keys(&package_id, None).map(|k| k.len()), Some(0));
+    }
+}
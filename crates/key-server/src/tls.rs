@@ -0,0 +1,412 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Optional native HTTPS for deployments without a TLS terminating proxy in front of the key server.
+//!
+//! If a client CA is given, the server also listens on a second port in mutual TLS mode which only accepts clients
+//! with a certificate issued by that CA, e.g., aggregators, other key servers or operators using the admin endpoints.
+//! The accepted client certificates can further be restricted to an allow-list of SHA-256 fingerprints. The public
+//! listener never asks for client certificates, so regular clients are not affected.
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use fastcrypto::hash::{HashFunction, Sha256};
+use rustls::client::danger::HandshakeSignatureValid;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{
+    DigitallySignedStruct, DistinguishedName, RootCertStore, ServerConfig, SignatureScheme,
+};
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tonic::transport::{Identity, ServerTlsConfig};
+use tracing::{info, warn};
+
+/// The port the key server listens on, same as for plain HTTP.
+pub(crate) const DEFAULT_PORT: u16 = 2024;
+
+/// The port of the mutual TLS listener, unless set by `MTLS_PORT`.
+const DEFAULT_MTLS_PORT: u16 = 2025;
+
+/// The interval at which the certificate files are checked for changes.
+const TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+type Fingerprint = [u8; 32];
+
+#[derive(Clone, Debug)]
+pub(crate) struct TlsConfig {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    /// If set, a second listener is started in mutual TLS mode.
+    mtls: Option<MtlsConfig>,
+}
+
+#[derive(Clone, Debug)]
+struct MtlsConfig {
+    port: u16,
+    /// Clients must present a certificate issued by this CA.
+    client_ca_path: PathBuf,
+    /// If non-empty, only client certificates with these fingerprints are accepted.
+    client_allowlist: HashSet<Fingerprint>,
+}
+
+impl TlsConfig {
+    /// Read the configuration from the environment variables `TLS_CERT_PATH`, `TLS_KEY_PATH`, `TLS_CLIENT_CA_PATH`,
+    /// `TLS_CLIENT_ALLOWLIST` (comma separated hex encoded SHA-256 fingerprints of client certificates) and
+    /// `MTLS_PORT`. Returns `None` if TLS is not enabled.
+    pub fn from_env() -> Option<Self> {
+        let cert_path = env::var("TLS_CERT_PATH").ok()?;
+        let key_path =
+            env::var("TLS_KEY_PATH").expect("TLS_KEY_PATH must be set if TLS_CERT_PATH is set");
+        let client_ca_path = env::var("TLS_CLIENT_CA_PATH").ok().map(PathBuf::from);
+        let client_allowlist = env::var("TLS_CLIENT_ALLOWLIST")
+            .map(|list| parse_fingerprints(&list).expect("Invalid TLS_CLIENT_ALLOWLIST"))
+            .unwrap_or_default();
+        if !client_allowlist.is_empty() && client_ca_path.is_none() {
+            panic!("TLS_CLIENT_CA_PATH must be set if TLS_CLIENT_ALLOWLIST is set");
+        }
+        let mtls = client_ca_path.map(|client_ca_path| MtlsConfig {
+            port: env::var("MTLS_PORT")
+                .map(|port| port.parse().expect("Invalid MTLS_PORT"))
+                .unwrap_or(DEFAULT_MTLS_PORT),
+            client_ca_path,
+            client_allowlist,
+        });
+        Some(Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            mtls,
+        })
+    }
+
+    /// Whether a mutual TLS listener is configured.
+    pub fn has_mtls(&self) -> bool {
+        self.mtls.is_some()
+    }
+
+    /// Serve the app over HTTPS. The certificates are reloaded when the files change.
+    ///
+    /// The admin endpoints, if given, are only served on the mutual TLS listener and never on the public listener.
+    pub async fn serve(self, app: Router, admin: Option<Router>) -> anyhow::Result<()> {
+        let public_config = RustlsConfig::from_config(Arc::new(self.server_config()?));
+        let mtls_config = match &self.mtls {
+            Some(mtls) => Some(RustlsConfig::from_config(Arc::new(
+                self.mtls_server_config(mtls)?,
+            ))),
+            None => None,
+        };
+        self.spawn_reloader(public_config.clone(), mtls_config.clone());
+
+        let addr = SocketAddr::from(([0, 0, 0, 0], DEFAULT_PORT));
+        match (&self.mtls, mtls_config) {
+            (Some(mtls), Some(mtls_config)) => {
+                let mtls_addr = SocketAddr::from(([0, 0, 0, 0], mtls.port));
+                info!("Serving HTTPS on {} and mutual TLS on {}", addr, mtls_addr);
+                let public = axum_server::bind_rustls(addr, public_config).serve(
+                    app.clone()
+                        .into_make_service_with_connect_info::<SocketAddr>(),
+                );
+                let internal = match admin {
+                    Some(admin) => app.merge(admin),
+                    None => app,
+                };
+                let internal = axum_server::bind_rustls(mtls_addr, mtls_config)
+                    .serve(internal.into_make_service_with_connect_info::<SocketAddr>());
+                tokio::try_join!(public, internal)?;
+            }
+            _ => {
+                info!("Serving HTTPS on {}", addr);
+                if admin.is_some() {
+                    warn!("Admin endpoints are not served on the public listener");
+                }
+                axum_server::bind_rustls(addr, public_config)
+                    .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The TLS configuration of the gRPC API. Like the public listener, it does not ask for client certificates. The
+    /// certificates are only read once, so the key server must be restarted for the gRPC API to use new ones.
+    pub fn grpc_tls_config(&self) -> anyhow::Result<ServerTlsConfig> {
+        Ok(ServerTlsConfig::new().identity(Identity::from_pem(
+            fs::read(&self.cert_path)?,
+            fs::read(&self.key_path)?,
+        )))
+    }
+
+    /// The configuration of the public listener, which does not ask for client certificates.
+    fn server_config(&self) -> anyhow::Result<ServerConfig> {
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(read_certs(&self.cert_path)?, read_key(&self.key_path)?)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+
+    /// The configuration of the mutual TLS listener.
+    fn mtls_server_config(&self, mtls: &MtlsConfig) -> anyhow::Result<ServerConfig> {
+        let mut config = ServerConfig::builder()
+            .with_client_cert_verifier(Arc::new(mtls.client_verifier()?))
+            .with_single_cert(read_certs(&self.cert_path)?, read_key(&self.key_path)?)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+
+    fn paths(&self) -> Vec<&Path> {
+        let mut paths = vec![self.cert_path.as_path(), self.key_path.as_path()];
+        paths.extend(self.mtls.as_ref().map(|mtls| mtls.client_ca_path.as_path()));
+        paths
+    }
+
+    /// Spawns a task that reloads the certificates when one of the files is modified. If the new files are invalid,
+    /// the previous configuration is kept.
+    fn spawn_reloader(&self, public_config: RustlsConfig, mtls_config: Option<RustlsConfig>) {
+        let tls_config = self.clone();
+        let mut interval = tokio::time::interval(TLS_RELOAD_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        tokio::task::spawn(async move {
+            let mut last_modified = modified_times(&tls_config.paths());
+            loop {
+                interval.tick().await;
+                let modified = modified_times(&tls_config.paths());
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                let reloaded = tls_config.server_config().and_then(|server_config| {
+                    let mtls_server_config = match (&tls_config.mtls, &mtls_config) {
+                        (Some(mtls), Some(_)) => Some(tls_config.mtls_server_config(mtls)?),
+                        _ => None,
+                    };
+                    Ok((server_config, mtls_server_config))
+                });
+                match reloaded {
+                    Ok((server_config, mtls_server_config)) => {
+                        public_config.reload_from_config(Arc::new(server_config));
+                        if let (Some(mtls_config), Some(mtls_server_config)) =
+                            (&mtls_config, mtls_server_config)
+                        {
+                            mtls_config.reload_from_config(Arc::new(mtls_server_config));
+                        }
+                        info!("Reloaded TLS certificates");
+                    }
+                    Err(e) => warn!("Failed to reload TLS certificates: {:?}", e),
+                }
+            }
+        });
+    }
+}
+
+impl MtlsConfig {
+    fn client_verifier(&self) -> anyhow::Result<AllowListVerifier> {
+        let mut roots = RootCertStore::empty();
+        for cert in read_certs(&self.client_ca_path)? {
+            roots.add(cert)?;
+        }
+        Ok(AllowListVerifier {
+            inner: WebPkiClientVerifier::builder(Arc::new(roots)).build()?,
+            allowlist: self.client_allowlist.clone(),
+        })
+    }
+}
+
+/// Verifies client certificates with the given verifier and checks that they are in the allow-list, if non-empty.
+#[derive(Debug)]
+struct AllowListVerifier {
+    inner: Arc<dyn ClientCertVerifier>,
+    allowlist: HashSet<Fingerprint>,
+}
+
+impl ClientCertVerifier for AllowListVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        self.inner.root_hint_subjects()
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.inner
+            .verify_client_cert(end_entity, intermediates, now)?;
+        if !self.allowlist.is_empty() && !self.allowlist.contains(&fingerprint(end_entity)) {
+            warn!(
+                "Rejected client certificate with fingerprint {}",
+                hex::encode(fingerprint(end_entity))
+            );
+            return Err(rustls::Error::General(
+                "Client certificate is not allowed".to_string(),
+            ));
+        }
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+fn fingerprint(cert: &CertificateDer<'_>) -> Fingerprint {
+    Sha256::digest(cert.as_ref()).digest
+}
+
+fn parse_fingerprints(list: &str) -> anyhow::Result<HashSet<Fingerprint>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            hex::decode(s.trim_start_matches("0x").replace(':', ""))
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid fingerprint {}", s))
+        })
+        .collect()
+}
+
+fn read_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in {}", path.display());
+    }
+    Ok(certs)
+}
+
+fn read_key(path: &Path) -> anyhow::Result<PrivateKeyDer<'static>> {
+    rustls_pemfile::private_key(&mut BufReader::new(File::open(path)?))?
+        .ok_or_else(|| anyhow::anyhow!("No private key found in {}", path.display()))
+}
+
+fn modified_times(paths: &[&Path]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| path.metadata().and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", rand::random::<u64>(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_parse_fingerprints() {
+        let fingerprint = hex::encode([7u8; 32]);
+        let parsed = parse_fingerprints(&format!("{}, 0x{}", fingerprint, fingerprint)).unwrap();
+        assert_eq!(parsed, HashSet::from([[7u8; 32]]));
+        assert!(parse_fingerprints("").unwrap().is_empty());
+        assert!(parse_fingerprints("0102").is_err());
+    }
+
+    #[test]
+    fn test_server_config() {
+        let server = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let client = rcgen::generate_simple_self_signed(vec!["client".to_string()]).unwrap();
+        let cert_path = write_temp("cert.pem", &server.cert.pem());
+        let key_path = write_temp("key.pem", &server.key_pair.serialize_pem());
+        let client_ca_path = write_temp("ca.pem", &client.cert.pem());
+
+        let mut config = TlsConfig {
+            cert_path: cert_path.clone(),
+            key_path: key_path.clone(),
+            mtls: None,
+        };
+        assert!(config.server_config().is_ok());
+
+        let mtls = MtlsConfig {
+            port: DEFAULT_MTLS_PORT,
+            client_ca_path: client_ca_path.clone(),
+            client_allowlist: HashSet::from([fingerprint(client.cert.der())]),
+        };
+        assert!(config.mtls_server_config(&mtls).is_ok());
+
+        // The key is not a certificate
+        config.cert_path = key_path.clone();
+        assert!(config.server_config().is_err());
+        assert!(config.mtls_server_config(&mtls).is_err());
+
+        for path in [cert_path, key_path, client_ca_path] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_client_allowlist() {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let client = |name: &str| {
+            CertificateParams::new(vec![name.to_string()])
+                .unwrap()
+                .signed_by(&KeyPair::generate().unwrap(), &ca, &ca_key)
+                .unwrap()
+        };
+        let allowed = client("allowed");
+        let other = client("other");
+        let client_ca_path = write_temp("ca.pem", &ca.pem());
+
+        let mtls = MtlsConfig {
+            port: DEFAULT_MTLS_PORT,
+            client_ca_path: client_ca_path.clone(),
+            client_allowlist: HashSet::from([fingerprint(allowed.der())]),
+        };
+        let verifier = mtls.client_verifier().unwrap();
+        let now = UnixTime::now();
+        assert!(verifier.verify_client_cert(allowed.der(), &[], now).is_ok());
+
+        // Issued by the CA, but not in the allow-list
+        assert!(verifier.verify_client_cert(other.der(), &[], now).is_err());
+
+        // Any certificate issued by the CA is accepted without an allow-list
+        let verifier = MtlsConfig {
+            client_allowlist: HashSet::new(),
+            ..mtls
+        }
+        .client_verifier()
+        .unwrap();
+        assert!(verifier.verify_client_cert(other.der(), &[], now).is_ok());
+
+        // Not issued by the CA
+        let self_signed = rcgen::generate_simple_self_signed(vec!["allowed".to_string()]).unwrap();
+        assert!(verifier
+            .verify_client_cert(self_signed.cert.der(), &[], now)
+            .is_err());
+
+        std::fs::remove_file(client_ca_path).unwrap();
+    }
+}
@@ -1,8 +1,11 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crypto::audit::PolicyEvidence;
 use crypto::elgamal;
 use crypto::ibe;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// The Identity-based encryption types.
 pub type IbeMasterKey = ibe::MasterKey;
@@ -17,6 +20,34 @@ pub type ElgamalVerificationKey = elgamal::VerificationKey<IbePublicKey>;
 /// Proof-of-possession of a key-servers master key.
 pub type MasterKeyPOP = ibe::ProofOfPossession;
 
+/// The outcome of evaluating the policy for a single key id, without releasing the key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AccessDecision {
+    /// The full id of the key.
+    pub id: Vec<u8>,
+    pub approved: bool,
+    /// Why access was denied, e.g., the execution error of the policy.
+    pub reason: Option<String>,
+    /// The abort code if a Move policy aborted.
+    pub abort_code: Option<u64>,
+    /// The output of an EVM policy that did not approve.
+    pub revert_data: Option<String>,
+    pub gas_used: Option<u64>,
+    /// The full simulation result. Only returned if the key server runs in developer mode. For Sui this is the
+    /// `DryRunTransactionBlockResponse` of `sui_dryRunTransactionBlock`, for Ethereum the `call_trace` of the
+    /// simulated transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Value>)]
+    pub trace: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CheckAccessResponse {
+    pub decisions: Vec<AccessDecision>,
+    /// The evidence the policy was evaluated against.
+    pub evidence: PolicyEvidence,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Network {
     Devnet,
@@ -1,13 +1,18 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use clap::{Parser, Subcommand};
-use crypto::dem::{Aes256Gcm, Hmac256Ctr};
+use clap::{Parser, Subcommand, ValueEnum};
+use crypto::audit::{read_log, verify_log, AuditSummary};
+use crypto::dem::{Aes256Gcm, ChaCha20Poly1305, Hmac256Ctr};
+use crypto::recovery::{RecipientPrivateKey, RecipientPublicKey};
 use crypto::EncryptionInput::Plain;
 use crypto::{
-    create_full_id, ibe, seal_decrypt, seal_encrypt, Ciphertext, EncryptedObject, EncryptionInput,
-    IBEEncryptions, IBEPublicKeys, IBEUserSecretKeys, ObjectID,
+    create_full_id, ibe, recover_base_key, recovery, reseal, seal_decrypt,
+    seal_decrypt_with_recovery_key, seal_encrypt_with_base_key, Ciphertext, EncryptedObject,
+    EncryptionInput, IBEEncryptions, IBEPublicKeys, IBEUserSecretKeys, Namespace, ObjectID,
+    RecoveryRecipient,
 };
+use fastcrypto::ed25519::Ed25519PublicKey;
 use fastcrypto::encoding::Encoding;
 use fastcrypto::encoding::Hex;
 use fastcrypto::error::{FastCryptoError, FastCryptoResult};
@@ -16,6 +21,9 @@ use rand::thread_rng;
 use serde::Deserialize;
 use serde::Serialize;
 use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 const KEY_LENGTH: usize = 32;
@@ -35,6 +43,12 @@ struct Arguments {
 enum Command {
     /// Generate a new master key and public key.
     Genkey,
+    /// Generate a new private key and public key for a recovery recipient.
+    GenkeyRecovery {
+        /// The curve of the key pair
+        #[arg(long, value_enum, default_value_t)]
+        key_type: RecoveryKeyType,
+    },
     /// Extract a user secret key from an id and a master key.
     Extract {
         /// The Sui address of the Move package that handles the KMS for this key
@@ -64,7 +78,7 @@ enum Command {
     },
     /// Derive a key using Seal.
     /// The key is derived from the ID using an IBKEM, Boneh-Franklin over BLS12381.
-    /// This outputs both the encrypted object as a hex-encoded BCS serialization, which can be shared publicly, and the derived symmetric key which should be kept privately.
+    /// This outputs the encrypted object as a hex-encoded BCS serialization, which can be shared publicly, and the derived symmetric key and the base key, which should be kept privately.
     Plain {
         /// The Sui address of the Move package that handles the KMS for this key
         #[arg(long)]
@@ -75,6 +89,10 @@ enum Command {
         /// The number of key servers that need to be present for decryption
         #[arg(long)]
         threshold: u8,
+        /// Comma separated public keys of recovery recipients who can decrypt without the key servers. The curve is given
+        /// by the length: 48 bytes for BLS12-381, 32 bytes for X25519 and 33 bytes for a compressed secp256k1 key.
+        #[arg(long, value_delimiter = ',', value_parser = parse_recipient_public_key)]
+        recovery_keys: Vec<RecipientPublicKey>,
         /// The hex-encoded public keys for the key servers
         #[arg(value_parser = parse_serializable::<G2Element, DefaultEncoding>, num_args = 1..)]
         public_keys: Vec<G2Element>,
@@ -84,7 +102,7 @@ enum Command {
     },
     /// Encrypt a message using Seal.
     /// The key is derived from the ID using an IBKEM, Boneh-Franklin over BLS12381, and the message is encrypted using AES-256-GCM.
-    /// This outputs both the encrypted object as a hex-encoded BCS serialization, which can be shared publicly, and the derived symmetric key which should be kept privately.
+    /// This outputs the encrypted object as a hex-encoded BCS serialization, which can be shared publicly, and the derived symmetric key and the base key, which should be kept privately.
     EncryptAes {
         /// The message to encrypt as hex-encoded bytes
         #[arg(long)]
@@ -92,6 +110,9 @@ enum Command {
         /// Optional additional authenticated data as hex-encoded bytes
         #[arg(long)]
         aad: Option<EncodedBytes>,
+        /// Add a commitment to the key such that everyone decrypting the object gets the same message
+        #[arg(long)]
+        committing: bool,
         /// The Sui address of the Move package that handles the KMS for this encryption
         #[arg(long)]
         package_id: ObjectID,
@@ -101,6 +122,10 @@ enum Command {
         /// The number of key servers that need to be present for decryption
         #[arg(long)]
         threshold: u8,
+        /// Comma separated public keys of recovery recipients who can decrypt without the key servers. The curve is given
+        /// by the length: 48 bytes for BLS12-381, 32 bytes for X25519 and 33 bytes for a compressed secp256k1 key.
+        #[arg(long, value_delimiter = ',', value_parser = parse_recipient_public_key)]
+        recovery_keys: Vec<RecipientPublicKey>,
         /// The hex-encoded public keys for the key servers
         #[arg(value_parser = parse_serializable::<G2Element, DefaultEncoding>, num_args = 1..)]
         public_keys: Vec<G2Element>,
@@ -110,7 +135,7 @@ enum Command {
     },
     /// Encrypt a message using Seal.
     /// The key is derived from the ID using an IBKEM, Boneh-Franklin over BLS12381, and the message is encrypted using counter-mode with hmac-sha3-256 as a PRF.
-    /// This outputs both the encrypted object as a hex-encoded BCS serialization, which can be shared publicly, and the derived symmetric key which should be kept privately.
+    /// This outputs the encrypted object as a hex-encoded BCS serialization, which can be shared publicly, and the derived symmetric key and the base key, which should be kept privately.
     EncryptHmac {
         /// The message to encrypt as hex-encoded bytes
         #[arg(long)]
@@ -127,6 +152,40 @@ enum Command {
         /// The number of key servers that need to be present for decryption
         #[arg(long)]
         threshold: u8,
+        /// Comma separated public keys of recovery recipients who can decrypt without the key servers. The curve is given
+        /// by the length: 48 bytes for BLS12-381, 32 bytes for X25519 and 33 bytes for a compressed secp256k1 key.
+        #[arg(long, value_delimiter = ',', value_parser = parse_recipient_public_key)]
+        recovery_keys: Vec<RecipientPublicKey>,
+        /// The hex-encoded public keys for the key servers
+        #[arg(value_parser = parse_serializable::<G2Element, DefaultEncoding>, num_args = 1..)]
+        public_keys: Vec<G2Element>,
+        /// The address for the Move objects representing the key servers
+        #[arg(num_args = 1.., last = true)]
+        object_ids: Vec<ObjectID>,
+    },
+    /// Encrypt a message using Seal.
+    /// The key is derived from the ID using an IBKEM, Boneh-Franklin over BLS12381, and the message is encrypted using ChaCha20-Poly1305.
+    /// This outputs the encrypted object as a hex-encoded BCS serialization, which can be shared publicly, and the derived symmetric key and the base key, which should be kept privately.
+    EncryptChacha {
+        /// The message to encrypt as hex-encoded bytes
+        #[arg(long)]
+        message: EncodedBytes,
+        /// Optional additional authenticated data as hex-encoded bytes
+        #[arg(long)]
+        aad: Option<EncodedBytes>,
+        /// The Sui address of the Move package that handles the KMS for this encryption
+        #[arg(long)]
+        package_id: ObjectID,
+        /// The ID of the key that should be used for this encryption
+        #[arg(long)]
+        id: EncodedBytes,
+        /// The number of key servers that need to be present for decryption
+        #[arg(long)]
+        threshold: u8,
+        /// Comma separated public keys of recovery recipients who can decrypt without the key servers. The curve is given
+        /// by the length: 48 bytes for BLS12-381, 32 bytes for X25519 and 33 bytes for a compressed secp256k1 key.
+        #[arg(long, value_delimiter = ',', value_parser = parse_recipient_public_key)]
+        recovery_keys: Vec<RecipientPublicKey>,
         /// The hex-encoded public keys for the key servers
         #[arg(value_parser = parse_serializable::<G2Element, DefaultEncoding>, num_args = 1..)]
         public_keys: Vec<G2Element>,
@@ -148,6 +207,45 @@ enum Command {
         #[arg(num_args = 1.., last = true)]
         object_ids: Vec<ObjectID>,
     },
+    /// Decrypt a Seal encrypted object with the private key of one of its recovery recipients, without key servers.
+    DecryptRecovery {
+        /// An encrypted object as hex-encoded bytes
+        #[arg(value_parser = parse_serializable::<EncryptedObject, DefaultEncoding>)]
+        encrypted_object: EncryptedObject,
+        /// Private key of the recovery recipient as hex-encoded bytes.
+        #[arg(long)]
+        private_key: EncodedBytes,
+        /// The curve of the private key
+        #[arg(long, value_enum, default_value_t)]
+        key_type: RecoveryKeyType,
+    },
+    /// Re-seal a Seal encrypted object for a new set of key servers and threshold without re-encrypting the message.
+    /// The base key is either given directly or recovered from secret keys of the current key servers.
+    /// This outputs the re-sealed encrypted object as a hex-encoded BCS serialization.
+    Reseal {
+        /// An encrypted object as hex-encoded bytes
+        #[arg(value_parser = parse_serializable::<EncryptedObject, DefaultEncoding>)]
+        encrypted_object: EncryptedObject,
+        /// Comma separated secret keys for current key servers of the encrypted object
+        #[arg(long, value_delimiter = ',', value_parser = parse_serializable::<G1Element, DefaultEncoding>, requires = "secret_key_object_ids", required_unless_present = "base_key")]
+        secret_keys: Vec<G1Element>,
+        /// Comma separated addresses for the Move objects representing the key servers of the secret keys
+        #[arg(long, value_delimiter = ',')]
+        secret_key_object_ids: Vec<ObjectID>,
+        /// The base key of the encrypted object as hex-encoded bytes, as output by the encryption commands. This is not
+        /// the symmetric key. Can be given instead of the secret keys.
+        #[arg(long, conflicts_with = "secret_keys")]
+        base_key: Option<EncodedBytes>,
+        /// The number of new key servers that need to be present for decryption
+        #[arg(long)]
+        threshold: u8,
+        /// The hex-encoded public keys for the new key servers
+        #[arg(value_parser = parse_serializable::<G2Element, DefaultEncoding>, num_args = 1..)]
+        public_keys: Vec<G2Element>,
+        /// The address for the Move objects representing the new key servers
+        #[arg(num_args = 1.., last = true)]
+        object_ids: Vec<ObjectID>,
+    },
     /// Parse a Seal encrypted object.
     /// This outputs the parts of the parsed encrypted object as a hex-encoded BCS serialization.
     Parse {
@@ -163,21 +261,44 @@ enum Command {
         #[arg(long)]
         key: EncodedBytes,
     },
+    /// Inspect the audit log of a key server.
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditCommand {
+    /// Verify that an audit log is a valid hash chain and that all checkpoints are correctly signed.
+    Verify {
+        /// The path to the audit log.
+        path: PathBuf,
+        /// Optional audit public key of the key server. If given, all checkpoints must be signed by this key.
+        #[arg(long, value_parser = parse_serializable::<Ed25519PublicKey, DefaultEncoding>)]
+        audit_key: Option<Ed25519PublicKey>,
+    },
 }
 
 struct GenkeyOutput((Scalar, G2Element));
+struct GenkeyRecoveryOutput((RecipientPrivateKey, RecipientPublicKey));
 struct ExtractOutput(G1Element);
 struct VerifyOutput(FastCryptoResult<()>);
-struct EncryptionOutput((EncryptedObject, [u8; KEY_LENGTH]));
+struct EncryptionOutput((EncryptedObject, [u8; KEY_LENGTH], [u8; KEY_LENGTH]));
 struct DecryptionOutput(Vec<u8>);
+struct ResealOutput(EncryptedObject);
 struct ParseOutput(EncryptedObject);
 struct SymmetricDecryptOutput(Vec<u8>);
+struct AuditVerifyOutput(AuditSummary);
 
 fn main() -> FastCryptoResult<()> {
     let args = Arguments::parse();
 
     let output = match args.command {
         Command::Genkey => GenkeyOutput(ibe::generate_key_pair(&mut thread_rng())).to_string(),
+        Command::GenkeyRecovery { key_type } => {
+            GenkeyRecoveryOutput(generate_recovery_key_pair(key_type)?).to_string()
+        }
         Command::Extract {
             package_id,
             id,
@@ -202,34 +323,46 @@ fn main() -> FastCryptoResult<()> {
             package_id,
             id,
             threshold,
+            recovery_keys,
             public_keys,
             object_ids,
-        } => EncryptionOutput(seal_encrypt(
+        } => EncryptionOutput(encrypt(
             package_id,
             id.0,
             object_ids,
             &IBEPublicKeys::BonehFranklinBLS12381(public_keys),
             threshold,
+            &recovery_keys,
             Plain,
         )?)
         .to_string(),
         Command::EncryptAes {
             message,
             aad,
+            committing,
             package_id,
             id,
             threshold,
+            recovery_keys,
             public_keys,
             object_ids,
-        } => EncryptionOutput(seal_encrypt(
+        } => EncryptionOutput(encrypt(
             package_id,
             id.0,
             object_ids,
             &IBEPublicKeys::BonehFranklinBLS12381(public_keys),
             threshold,
-            EncryptionInput::Aes256Gcm {
-                data: message.0,
-                aad: aad.map(|a| a.0),
+            &recovery_keys,
+            if committing {
+                EncryptionInput::CommittingAes256Gcm {
+                    data: message.0,
+                    aad: aad.map(|a| a.0),
+                }
+            } else {
+                EncryptionInput::Aes256Gcm {
+                    data: message.0,
+                    aad: aad.map(|a| a.0),
+                }
             },
         )?)
         .to_string(),
@@ -239,20 +372,44 @@ fn main() -> FastCryptoResult<()> {
             package_id,
             id,
             threshold,
+            recovery_keys,
             public_keys,
             object_ids,
-        } => EncryptionOutput(seal_encrypt(
+        } => EncryptionOutput(encrypt(
             package_id,
             id.0,
             object_ids,
             &IBEPublicKeys::BonehFranklinBLS12381(public_keys),
             threshold,
+            &recovery_keys,
             EncryptionInput::Hmac256Ctr {
                 data: message.0,
                 aad: aad.map(|a| a.0),
             },
         )?)
         .to_string(),
+        Command::EncryptChacha {
+            message,
+            aad,
+            package_id,
+            id,
+            threshold,
+            recovery_keys,
+            public_keys,
+            object_ids,
+        } => EncryptionOutput(encrypt(
+            package_id,
+            id.0,
+            object_ids,
+            &IBEPublicKeys::BonehFranklinBLS12381(public_keys),
+            threshold,
+            &recovery_keys,
+            EncryptionInput::ChaCha20Poly1305 {
+                data: message.0,
+                aad: aad.map(|a| a.0),
+            },
+        )?)
+        .to_string(),
         Command::Decrypt {
             encrypted_object,
             secret_keys,
@@ -265,6 +422,46 @@ fn main() -> FastCryptoResult<()> {
             None,
         )?)
         .to_string(),
+        Command::DecryptRecovery {
+            encrypted_object,
+            private_key,
+            key_type,
+        } => DecryptionOutput(seal_decrypt_with_recovery_key(
+            &encrypted_object,
+            &recovery_private_key(key_type, private_key.0)?,
+        )?)
+        .to_string(),
+        Command::Reseal {
+            encrypted_object,
+            secret_keys,
+            secret_key_object_ids,
+            base_key,
+            threshold,
+            public_keys,
+            object_ids,
+        } => {
+            let base_key = match base_key {
+                Some(base_key) => base_key
+                    .0
+                    .try_into()
+                    .map_err(|_| FastCryptoError::InvalidInput)?,
+                None => recover_base_key(
+                    &encrypted_object,
+                    &IBEUserSecretKeys::BonehFranklinBLS12381(
+                        secret_key_object_ids.into_iter().zip(secret_keys).collect(),
+                    ),
+                    None,
+                )?,
+            };
+            ResealOutput(reseal(
+                &encrypted_object,
+                &base_key,
+                object_ids,
+                &IBEPublicKeys::BonehFranklinBLS12381(public_keys),
+                threshold,
+            )?)
+            .to_string()
+        }
         Command::Parse { encrypted_object } => ParseOutput(encrypted_object).to_string(),
         Command::SymmetricDecrypt {
             encrypted_object,
@@ -277,22 +474,110 @@ fn main() -> FastCryptoResult<()> {
             let EncryptedObject { ciphertext, .. } = encrypted_object;
 
             match ciphertext {
-                Ciphertext::Aes256Gcm { blob, aad } => {
+                // The key commitment cannot be checked given only the DEM key
+                Ciphertext::Aes256Gcm { blob, aad }
+                | Ciphertext::CommittingAes256Gcm { blob, aad, .. } => {
                     Aes256Gcm::decrypt(&blob, &aad.unwrap_or(vec![]), &dem_key)
                 }
                 Ciphertext::Hmac256Ctr { blob, aad, mac } => {
                     Hmac256Ctr::decrypt(&blob, &mac, &aad.unwrap_or(vec![]), &dem_key)
                 }
+                Ciphertext::ChaCha20Poly1305 { blob, aad } => {
+                    ChaCha20Poly1305::decrypt(&blob, &aad.unwrap_or(vec![]), &dem_key)
+                }
                 _ => Err(FastCryptoError::InvalidInput),
             }
             .map(SymmetricDecryptOutput)?
             .to_string()
         }
+        Command::Audit {
+            command: AuditCommand::Verify { path, audit_key },
+        } => {
+            let file = File::open(&path).map_err(|e| {
+                FastCryptoError::GeneralError(format!("Failed to open {}: {}", path.display(), e))
+            })?;
+            let entries = read_log(BufReader::new(file))?;
+            AuditVerifyOutput(verify_log(&entries, audit_key.as_ref())?).to_string()
+        }
     };
     println!("{}", output);
     Ok(())
 }
 
+/// Encrypt for a Sui package, and for the recovery recipients if any are given.
+fn encrypt(
+    package_id: ObjectID,
+    id: Vec<u8>,
+    key_servers: Vec<ObjectID>,
+    public_keys: &IBEPublicKeys,
+    threshold: u8,
+    recovery_keys: &[RecipientPublicKey],
+    encryption_input: EncryptionInput,
+) -> FastCryptoResult<(EncryptedObject, [u8; KEY_LENGTH], [u8; KEY_LENGTH])> {
+    seal_encrypt_with_base_key(
+        Namespace::SuiPackage(package_id),
+        id,
+        key_servers,
+        public_keys,
+        threshold,
+        recovery_keys,
+        encryption_input,
+    )
+}
+
+/// The curve of a recovery recipient key.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+enum RecoveryKeyType {
+    #[default]
+    Bls12381,
+    X25519,
+    Secp256k1,
+}
+
+fn generate_recovery_key_pair(
+    key_type: RecoveryKeyType,
+) -> FastCryptoResult<(RecipientPrivateKey, RecipientPublicKey)> {
+    let private_key = match key_type {
+        RecoveryKeyType::Bls12381 => {
+            RecipientPrivateKey::BLS12381(recovery::generate_key_pair(&mut thread_rng()).0)
+        }
+        RecoveryKeyType::X25519 => {
+            RecipientPrivateKey::X25519(recovery::x25519::generate_key_pair(&mut thread_rng()).0)
+        }
+        RecoveryKeyType::Secp256k1 => RecipientPrivateKey::Secp256k1(
+            recovery::secp256k1::generate_key_pair(&mut thread_rng()).0,
+        ),
+    };
+    let public_key = private_key.public_key()?;
+    Ok((private_key, public_key))
+}
+
+fn recovery_private_key(
+    key_type: RecoveryKeyType,
+    bytes: Vec<u8>,
+) -> FastCryptoResult<RecipientPrivateKey> {
+    Ok(match key_type {
+        RecoveryKeyType::Bls12381 => RecipientPrivateKey::BLS12381(
+            bcs::from_bytes(&bytes).map_err(|_| FastCryptoError::InvalidInput)?,
+        ),
+        RecoveryKeyType::X25519 => RecipientPrivateKey::X25519(
+            bytes
+                .try_into()
+                .map_err(|_| FastCryptoError::InvalidInput)?,
+        ),
+        RecoveryKeyType::Secp256k1 => RecipientPrivateKey::Secp256k1(
+            bytes
+                .try_into()
+                .map_err(|_| FastCryptoError::InvalidInput)?,
+        ),
+    })
+}
+
+fn parse_recipient_public_key(s: &str) -> Result<RecipientPublicKey, String> {
+    let bytes = DefaultEncoding::decode(s).map_err(|e| format!("{}", e))?;
+    RecipientPublicKey::from_bytes(&bytes).map_err(|e| format!("{}", e))
+}
+
 /// Type used for binary inputs to the CLI.
 #[derive(Debug, Clone)]
 struct EncodedBytes(Vec<u8>);
@@ -328,6 +613,17 @@ impl Display for GenkeyOutput {
     }
 }
 
+impl Display for GenkeyRecoveryOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Private key: {}\nPublic key: {}",
+            DefaultEncoding::encode(self.0 .0.to_bytes()),
+            DefaultEncoding::encode(self.0 .1.to_bytes()),
+        )
+    }
+}
+
 impl Display for ExtractOutput {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "User secret key: {}", serializable_to_string(&self.0))
@@ -352,9 +648,10 @@ impl Display for EncryptionOutput {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Encrypted object (bcs): {}\nSymmetric key: {}",
+            "Encrypted object (bcs): {}\nSymmetric key: {}\nBase key: {}",
             DefaultEncoding::encode(bcs::to_bytes(&self.0 .0).unwrap()),
-            Hex::encode(self.0 .1)
+            Hex::encode(self.0 .1),
+            Hex::encode(self.0 .2)
         )
     }
 }
@@ -365,10 +662,20 @@ impl Display for DecryptionOutput {
     }
 }
 
+impl Display for ResealOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Encrypted object (bcs): {}",
+            DefaultEncoding::encode(bcs::to_bytes(&self.0).unwrap())
+        )
+    }
+}
+
 impl Display for ParseOutput {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Version: {}", self.0.version)?;
-        writeln!(f, "Package ID: {}", self.0.package_id)?;
+        write_namespace(f, &self.0.namespace)?;
         writeln!(f, "ID: {}", DefaultEncoding::encode(&self.0.id))?;
         writeln!(f, "Services: share index:")?;
         for (id, index) in &self.0.services {
@@ -401,6 +708,41 @@ impl Display for ParseOutput {
             Ciphertext::Plain => {
                 writeln!(f, "  Type: Plain")?;
             }
+            Ciphertext::ChaCha20Poly1305 { blob, aad } => {
+                writeln!(f, "  Type: ChaCha20-Poly1305")?;
+                writeln!(f, "  Blob: {}", DefaultEncoding::encode(blob))?;
+                writeln!(
+                    f,
+                    "  AAD: {}",
+                    aad.as_ref()
+                        .map_or("None".to_string(), DefaultEncoding::encode)
+                )?;
+            }
+            Ciphertext::CommittingAes256Gcm {
+                blob,
+                aad,
+                commitment,
+            } => {
+                writeln!(f, "  Type: AES-256-GCM with key commitment")?;
+                writeln!(f, "  Blob: {}", DefaultEncoding::encode(blob))?;
+                writeln!(
+                    f,
+                    "  AAD: {}",
+                    aad.as_ref()
+                        .map_or("None".to_string(), DefaultEncoding::encode)
+                )?;
+                writeln!(f, "  Commitment: {}", DefaultEncoding::encode(commitment))?;
+            }
+            Ciphertext::Aes256GcmStream { chunk_size, aad } => {
+                writeln!(f, "  Type: AES-256-GCM stream")?;
+                writeln!(f, "  Chunk size: {}", chunk_size)?;
+                writeln!(
+                    f,
+                    "  AAD: {}",
+                    aad.as_ref()
+                        .map_or("None".to_string(), DefaultEncoding::encode)
+                )?;
+            }
         }
         writeln!(f, "Encrypted shares:")?;
         match &self.0.encrypted_shares {
@@ -421,12 +763,65 @@ impl Display for ParseOutput {
                 )?;
             }
         };
+        for (i, policy) in self.0.alternatives.iter().enumerate() {
+            writeln!(f, "\nAlternative policy {}:", i)?;
+            write_namespace(f, &policy.namespace)?;
+            writeln!(f, "ID: {}", DefaultEncoding::encode(&policy.id))?;
+            writeln!(f, "Services: share index:")?;
+            for (id, index) in &policy.services {
+                writeln!(f, "  {}: {}", id, index)?;
+            }
+            write!(f, "Threshold: {}", policy.threshold)?;
+        }
+        if !self.0.recovery.is_empty() {
+            write!(f, "\nRecovery recipients:")?;
+        }
+        for recipient in &self.0.recovery {
+            let curve = match recipient {
+                RecoveryRecipient::EciesBLS12381 { .. } => "BLS12-381",
+                RecoveryRecipient::EciesX25519 { .. } => "X25519",
+                RecoveryRecipient::EciesSecp256k1 { .. } => "secp256k1",
+            };
+            write!(
+                f,
+                "\n  {} ({})",
+                DefaultEncoding::encode(recipient.public_key().to_bytes()),
+                curve
+            )?;
+        }
         Ok(())
     }
 }
 
+fn write_namespace(f: &mut Formatter<'_>, namespace: &Namespace) -> std::fmt::Result {
+    match namespace {
+        Namespace::SuiPackage(package_id) => writeln!(f, "Package ID: {}", package_id),
+        Namespace::Evm { chain_id, address } => {
+            writeln!(f, "EVM chain ID: {}", chain_id)?;
+            writeln!(f, "Contract address: 0x{}", Hex::encode(address))
+        }
+        Namespace::Solana { program } => {
+            writeln!(f, "Solana program: {}", DefaultEncoding::encode(program))
+        }
+    }
+}
+
 impl Display for SymmetricDecryptOutput {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Decrypted message: {}", DefaultEncoding::encode(&self.0))
     }
 }
+
+impl Display for AuditVerifyOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Audit log is valid")?;
+        writeln!(f, "Entries: {}", self.0.entries)?;
+        writeln!(f, "Key releases: {}", self.0.key_releases)?;
+        writeln!(f, "Checkpoints: {}", self.0.checkpoints)?;
+        write!(
+            f,
+            "Entries after last checkpoint: {}",
+            self.0.unsigned_entries
+        )
+    }
+}
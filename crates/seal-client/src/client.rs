@@ -0,0 +1,279 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::errors::{SealClientError, ServerError};
+use crate::request::{FetchKeyResponse, SignedRequest};
+use crate::session::{EthereumCertificate, SessionKey, SuiCertificate};
+use crate::FetchedKeys;
+use crypto::ibe;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::Serialize;
+use sui_types::base_types::ObjectID;
+use sui_types::transaction::ProgrammableTransaction;
+use tracing::{debug, warn};
+
+/// The SDK version sent to the key servers. Key servers reject requests from SDK versions they no longer support, and
+/// this client implements the API of SDK version 0.3.5.
+const CLIENT_SDK_VERSION: &str = "0.3.5";
+
+/// A key server, identified by the id of its on-chain object.
+#[derive(Clone, Debug)]
+pub struct KeyServerConfig {
+    pub object_id: ObjectID,
+    pub url: String,
+    /// The public key of the key server, as registered on-chain. All fetched keys are verified against it.
+    pub public_key: ibe::PublicKey,
+}
+
+/// Fetches keys from a set of key servers.
+pub struct SealClient {
+    http: reqwest::Client,
+    key_servers: Vec<KeyServerConfig>,
+}
+
+impl SealClient {
+    pub fn new(key_servers: Vec<KeyServerConfig>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            key_servers,
+        }
+    }
+
+    /// Fetch the keys approved by the `seal_approve*` calls of the given PTB from at least `threshold` key servers.
+    pub async fn fetch_keys_sui(
+        &self,
+        session_key: &SessionKey,
+        certificate: SuiCertificate,
+        ptb: &ProgrammableTransaction,
+        threshold: usize,
+    ) -> Result<FetchedKeys, SealClientError> {
+        let request = SignedRequest::sui(session_key, certificate, ptb);
+        self.fetch_keys("/v1/fetch_key", &request, threshold).await
+    }
+
+    /// Fetch the keys approved by the given JSON encoded list of transactions from at least `threshold` key servers.
+    pub async fn fetch_keys_ethereum(
+        &self,
+        session_key: &SessionKey,
+        certificate: EthereumCertificate,
+        transactions: &[u8],
+        threshold: usize,
+    ) -> Result<FetchedKeys, SealClientError> {
+        let request = SignedRequest::ethereum(session_key, certificate, transactions);
+        self.fetch_keys("/v1/fetch_key_ethereum", &request, threshold)
+            .await
+    }
+
+    /// Send the request to all key servers and return once `threshold` of them returned valid keys.
+    async fn fetch_keys<C: Serialize>(
+        &self,
+        path: &str,
+        request: &SignedRequest<C>,
+        threshold: usize,
+    ) -> Result<FetchedKeys, SealClientError> {
+        if threshold == 0 || threshold > self.key_servers.len() {
+            return Err(SealClientError::InvalidThreshold);
+        }
+
+        let mut pending = self
+            .key_servers
+            .iter()
+            .map(|server| async move { (server, self.fetch_from(server, path, request).await) })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut keys = FetchedKeys::default();
+        let mut responses = 0;
+        let mut errors = Vec::new();
+        while let Some((server, result)) = pending.next().await {
+            match result {
+                Ok(server_keys) => {
+                    debug!("Received {} keys from {}", server_keys.len(), server.url);
+                    for (full_id, key) in server_keys {
+                        keys.insert(server.object_id, full_id, key);
+                    }
+                    responses += 1;
+                    if responses == threshold {
+                        return Ok(keys);
+                    }
+                }
+                Err(error) => {
+                    warn!("Request to {} failed: {}", server.url, error.error);
+                    errors.push(error);
+                }
+            }
+        }
+        Err(SealClientError::NotEnoughKeys(errors))
+    }
+
+    async fn fetch_from<C: Serialize>(
+        &self,
+        server: &KeyServerConfig,
+        path: &str,
+        request: &SignedRequest<C>,
+    ) -> Result<Vec<(Vec<u8>, ibe::UserSecretKey)>, ServerError> {
+        let error = |status: Option<u16>, error: String| ServerError {
+            url: server.url.clone(),
+            status,
+            error,
+        };
+        let response = self
+            .http
+            .post(format!("{}{}", server.url.trim_end_matches('/'), path))
+            .header("Client-Sdk-Type", "rust")
+            .header("Client-Sdk-Version", CLIENT_SDK_VERSION)
+            .json(&request.body)
+            .send()
+            .await
+            .map_err(|e| error(None, e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(error(Some(status.as_u16()), body));
+        }
+        let response: FetchKeyResponse = response
+            .json()
+            .await
+            .map_err(|e| error(Some(status.as_u16()), e.to_string()))?;
+        request
+            .decrypt_response(response, &server.public_key)
+            .map_err(|e| error(Some(status.as_u16()), e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decrypt;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use crypto::elgamal;
+    use crypto::{create_full_id, seal_encrypt, EncryptionInput, IBEPublicKeys};
+    use fastcrypto::groups::bls12381::G1Element;
+    use rand::thread_rng;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use sui_types::crypto::{get_key_pair_from_rng, SuiKeyPair};
+
+    struct MockKeyServer {
+        master_key: ibe::MasterKey,
+        /// The full ids for which keys are returned.
+        full_ids: Vec<Vec<u8>>,
+    }
+
+    /// Returns the keys for all full ids without checking the request.
+    async fn handle_fetch_key(
+        State(server): State<Arc<MockKeyServer>>,
+        Json(request): Json<Value>,
+    ) -> Json<Value> {
+        let enc_key: elgamal::PublicKey<G1Element> =
+            serde_json::from_value(request["enc_key"].clone()).unwrap();
+        let decryption_keys = server
+            .full_ids
+            .iter()
+            .map(|id| {
+                let key = ibe::extract(&server.master_key, id);
+                json!({ "id": id, "encrypted_key": elgamal::encrypt(&mut thread_rng(), &key, &enc_key) })
+            })
+            .collect::<Vec<_>>();
+        Json(json!({ "decryption_keys": decryption_keys }))
+    }
+
+    async fn start_server(server: MockKeyServer) -> String {
+        let app = Router::new()
+            .route("/v1/fetch_key", post(handle_fetch_key))
+            .with_state(Arc::new(server));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_decrypt() {
+        let package_id = ObjectID::random();
+        let id = vec![1, 2, 3];
+        let full_id = create_full_id(&package_id, &id);
+
+        // The third key server returns keys that do not match its public key
+        let mut key_servers = Vec::new();
+        for i in 0..3 {
+            let (master_key, public_key) = ibe::generate_key_pair(&mut thread_rng());
+            let served_key = if i == 2 {
+                ibe::generate_key_pair(&mut thread_rng()).0
+            } else {
+                master_key
+            };
+            let url = start_server(MockKeyServer {
+                master_key: served_key,
+                full_ids: vec![full_id.clone()],
+            })
+            .await;
+            key_servers.push(KeyServerConfig {
+                object_id: ObjectID::random(),
+                url,
+                public_key,
+            });
+        }
+
+        let public_keys = IBEPublicKeys::BonehFranklinBLS12381(
+            key_servers.iter().map(|s| s.public_key).collect(),
+        );
+        let message = b"Hello, Seal!";
+        let (encrypted_object, _) = seal_encrypt(
+            package_id.into(),
+            id,
+            key_servers.iter().map(|s| s.object_id.into()).collect(),
+            &public_keys,
+            2,
+            EncryptionInput::Aes256Gcm {
+                data: message.to_vec(),
+                aad: None,
+            },
+        )
+        .unwrap();
+
+        let (_, user): (_, fastcrypto::ed25519::Ed25519KeyPair) =
+            get_key_pair_from_rng(&mut thread_rng());
+        let session_key = SessionKey::new(&mut thread_rng(), 10);
+        let certificate = session_key.sui_certificate(&package_id, &SuiKeyPair::Ed25519(user));
+        let ptb = ProgrammableTransaction {
+            inputs: vec![],
+            commands: vec![],
+        };
+
+        let client = SealClient::new(key_servers.clone());
+        let keys = client
+            .fetch_keys_sui(&session_key, certificate.clone(), &ptb, 2)
+            .await
+            .unwrap();
+        assert_eq!(keys.key_servers(&full_id), 2);
+        assert!(!keys
+            .get(&full_id)
+            .unwrap()
+            .contains_key(&key_servers[2].object_id));
+        assert_eq!(
+            decrypt(&encrypted_object, &keys, Some(&public_keys)).unwrap(),
+            message
+        );
+
+        // Only two key servers return valid keys
+        match client
+            .fetch_keys_sui(&session_key, certificate.clone(), &ptb, 3)
+            .await
+        {
+            Err(SealClientError::NotEnoughKeys(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].url, key_servers[2].url);
+            }
+            _ => panic!("Expected NotEnoughKeys"),
+        }
+
+        assert!(matches!(
+            client
+                .fetch_keys_sui(&session_key, certificate, &ptb, 4)
+                .await,
+            Err(SealClientError::InvalidThreshold)
+        ));
+    }
+}
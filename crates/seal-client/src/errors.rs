@@ -0,0 +1,47 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use fastcrypto::error::FastCryptoError;
+use std::fmt::{Display, Formatter};
+
+/// A failed request to a single key server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerError {
+    pub url: String,
+    /// The HTTP status returned by the key server, if any.
+    pub status: Option<u16>,
+    pub error: String,
+}
+
+#[derive(Debug)]
+pub enum SealClientError {
+    /// The threshold is zero or larger than the number of key servers.
+    InvalidThreshold,
+    /// Fewer than `threshold` key servers returned valid keys.
+    NotEnoughKeys(Vec<ServerError>),
+    /// No keys have been fetched for the full id of the encrypted object (hex encoded).
+    MissingKeys(String),
+    /// The user's key failed to sign the certificate.
+    Signing(String),
+    Decryption(FastCryptoError),
+}
+
+impl Display for SealClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SealClientError::InvalidThreshold => write!(f, "Invalid threshold"),
+            SealClientError::NotEnoughKeys(errors) => {
+                write!(f, "Not enough key servers returned valid keys")?;
+                for error in errors {
+                    write!(f, "; {}: {}", error.url, error.error)?;
+                }
+                Ok(())
+            }
+            SealClientError::MissingKeys(full_id) => write!(f, "No keys for id {}", full_id),
+            SealClientError::Signing(error) => write!(f, "Failed to sign certificate: {}", error),
+            SealClientError::Decryption(error) => write!(f, "Failed to decrypt: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SealClientError {}
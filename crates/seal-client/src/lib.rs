@@ -0,0 +1,109 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Client for Seal key servers, for Rust services that need to decrypt Seal encrypted data.
+//!
+//! The flow is the same as in the TypeScript SDK:
+//!  1. Create a [SessionKey] and let the user sign a certificate for it, see [SessionKey::sui_certificate] and
+//!     [SessionKey::ethereum_certificate].
+//!  2. Fetch the keys for the ids approved by a PTB (Sui) or a list of transactions (EVM) from the key servers using
+//!     [SealClient]. The keys are verified against the public keys of the key servers.
+//!  3. Decrypt the encrypted objects with [decrypt].
+
+use crypto::ibe::UserSecretKey;
+use crypto::{
+    seal_decrypt, seal_decrypt_robust, EncryptedObject, IBEPublicKeys, IBEUserSecretKeys,
+};
+use std::collections::HashMap;
+use sui_types::base_types::ObjectID;
+
+pub use client::{KeyServerConfig, SealClient};
+pub use errors::{SealClientError, ServerError};
+pub use request::{FetchKeyRequest, FetchKeyResponse, SignedRequest};
+pub use session::{EthereumCertificate, EthereumMessageFormat, SessionKey, SuiCertificate};
+
+mod client;
+mod errors;
+pub mod messages;
+mod request;
+mod session;
+
+/// User secret keys fetched from key servers, by full id and key server.
+#[derive(Clone, Debug, Default)]
+pub struct FetchedKeys(HashMap<Vec<u8>, HashMap<ObjectID, UserSecretKey>>);
+
+impl FetchedKeys {
+    /// The keys for the given full id, by key server.
+    pub fn get(&self, full_id: &[u8]) -> Option<&HashMap<ObjectID, UserSecretKey>> {
+        self.0.get(full_id)
+    }
+
+    pub fn insert(&mut self, key_server: ObjectID, full_id: Vec<u8>, key: UserSecretKey) {
+        self.0.entry(full_id).or_default().insert(key_server, key);
+    }
+
+    /// Add the keys from another set, e.g., from a later request to the same key servers.
+    pub fn extend(&mut self, other: FetchedKeys) {
+        for (full_id, keys) in other.0 {
+            self.0.entry(full_id).or_default().extend(keys);
+        }
+    }
+
+    /// The keys for the first policy of the encrypted object for which any keys were fetched.
+    fn for_object(
+        &self,
+        encrypted_object: &EncryptedObject,
+    ) -> Result<&HashMap<ObjectID, UserSecretKey>, SealClientError> {
+        let full_ids = encrypted_object
+            .full_ids()
+            .map_err(SealClientError::Decryption)?;
+        full_ids
+            .iter()
+            .find_map(|full_id| self.get(full_id))
+            .ok_or_else(|| SealClientError::MissingKeys(hex::encode(&full_ids[0])))
+    }
+
+    /// The number of key servers that returned a key for the given full id.
+    pub fn key_servers(&self, full_id: &[u8]) -> usize {
+        self.get(full_id).map_or(0, HashMap::len)
+    }
+}
+
+/// Decrypt an encrypted object with the fetched keys. If the object has alternative policies, the keys of the first
+/// policy with fetched keys are used. If the public keys of its key servers are given, all shares are checked for
+/// consistency, see [seal_decrypt].
+pub fn decrypt(
+    encrypted_object: &EncryptedObject,
+    keys: &FetchedKeys,
+    public_keys: Option<&IBEPublicKeys>,
+) -> Result<Vec<u8>, SealClientError> {
+    let keys = keys.for_object(encrypted_object)?;
+    seal_decrypt(encrypted_object, &user_secret_keys(keys), public_keys)
+        .map_err(SealClientError::Decryption)
+}
+
+/// Decrypt an encrypted object like [decrypt], but tolerate invalid keys from some of the key servers, see
+/// [crypto::seal_decrypt_robust]. Returns the plaintext and the key servers that returned invalid keys.
+pub fn decrypt_robust(
+    encrypted_object: &EncryptedObject,
+    keys: &FetchedKeys,
+    public_keys: Option<&IBEPublicKeys>,
+) -> Result<(Vec<u8>, Vec<ObjectID>), SealClientError> {
+    let keys = keys.for_object(encrypted_object)?;
+    let decryption = seal_decrypt_robust(encrypted_object, &user_secret_keys(keys), public_keys)
+        .map_err(SealClientError::Decryption)?;
+    Ok((
+        decryption.plaintext,
+        decryption
+            .faulty_key_servers
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+    ))
+}
+
+fn user_secret_keys(keys: &HashMap<ObjectID, UserSecretKey>) -> IBEUserSecretKeys {
+    IBEUserSecretKeys::BonehFranklinBLS12381(
+        keys.iter().map(|(id, key)| ((*id).into(), *key)).collect(),
+    )
+}
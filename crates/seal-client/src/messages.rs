@@ -0,0 +1,141 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The messages signed by the user and the session key. These must match the messages the key servers verify.
+
+use crate::request::{ElGamalPublicKey, ElGamalVerificationKey};
+use chrono::{DateTime, Utc};
+use ethers::types::Address;
+use fastcrypto::ed25519::Ed25519PublicKey;
+use serde::Serialize;
+use sui_types::base_types::ObjectID;
+use sui_types::transaction::ProgrammableTransaction;
+
+/// The personal message the user signs to authorize a session key for a Sui package.
+/// The package id must be the id of the first version of the package.
+pub fn sui_certificate_message(
+    package_id: &ObjectID,
+    session_vk: &Ed25519PublicKey,
+    creation_time: u64,
+    ttl_min: u16,
+) -> String {
+    certificate_message(
+        &package_id.to_hex_uncompressed(),
+        session_vk,
+        creation_time,
+        ttl_min,
+    )
+}
+
+/// The message the user signs to authorize a session key for an EVM contract.
+pub fn ethereum_certificate_message(
+    contract: &Address,
+    session_vk: &Ed25519PublicKey,
+    creation_time: u64,
+    ttl_min: u16,
+) -> String {
+    certificate_message(
+        &format!("0x{}", hex::encode(contract)),
+        session_vk,
+        creation_time,
+        ttl_min,
+    )
+}
+
+fn certificate_message(
+    namespace: &str,
+    session_vk: &Ed25519PublicKey,
+    creation_time: u64,
+    ttl_min: u16,
+) -> String {
+    format!(
+        "Accessing keys of package {} for {} mins from {}, session key {}",
+        namespace,
+        ttl_min,
+        DateTime::<Utc>::from_timestamp((creation_time / 1000) as i64, 0).expect("valid timestamp"),
+        session_vk,
+    )
+}
+
+#[derive(Serialize)]
+struct RequestFormat {
+    ptb: Vec<u8>,
+    enc_key: Vec<u8>,
+    enc_verification_key: Vec<u8>,
+}
+
+/// The message the session key signs for a fetch key request for Sui.
+pub fn sui_request_message(
+    ptb: &ProgrammableTransaction,
+    enc_key: &ElGamalPublicKey,
+    enc_verification_key: &ElGamalVerificationKey,
+) -> Vec<u8> {
+    request_message(
+        bcs::to_bytes(ptb).expect("should serialize"),
+        enc_key,
+        enc_verification_key,
+    )
+}
+
+/// The message the session key signs for a fetch key request for an EVM chain. The transactions are the JSON encoded
+/// list of transactions sent as the `ptb` of the request.
+pub fn ethereum_request_message(
+    transactions: &[u8],
+    enc_key: &ElGamalPublicKey,
+    enc_verification_key: &ElGamalVerificationKey,
+) -> Vec<u8> {
+    request_message(transactions.to_vec(), enc_key, enc_verification_key)
+}
+
+fn request_message(
+    ptb: Vec<u8>,
+    enc_key: &ElGamalPublicKey,
+    enc_verification_key: &ElGamalVerificationKey,
+) -> Vec<u8> {
+    let request = RequestFormat {
+        ptb,
+        enc_key: bcs::to_bytes(enc_key).expect("should serialize"),
+        enc_verification_key: bcs::to_bytes(enc_verification_key).expect("should serialize"),
+    };
+    bcs::to_bytes(&request).expect("should serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_certificate_messages() {
+        let kp = Ed25519KeyPair::generate(&mut StdRng::from_seed([0; 32]));
+        let creation_time = 1_700_000_000_000;
+
+        let message = sui_certificate_message(
+            &ObjectID::from_single_byte(1),
+            kp.public(),
+            creation_time,
+            10,
+        );
+        assert_eq!(
+            message,
+            format!(
+                "Accessing keys of package 0x{} for 10 mins from 2023-11-14 22:13:20 UTC, session key {}",
+                hex::encode(ObjectID::from_single_byte(1)),
+                kp.public()
+            )
+        );
+
+        let message = ethereum_certificate_message(
+            &Address::from_low_u64_be(1),
+            kp.public(),
+            creation_time,
+            10,
+        );
+        assert!(message.starts_with(
+            "Accessing keys of package 0x0000000000000000000000000000000000000001 for 10 mins"
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::errors::SealClientError;
+use crate::messages::{ethereum_request_message, sui_request_message};
+use crate::session::{EthereumCertificate, SessionKey, SuiCertificate};
+use crypto::elgamal;
+use crypto::ibe;
+use crypto::ibe::UserSecretKey;
+use fastcrypto::ed25519::Ed25519Signature;
+use fastcrypto::encoding::{Base64, Encoding};
+use fastcrypto::groups::bls12381::{G1Element, G2Element};
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+use sui_types::transaction::ProgrammableTransaction;
+
+pub(crate) type ElGamalSecretKey = elgamal::SecretKey<G1Element>;
+pub(crate) type ElGamalPublicKey = elgamal::PublicKey<G1Element>;
+pub(crate) type ElGamalVerificationKey = elgamal::VerificationKey<G2Element>;
+
+/// The body of a request to `/v1/fetch_key` (with a [SuiCertificate]) or `/v1/fetch_key_ethereum` (with an
+/// [EthereumCertificate]).
+#[derive(Serialize)]
+pub struct FetchKeyRequest<C> {
+    /// Base64 encoding of the BCS encoded PTB (Sui) or the JSON encoded transactions (EVM).
+    pub ptb: String,
+    pub enc_key: ElGamalPublicKey,
+    pub enc_verification_key: ElGamalVerificationKey,
+    pub request_signature: Ed25519Signature,
+    pub certificate: C,
+}
+
+#[derive(Deserialize)]
+pub struct DecryptionKey {
+    pub id: Vec<u8>,
+    pub encrypted_key: elgamal::Encryption<G1Element>,
+}
+
+/// The response of a key server to a fetch key request.
+#[derive(Deserialize)]
+pub struct FetchKeyResponse {
+    pub decryption_keys: Vec<DecryptionKey>,
+}
+
+/// A fetch key request signed by a session key, together with the ElGamal secret key the returned keys are encrypted
+/// to. The same request can be sent to all key servers.
+pub struct SignedRequest<C> {
+    pub body: FetchKeyRequest<C>,
+    enc_secret_key: ElGamalSecretKey,
+}
+
+impl SignedRequest<SuiCertificate> {
+    pub fn sui(
+        session_key: &SessionKey,
+        certificate: SuiCertificate,
+        ptb: &ProgrammableTransaction,
+    ) -> Self {
+        let (enc_secret_key, enc_key, enc_verification_key) = elgamal::genkey(&mut thread_rng());
+        let request_signature =
+            session_key.sign(&sui_request_message(ptb, &enc_key, &enc_verification_key));
+        Self {
+            body: FetchKeyRequest {
+                ptb: Base64::encode(bcs::to_bytes(ptb).expect("should serialize")),
+                enc_key,
+                enc_verification_key,
+                request_signature,
+                certificate,
+            },
+            enc_secret_key,
+        }
+    }
+}
+
+impl SignedRequest<EthereumCertificate> {
+    /// Create a request for the keys approved by the given JSON encoded list of transactions.
+    pub fn ethereum(
+        session_key: &SessionKey,
+        certificate: EthereumCertificate,
+        transactions: &[u8],
+    ) -> Self {
+        let (enc_secret_key, enc_key, enc_verification_key) = elgamal::genkey(&mut thread_rng());
+        let request_signature = session_key.sign(&ethereum_request_message(
+            transactions,
+            &enc_key,
+            &enc_verification_key,
+        ));
+        Self {
+            body: FetchKeyRequest {
+                ptb: Base64::encode(transactions),
+                enc_key,
+                enc_verification_key,
+                request_signature,
+                certificate,
+            },
+            enc_secret_key,
+        }
+    }
+}
+
+impl<C> SignedRequest<C> {
+    /// Decrypt the keys in a response from the key server with the given public key, and verify them.
+    /// Returns the full ids and their keys.
+    pub fn decrypt_response(
+        &self,
+        response: FetchKeyResponse,
+        public_key: &ibe::PublicKey,
+    ) -> Result<Vec<(Vec<u8>, UserSecretKey)>, SealClientError> {
+        response
+            .decryption_keys
+            .into_iter()
+            .map(|key| {
+                let user_secret_key = elgamal::decrypt(&self.enc_secret_key, &key.encrypted_key);
+                ibe::verify_user_secret_key(&user_secret_key, &key.id, public_key)
+                    .map_err(SealClientError::Decryption)?;
+                Ok((key.id, user_secret_key))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::errors::SealClientError;
+use crate::messages::{ethereum_certificate_message, sui_certificate_message};
+use ethers::signers::LocalWallet;
+use ethers::types::{Address, Signature, H256};
+use ethers::utils::hash_message;
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::traits::{AllowedRng, KeyPair, Signer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::crypto::{Signature as SuiSignature, SuiKeyPair};
+use sui_types::signature::GenericSignature;
+
+/// The certificate authorizing a session key to fetch keys for a Sui package on behalf of a user.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SuiCertificate {
+    pub user: SuiAddress,
+    pub session_vk: Ed25519PublicKey,
+    pub creation_time: u64,
+    pub ttl_min: u16,
+    pub signature: GenericSignature,
+}
+
+/// The certificate authorizing a session key to fetch keys for an EVM contract on behalf of a user.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EthereumCertificate {
+    pub user: Address,
+    pub session_vk: Ed25519PublicKey,
+    pub creation_time: u64,
+    pub ttl_min: u16,
+    /// Sent as the hex encoding of `r || s || v`.
+    #[serde(
+        serialize_with = "serialize_signature",
+        deserialize_with = "deserialize_signature"
+    )]
+    pub signature: Signature,
+}
+
+fn serialize_signature<S: Serializer>(
+    signature: &Signature,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("0x{}", signature))
+}
+
+fn deserialize_signature<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Signature, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Signature::from_str(&s).map_err(serde::de::Error::custom)
+}
+
+/// How the certificate message is signed by an Ethereum account.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EthereumMessageFormat {
+    /// `personal_sign` as defined in EIP-191.
+    #[default]
+    Eip191,
+}
+
+impl EthereumMessageFormat {
+    fn hash(&self, message: &str) -> H256 {
+        match self {
+            EthereumMessageFormat::Eip191 => hash_message(message),
+        }
+    }
+}
+
+/// A short lived key used to sign requests to the key servers once the user has signed a certificate for it.
+pub struct SessionKey {
+    keypair: Ed25519KeyPair,
+    creation_time: u64,
+    ttl_min: u16,
+}
+
+impl SessionKey {
+    /// Create a new session key which is valid for `ttl_min` minutes from now.
+    /// Key servers reject session keys with a ttl of more than 30 minutes.
+    pub fn new<R: AllowedRng>(rng: &mut R, ttl_min: u16) -> Self {
+        let creation_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is after the epoch")
+            .as_millis() as u64;
+        Self {
+            keypair: Ed25519KeyPair::generate(rng),
+            creation_time,
+            ttl_min,
+        }
+    }
+
+    pub fn public_key(&self) -> &Ed25519PublicKey {
+        self.keypair.public()
+    }
+
+    /// The creation time in milliseconds since the epoch.
+    pub fn creation_time(&self) -> u64 {
+        self.creation_time
+    }
+
+    pub fn ttl_min(&self) -> u16 {
+        self.ttl_min
+    }
+
+    pub fn is_expired(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is after the epoch")
+            .as_millis() as u64;
+        now > self.creation_time + self.ttl_min as u64 * 60 * 1000
+    }
+
+    /// Sign a certificate for the given package with the user's key. The package id must be the id of the first
+    /// version of the package.
+    pub fn sui_certificate(&self, package_id: &ObjectID, user: &SuiKeyPair) -> SuiCertificate {
+        let message = sui_certificate_message(
+            package_id,
+            self.public_key(),
+            self.creation_time,
+            self.ttl_min,
+        );
+        let intent_message = IntentMessage::new(
+            Intent::personal_message(),
+            PersonalMessage {
+                message: message.into_bytes(),
+            },
+        );
+        SuiCertificate {
+            user: SuiAddress::from(&user.public()),
+            session_vk: self.public_key().clone(),
+            creation_time: self.creation_time,
+            ttl_min: self.ttl_min,
+            signature: GenericSignature::Signature(SuiSignature::new_secure(&intent_message, user)),
+        }
+    }
+
+    /// Sign a certificate for the given contract with the user's key.
+    pub fn ethereum_certificate(
+        &self,
+        contract: &Address,
+        user: &LocalWallet,
+        format: EthereumMessageFormat,
+    ) -> Result<EthereumCertificate, SealClientError> {
+        let message = ethereum_certificate_message(
+            contract,
+            self.public_key(),
+            self.creation_time,
+            self.ttl_min,
+        );
+        let signature = user
+            .sign_hash(format.hash(&message))
+            .map_err(|e| SealClientError::Signing(e.to_string()))?;
+        Ok(EthereumCertificate {
+            user: ethers::signers::Signer::address(user),
+            session_vk: self.public_key().clone(),
+            creation_time: self.creation_time,
+            ttl_min: self.ttl_min,
+            signature,
+        })
+    }
+
+    pub(crate) fn sign(&self, message: &[u8]) -> Ed25519Signature {
+        self.keypair.sign(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_ethereum_certificate() {
+        let session_key = SessionKey::new(&mut thread_rng(), 10);
+        assert!(!session_key.is_expired());
+
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let contract = Address::from_low_u64_be(1);
+        let certificate = session_key
+            .ethereum_certificate(&contract, &wallet, EthereumMessageFormat::Eip191)
+            .unwrap();
+
+        // The key servers recover the user's address from the signature on the EIP-191 hash of the message
+        let message = ethereum_certificate_message(
+            &contract,
+            session_key.public_key(),
+            certificate.creation_time,
+            certificate.ttl_min,
+        );
+        assert_eq!(
+            certificate
+                .signature
+                .recover(hash_message(message))
+                .unwrap(),
+            certificate.user
+        );
+
+        // Round trip through the JSON sent to the key servers
+        let json = serde_json::to_string(&certificate).unwrap();
+        let decoded: EthereumCertificate = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.signature, certificate.signature);
+        assert_eq!(decoded.user, certificate.user);
+    }
+}
@@ -131,7 +131,7 @@ export const TextEncryptDecrypt = ({
         const encryptedObject = EncryptedObject.parse(ciphertextBytes);
         console.log("Encrypted object:", {
           id: encryptedObject.id,
-          packageId: encryptedObject.packageId,
+          namespace: encryptedObject.namespace,
           threshold: encryptedObject.threshold,
           services: encryptedObject.services,
           encryptedShares: encryptedObject.encryptedShares,
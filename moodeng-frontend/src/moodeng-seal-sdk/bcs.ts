@@ -23,14 +23,74 @@ export const Ciphertext = bcs.enum('Ciphertext', {
 		mac: bcs.bytes(32),
 	}),
 	Plain: bcs.struct('Plain', {}),
+	Aes256GcmStream: bcs.struct('Aes256GcmStream', {
+		chunkSize: bcs.U32,
+		aad: bcs.option(bcs.vector(bcs.U8)),
+	}),
+	ChaCha20Poly1305: bcs.struct('ChaCha20Poly1305', {
+		blob: bcs.vector(bcs.U8),
+		aad: bcs.option(bcs.vector(bcs.U8)),
+	}),
+	CommittingAes256Gcm: bcs.struct('CommittingAes256Gcm', {
+		blob: bcs.vector(bcs.U8),
+		aad: bcs.option(bcs.vector(bcs.U8)),
+		commitment: bcs.bytes(32),
+	}),
+});
+
+export const Namespace = bcs.enum('Namespace', {
+	SuiPackage: bcs.Address,
+	Evm: bcs.struct('Evm', {
+		chainId: bcs.U64,
+		address: bcs.bytes(20),
+	}),
+	Solana: bcs.struct('Solana', {
+		program: bcs.bytes(32),
+	}),
+});
+
+export const AlternativePolicy = bcs.struct('AlternativePolicy', {
+	namespace: Namespace,
+	id: bcs.vector(bcs.U8),
+	services: bcs.vector(bcs.tuple([bcs.Address, bcs.U8])),
+	threshold: bcs.U8,
+	encryptedShares: IBEEncryptions,
+});
+
+export const RecoveryRecipient = bcs.enum('RecoveryRecipient', {
+	EciesBLS12381: bcs.struct('EciesBLS12381', {
+		publicKey: bcs.bytes(48),
+		ephemeralKey: bcs.bytes(48),
+		encryptedKey: bcs.vector(bcs.U8),
+	}),
+	EciesX25519: bcs.struct('EciesX25519', {
+		publicKey: bcs.bytes(32),
+		ephemeralKey: bcs.bytes(32),
+		encryptedKey: bcs.vector(bcs.U8),
+	}),
+	EciesSecp256k1: bcs.struct('EciesSecp256k1', {
+		publicKey: bcs.bytes(33),
+		ephemeralKey: bcs.bytes(33),
+		encryptedKey: bcs.vector(bcs.U8),
+	}),
+});
+
+/**
+ * The version and namespace of an encrypted object. The variant index is the version of the object.
+ */
+export const VersionedNamespace = bcs.enum('VersionedNamespace', {
+	V0: bcs.Address,
+	V1: Namespace,
+	V2: bcs.tuple([Namespace, bcs.vector(AlternativePolicy)]),
+	V3: bcs.tuple([Namespace, bcs.vector(AlternativePolicy), bcs.vector(RecoveryRecipient)]),
 });
 
 /**
- * The encrypted object format. Should be aligned with the Rust implementation.
+ * The encrypted object format. Should be aligned with the Rust implementation, see the
+ * `typescript_encrypted_object_alignment` test vectors.
  */
 export const EncryptedObject = bcs.struct('EncryptedObject', {
-	version: bcs.U8,
-	packageId: bcs.Address,
+	namespace: VersionedNamespace,
 	id: bcs.vector(bcs.U8).transform({
 		output: (val) => toHex(new Uint8Array(val)),
 		input: (val: string) => fromHex(val),
@@ -30,10 +30,14 @@ export async function decrypt({ encryptedObject, keys }: DecryptOptions): Promis
 	if (!encryptedObject.encryptedShares.BonehFranklinBLS12381) {
 		throw new UnsupportedFeatureError('Encryption mode not supported');
 	}
+	const packageId = encryptedObject.namespace.V0;
+	if (!packageId) {
+		throw new UnsupportedFeatureError('Only version 0 objects are supported');
+	}
 
 	console.log('decrypt.ts - Input:', {
 		encryptedObjectId: encryptedObject.id,
-		encryptedObjectPackageId: encryptedObject.packageId,
+		encryptedObjectPackageId: packageId,
 		encryptedObjectThreshold: encryptedObject.threshold,
 		encryptedObjectServices: encryptedObject.services,
 		keysSize: keys.size,
@@ -43,7 +47,7 @@ export async function decrypt({ encryptedObject, keys }: DecryptOptions): Promis
 		}))
 	});
 
-	const fullId = createFullId(DST, encryptedObject.packageId, encryptedObject.id);
+	const fullId = createFullId(DST, packageId, encryptedObject.id);
 	console.log('decrypt.ts - Full ID:', fullId);
 
 	// Get the indices of the service whose keys are in the keystore.
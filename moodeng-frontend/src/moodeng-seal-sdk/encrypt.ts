@@ -95,8 +95,7 @@ export async function encrypt({
 
 	return {
 		encryptedObject: EncryptedObject.serialize({
-			version: 0,
-			packageId,
+			namespace: { V0: packageId },
 			id,
 			services,
 			threshold,